@@ -0,0 +1,385 @@
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+use crate::encryption;
+use crate::read_setting;
+
+// ============================================
+// COMPRESSED BACKUP ARCHIVES
+// ============================================
+
+/// File extensions recognised as backups, longest first.
+/// The `.enc` variants are passphrase-encrypted exports.
+pub const BACKUP_EXTENSIONS: &[&str] = &[
+    ".db.zst.enc",
+    ".db.gz.enc",
+    ".db.enc",
+    ".db.zst",
+    ".db.gz",
+    ".db",
+];
+
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xB5, 0x2F, 0xFD];
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+
+const DEFAULT_ZSTD_LEVEL: i32 = 3;
+const DEFAULT_GZIP_LEVEL: i32 = 6;
+
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Private folder for unpacked and decrypted backups, set up at startup
+static TEMP_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+    Zstd,
+    Gzip,
+}
+
+impl Compression {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Compression::None => ".db",
+            Compression::Zstd => ".db.zst",
+            Compression::Gzip => ".db.gz",
+        }
+    }
+
+    /// Detects the compression of a backup file from its first bytes
+    pub fn detect(path: &Path) -> Result<Compression, String> {
+        let mut file = fs::File::open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+        let mut header = [0u8; 4];
+        let read = file
+            .read(&mut header)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        Ok(if header[..read].starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else if header[..read].starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else {
+            Compression::None
+        })
+    }
+}
+
+/// Compression settings for new backups, read from the `settings` table
+#[derive(Debug, Clone, Copy)]
+pub struct CompressionSettings {
+    pub compression: Compression,
+    pub level: i32,
+}
+
+impl CompressionSettings {
+    pub fn load(conn: &Connection) -> Result<Self, String> {
+        // Databases created before the settings table existed simply get uncompressed backups
+        let read = |key: &str| read_setting(conn, key).ok().flatten();
+
+        let compression = match read("backup_compression").as_deref() {
+            Some("zstd") => Compression::Zstd,
+            Some("gzip") => Compression::Gzip,
+            _ => Compression::None,
+        };
+
+        let level = read("backup_compression_level")
+            .and_then(|v| v.parse::<i32>().ok())
+            .unwrap_or(match compression {
+                Compression::Gzip => DEFAULT_GZIP_LEVEL,
+                _ => DEFAULT_ZSTD_LEVEL,
+            });
+
+        let level = match compression {
+            Compression::Zstd => level.clamp(1, 19),
+            Compression::Gzip => level.clamp(0, 9),
+            Compression::None => 0,
+        };
+
+        Ok(CompressionSettings { compression, level })
+    }
+}
+
+/// Returns true if the filename has one of the recognised backup extensions
+pub fn is_backup_file(path: &Path) -> bool {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .is_some_and(|name| BACKUP_EXTENSIONS.iter().any(|ext| name.ends_with(ext)))
+}
+
+/// Strips the backup extension from a filename, e.g. `x.db.zst` -> `x`
+pub fn strip_backup_extension(filename: &str) -> Option<&str> {
+    BACKUP_EXTENSIONS
+        .iter()
+        .find_map(|ext| filename.strip_suffix(ext))
+}
+
+/// Compresses `source` into `destination` with the given settings
+pub fn compress_file(
+    source: &Path,
+    destination: &Path,
+    settings: CompressionSettings,
+) -> Result<(), String> {
+    let input = fs::File::open(source)
+        .map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+    let output = fs::File::create(destination)
+        .map_err(|e| format!("Failed to create {}: {}", destination.display(), e))?;
+
+    let mut reader = BufReader::new(input);
+    let mut writer = BufWriter::new(output);
+
+    match settings.compression {
+        Compression::None => {
+            std::io::copy(&mut reader, &mut writer)
+                .map_err(|e| format!("Failed to copy backup: {}", e))?;
+        }
+        Compression::Zstd => {
+            zstd::stream::copy_encode(&mut reader, &mut writer, settings.level)
+                .map_err(|e| format!("Failed to compress backup: {}", e))?;
+        }
+        Compression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(
+                &mut writer,
+                flate2::Compression::new(settings.level as u32),
+            );
+            std::io::copy(&mut reader, &mut encoder)
+                .map_err(|e| format!("Failed to compress backup: {}", e))?;
+            encoder
+                .finish()
+                .map_err(|e| format!("Failed to compress backup: {}", e))?;
+        }
+    }
+
+    writer
+        .flush()
+        .map_err(|e| format!("Failed to write {}: {}", destination.display(), e))
+}
+
+fn decompress_file(
+    source: &Path,
+    destination: &Path,
+    compression: Compression,
+) -> Result<(), String> {
+    let input = fs::File::open(source)
+        .map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+    let output = fs::File::create(destination)
+        .map_err(|e| format!("Failed to create {}: {}", destination.display(), e))?;
+
+    let mut reader = BufReader::new(input);
+    let mut writer = BufWriter::new(output);
+
+    match compression {
+        Compression::None => {
+            std::io::copy(&mut reader, &mut writer)
+                .map_err(|e| format!("Failed to copy backup: {}", e))?;
+        }
+        Compression::Zstd => {
+            zstd::stream::copy_decode(&mut reader, &mut writer)
+                .map_err(|e| format!("Failed to decompress backup: {}", e))?;
+        }
+        Compression::Gzip => {
+            let mut decoder = flate2::read::GzDecoder::new(reader);
+            std::io::copy(&mut decoder, &mut writer)
+                .map_err(|e| format!("Failed to decompress backup: {}", e))?;
+        }
+    }
+
+    writer
+        .flush()
+        .map_err(|e| format!("Failed to write {}: {}", destination.display(), e))
+}
+
+/// A backup available as a plain SQLite file.
+/// Compressed backups are unpacked to a temporary file that is removed on drop.
+pub struct MaterializedBackup {
+    path: PathBuf,
+    is_temporary: bool,
+}
+
+impl MaterializedBackup {
+    /// Creates an empty temporary file, readable only by the current user, for a plain
+    /// database. It holds decrypted data, so it lives in the app's private temp folder.
+    pub fn temporary(label: &str) -> Result<Self, String> {
+        let path = temp_db_path(label)?;
+        create_private_file(&path)
+            .map_err(|e| format!("Failed to create temporary file: {}", e))?;
+
+        Ok(MaterializedBackup {
+            path,
+            is_temporary: true,
+        })
+    }
+
+    /// Path of the plain SQLite database
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Overwrites the temporary file with a copy of `source`, keeping its permissions
+    /// (`fs::copy` would take over the permissions of `source`)
+    pub fn copy_from(&self, source: &Path) -> Result<(), String> {
+        let mut input = fs::File::open(source)
+            .map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+        let mut output = fs::OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .open(&self.path)
+            .map_err(|e| format!("Failed to open temporary file: {}", e))?;
+
+        std::io::copy(&mut input, &mut output)
+            .map(|_| ())
+            .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))
+    }
+}
+
+impl Drop for MaterializedBackup {
+    fn drop(&mut self) {
+        if self.is_temporary {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Sets up `tmp` in the app config folder as the private temp folder and removes
+/// files left there by a previous run that did not exit cleanly
+pub fn init_temp_dir(app_config_dir: &Path) -> Result<(), String> {
+    let dir = app_config_dir.join("tmp");
+    create_private_dir(&dir)?;
+
+    if let Ok(entries) = fs::read_dir(&dir) {
+        for entry in entries.flatten() {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+
+    let _ = TEMP_DIR.set(dir);
+    Ok(())
+}
+
+/// The private temp folder. Before `init_temp_dir` (only in tests) a folder private to
+/// this process in the system temp directory is used instead.
+fn private_temp_dir() -> Result<PathBuf, String> {
+    if let Some(dir) = TEMP_DIR.get() {
+        return Ok(dir.clone());
+    }
+
+    let dir = std::env::temp_dir().join(format!("motormods_{}", std::process::id()));
+    create_private_dir(&dir)?;
+    Ok(dir)
+}
+
+fn create_private_dir(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+            .map_err(|e| format!("Failed to restrict {}: {}", dir.display(), e))?;
+    }
+
+    Ok(())
+}
+
+/// Creates a new empty file readable only by the current user
+pub fn create_private_file(path: &Path) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options.open(path).map(|_| ())
+}
+
+/// Returns a unique path in the private temp folder for an unpacked database
+fn temp_db_path(label: &str) -> Result<PathBuf, String> {
+    let n = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    Ok(private_temp_dir()?.join(format!(
+        "motormods_{}_{}_{}_{}.db",
+        label,
+        std::process::id(),
+        chrono::Local::now().format("%Y%m%d%H%M%S%f"),
+        n
+    )))
+}
+
+/// Makes a backup readable by SQLite, decrypting and decompressing it first if needed.
+/// `passphrase` is only used for encrypted exports and may be `None` otherwise.
+pub fn materialize(
+    backup_path: &Path,
+    passphrase: Option<&str>,
+) -> Result<MaterializedBackup, String> {
+    let decrypted = if encryption::is_encrypted(backup_path)? {
+        let passphrase = passphrase.filter(|p| !p.is_empty()).ok_or_else(|| {
+            "This backup is encrypted. A passphrase is required to open it.".to_string()
+        })?;
+
+        let decrypted = MaterializedBackup::temporary("decrypted")?;
+        encryption::decrypt_file(backup_path, decrypted.path(), passphrase)?;
+        Some(decrypted)
+    } else {
+        None
+    };
+
+    let source = decrypted.as_ref().map_or(backup_path, |d| d.path());
+    let compression = Compression::detect(source)?;
+
+    if compression == Compression::None {
+        return Ok(decrypted.unwrap_or_else(|| MaterializedBackup {
+            path: backup_path.to_path_buf(),
+            is_temporary: false,
+        }));
+    }
+
+    let unpacked = MaterializedBackup::temporary("unpacked")?;
+    decompress_file(source, unpacked.path(), compression)?;
+
+    Ok(unpacked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MaterializedBackup;
+    use std::fs;
+
+    #[test]
+    fn temporary_files_are_removed_on_drop() {
+        let temp = MaterializedBackup::temporary("test").unwrap();
+        let path = temp.path().to_path_buf();
+        assert!(path.exists());
+        drop(temp);
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn temporary_files_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = MaterializedBackup::temporary("test").unwrap();
+        let mode = |p: &std::path::Path| fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(temp.path()), 0o600);
+        assert_eq!(mode(temp.path().parent().unwrap()), 0o700);
+
+        // A copy keeps the temporary file's permissions, not the source's
+        let source = temp.path().with_extension("source");
+        fs::write(&source, b"data").unwrap();
+        fs::set_permissions(&source, fs::Permissions::from_mode(0o644)).unwrap();
+        temp.copy_from(&source).unwrap();
+        assert_eq!(fs::read(temp.path()).unwrap(), b"data");
+        assert_eq!(mode(temp.path()), 0o600);
+        fs::remove_file(source).unwrap();
+    }
+}
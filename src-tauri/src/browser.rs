@@ -0,0 +1,423 @@
+use rusqlite::types::ValueRef;
+use rusqlite::{params_from_iter, Connection, OpenFlags, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::archive::MaterializedBackup;
+use crate::{incremental, schema};
+
+// ============================================
+// READ-ONLY BACKUP BROWSER
+// ============================================
+//
+// Lets staff look up invoices, products and returns inside a backup without
+// restoring it. The backup is unpacked (and upgraded, if older) once and kept
+// until another backup is opened or `close_backup_browser` is called, so paging
+// through results does not unpack it again. It is always opened read-only.
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 500;
+
+/// A row as a JSON object keyed by column name, matching the frontend's record types
+pub type Record = Map<String, Value>;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BrowseFilter {
+    /// Matched against ids, names, phone numbers, SKUs and barcodes
+    pub search: Option<String>,
+    /// Inclusive `YYYY-MM-DD` bounds on the creation or return date
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+    /// Products only
+    pub category: Option<String>,
+    /// 1-based
+    pub page: Option<usize>,
+    pub page_size: Option<usize>,
+}
+
+impl BrowseFilter {
+    /// Page number and size as served: at least page 1, between 1 and `MAX_PAGE_SIZE` rows
+    fn page_and_size(&self) -> (usize, usize) {
+        (
+            self.page.unwrap_or(1).max(1),
+            self.page_size
+                .unwrap_or(DEFAULT_PAGE_SIZE)
+                .clamp(1, MAX_PAGE_SIZE),
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BrowsePage {
+    pub rows: Vec<Record>,
+    pub total: i64,
+    pub page: usize,
+    pub page_size: usize,
+}
+
+/// The backup currently open in the browser
+struct OpenBackup {
+    source: PathBuf,
+    modified: Option<SystemTime>,
+    unpacked: MaterializedBackup,
+    upgraded: Option<MaterializedBackup>,
+}
+
+impl OpenBackup {
+    fn path(&self) -> &Path {
+        self.upgraded
+            .as_ref()
+            .map_or(self.unpacked.path(), |u| u.path())
+    }
+}
+
+static OPEN_BACKUP: Mutex<Option<OpenBackup>> = Mutex::new(None);
+
+/// Runs `f` on a read-only connection to the backup, unpacking it first unless it is already open
+fn with_backup<R>(
+    backup_path: &str,
+    passphrase: Option<&str>,
+    f: impl FnOnce(&Connection) -> Result<R, String>,
+) -> Result<R, String> {
+    let source = Path::new(backup_path);
+    if !source.is_file() {
+        return Err(format!("Backup file not found: {}", backup_path));
+    }
+    let modified = source.metadata().and_then(|m| m.modified()).ok();
+
+    let mut open = OPEN_BACKUP
+        .lock()
+        .map_err(|_| "Backup browser is unavailable".to_string())?;
+
+    let reusable = open
+        .as_ref()
+        .is_some_and(|o| o.source == source && o.modified == modified);
+    if !reusable {
+        // Release the previous backup's temporary files before unpacking the next one
+        *open = None;
+
+        let unpacked = incremental::materialize_complete(source, passphrase)?;
+        let upgraded = schema::upgraded_copy(unpacked.path())?;
+        *open = Some(OpenBackup {
+            source: source.to_path_buf(),
+            modified,
+            unpacked,
+            upgraded,
+        });
+    }
+
+    let path = open.as_ref().map(|o| o.path()).unwrap_or(source);
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open backup database: {}", e))?;
+
+    f(&conn)
+}
+
+fn to_record(row: &Row) -> rusqlite::Result<Record> {
+    let mut record = Map::new();
+    let columns = row.as_ref().column_names();
+
+    for (i, name) in columns.iter().enumerate() {
+        let value = match row.get_ref(i)? {
+            ValueRef::Null => Value::Null,
+            ValueRef::Integer(n) => Value::from(n),
+            ValueRef::Real(n) => Value::from(n),
+            ValueRef::Text(t) => Value::from(String::from_utf8_lossy(t).to_string()),
+            ValueRef::Blob(_) => Value::Null,
+        };
+        record.insert(name.to_string(), value);
+    }
+
+    Ok(record)
+}
+
+fn query_records(conn: &Connection, sql: &str, args: &[String]) -> Result<Vec<Record>, String> {
+    let mut stmt = conn
+        .prepare(sql)
+        .map_err(|e| format!("Failed to query backup: {}", e))?;
+
+    let records = stmt
+        .query_map(params_from_iter(args), to_record)
+        .map_err(|e| format!("Failed to query backup: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read backup rows: {}", e))?;
+
+    Ok(records)
+}
+
+fn has_table(conn: &Connection, table: &str) -> Result<bool, String> {
+    Ok(!incremental::table_columns(conn, "main", table)?.is_empty())
+}
+
+/// WHERE clause and its arguments for a filter
+struct Conditions {
+    clauses: Vec<String>,
+    args: Vec<String>,
+}
+
+impl Conditions {
+    fn new() -> Self {
+        Conditions {
+            clauses: Vec::new(),
+            args: Vec::new(),
+        }
+    }
+
+    /// Adds `sql`, with every `?` bound to `value`
+    fn add(&mut self, sql: &str, value: &str) {
+        let mut clause = String::new();
+        for (i, part) in sql.split('?').enumerate() {
+            if i > 0 {
+                self.args.push(value.to_string());
+                clause.push_str(&format!("?{}", self.args.len()));
+            }
+            clause.push_str(part);
+        }
+        self.clauses.push(clause);
+    }
+
+    fn search(&mut self, filter: &BrowseFilter, sql: &str) {
+        if let Some(search) = filter.search.as_deref().map(str::trim) {
+            if !search.is_empty() {
+                self.add(sql, &format!("%{}%", search));
+            }
+        }
+    }
+
+    fn dates(&mut self, filter: &BrowseFilter, column: &str) {
+        if let Some(from) = filter.date_from.as_deref().filter(|d| !d.is_empty()) {
+            self.add(&format!("date({}) >= date(?)", column), from);
+        }
+        if let Some(to) = filter.date_to.as_deref().filter(|d| !d.is_empty()) {
+            self.add(&format!("date({}) <= date(?)", column), to);
+        }
+    }
+
+    fn where_sql(&self) -> String {
+        if self.clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", self.clauses.join(" AND "))
+        }
+    }
+}
+
+/// Runs a filtered, paginated query. `from` is everything after `SELECT ...` up to the WHERE clause.
+fn page(
+    conn: &Connection,
+    select: &str,
+    from: &str,
+    conditions: &Conditions,
+    order_by: &str,
+    filter: &BrowseFilter,
+) -> Result<BrowsePage, String> {
+    let (page, page_size) = filter.page_and_size();
+    // SQLite offsets are signed 64-bit; a page far past the end just comes back empty
+    let offset = (page - 1).saturating_mul(page_size).min(i64::MAX as usize);
+    let where_sql = conditions.where_sql();
+
+    let total: i64 = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM {} {}", from, where_sql),
+            params_from_iter(&conditions.args),
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to count backup rows: {}", e))?;
+
+    let rows = query_records(
+        conn,
+        &format!(
+            "SELECT {} FROM {} {} ORDER BY {} LIMIT {} OFFSET {}",
+            select, from, where_sql, order_by, page_size, offset
+        ),
+        &conditions.args,
+    )?;
+
+    Ok(BrowsePage {
+        rows,
+        total,
+        page,
+        page_size,
+    })
+}
+
+fn empty_page(filter: &BrowseFilter) -> BrowsePage {
+    let (page, page_size) = filter.page_and_size();
+    BrowsePage {
+        rows: Vec::new(),
+        total: 0,
+        page,
+        page_size,
+    }
+}
+
+/// Attaches each record's line items, with product names, under `items`
+fn attach_items(
+    conn: &Connection,
+    records: &mut [Record],
+    items_table: &str,
+    parent_column: &str,
+) -> Result<(), String> {
+    if !has_table(conn, items_table)? {
+        return Ok(());
+    }
+
+    let product_name = if has_table(conn, "products")? {
+        "(SELECT p.name FROM products p WHERE p.id = i.product_id) AS product_name"
+    } else {
+        "NULL AS product_name"
+    };
+    let sql = format!(
+        "SELECT i.*, {} FROM {} i WHERE i.{} = ?1 ORDER BY i.rowid",
+        product_name, items_table, parent_column
+    );
+
+    for record in records.iter_mut() {
+        let Some(id) = record.get("id").and_then(Value::as_str).map(str::to_string) else {
+            continue;
+        };
+        let items = query_records(conn, &sql, &[id])?;
+        record.insert(
+            "items".to_string(),
+            Value::Array(items.into_iter().map(Value::Object).collect()),
+        );
+    }
+
+    Ok(())
+}
+
+/// Lists invoices in a backup, newest first, each with its `items`
+#[tauri::command]
+pub async fn browse_backup_invoices(
+    backup_path: String,
+    filter: Option<BrowseFilter>,
+    passphrase: Option<String>,
+) -> Result<BrowsePage, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let filter = filter.unwrap_or_default();
+
+        with_backup(&backup_path, passphrase.as_deref(), |conn| {
+            if !has_table(conn, "invoices")? {
+                return Ok(empty_page(&filter));
+            }
+
+            let mut conditions = Conditions::new();
+            conditions.search(
+                &filter,
+                "(id LIKE ? OR customer_name LIKE ? OR customer_phone LIKE ?)",
+            );
+            conditions.dates(&filter, "created_at");
+
+            let mut result = page(
+                conn,
+                "*",
+                "invoices",
+                &conditions,
+                "created_at DESC",
+                &filter,
+            )?;
+            attach_items(conn, &mut result.rows, "invoice_items", "invoice_id")?;
+            Ok(result)
+        })
+    })
+    .await
+    .map_err(|e| format!("Failed to browse backup invoices: {}", e))?
+}
+
+/// Lists products in a backup by name
+#[tauri::command]
+pub async fn browse_backup_products(
+    backup_path: String,
+    filter: Option<BrowseFilter>,
+    passphrase: Option<String>,
+) -> Result<BrowsePage, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let filter = filter.unwrap_or_default();
+
+        with_backup(&backup_path, passphrase.as_deref(), |conn| {
+            if !has_table(conn, "products")? {
+                return Ok(empty_page(&filter));
+            }
+
+            let mut conditions = Conditions::new();
+            conditions.search(
+                &filter,
+                "(id LIKE ? OR name LIKE ? OR sku LIKE ? OR barcode LIKE ?)",
+            );
+            if let Some(category) = filter.category.as_deref().filter(|c| !c.is_empty()) {
+                conditions.add("category = ?", category);
+            }
+
+            page(
+                conn,
+                "*",
+                "products",
+                &conditions,
+                "name COLLATE NOCASE",
+                &filter,
+            )
+        })
+    })
+    .await
+    .map_err(|e| format!("Failed to browse backup products: {}", e))?
+}
+
+/// Lists sales returns in a backup, newest first, each with its `items` and the customer name
+#[tauri::command]
+pub async fn browse_backup_returns(
+    backup_path: String,
+    filter: Option<BrowseFilter>,
+    passphrase: Option<String>,
+) -> Result<BrowsePage, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let filter = filter.unwrap_or_default();
+
+        with_backup(&backup_path, passphrase.as_deref(), |conn| {
+            if !has_table(conn, "sales_returns")? {
+                return Ok(empty_page(&filter));
+            }
+
+            let (select, from, search) = if has_table(conn, "invoices")? {
+                (
+                    "r.*, i.customer_name, i.total_amount AS original_invoice_total",
+                    "sales_returns r LEFT JOIN invoices i ON i.id = r.invoice_id",
+                    "(r.id LIKE ? OR r.return_no LIKE ? OR r.invoice_id LIKE ? OR i.customer_name LIKE ?)",
+                )
+            } else {
+                (
+                    "r.*",
+                    "sales_returns r",
+                    "(r.id LIKE ? OR r.return_no LIKE ? OR r.invoice_id LIKE ?)",
+                )
+            };
+
+            let mut conditions = Conditions::new();
+            conditions.search(&filter, search);
+            conditions.dates(&filter, "r.return_date");
+
+            let mut result = page(
+                conn,
+                select,
+                from,
+                &conditions,
+                "r.return_date DESC, r.created_at DESC",
+                &filter,
+            )?;
+            attach_items(conn, &mut result.rows, "return_items", "return_id")?;
+            Ok(result)
+        })
+    })
+    .await
+    .map_err(|e| format!("Failed to browse backup returns: {}", e))?
+}
+
+/// Releases the backup kept open by the browser and its temporary files
+#[tauri::command]
+pub fn close_backup_browser() {
+    if let Ok(mut open) = OPEN_BACKUP.lock() {
+        *open = None;
+    }
+}
@@ -0,0 +1,259 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+// ============================================
+// ENCRYPTED BACKUP CONTAINER
+// ============================================
+//
+// Layout (all integers little-endian):
+//   magic "MMBAKENC" | version u8 | argon2 m_cost u32 | t_cost u32 | p_cost u32
+//   | salt [16] | nonce [24] | XChaCha20-Poly1305 ciphertext + tag
+//
+// The whole header is authenticated as associated data, so tampering with the
+// key derivation parameters is detected the same way as a wrong passphrase.
+
+const MAGIC: &[u8; 8] = b"MMBAKENC";
+const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 12 + SALT_LEN + NONCE_LEN;
+
+/// Argon2id parameters for new files (19 MiB, 2 passes, 1 lane)
+const M_COST_KIB: u32 = 19 * 1024;
+const T_COST: u32 = 2;
+const P_COST: u32 = 1;
+
+/// Upper bounds on the parameters accepted from a file header, so a crafted file
+/// cannot make key derivation run for minutes or exhaust memory
+const MAX_M_COST_KIB: u32 = 256 * 1024;
+const MAX_T_COST: u32 = 16;
+const MAX_P_COST: u32 = 16;
+
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+) -> Result<[u8; 32], String> {
+    let params = Params::new(m_cost, t_cost, p_cost, Some(32))
+        .map_err(|e| format!("Invalid key derivation parameters: {}", e))?;
+
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive encryption key: {}", e))?;
+
+    Ok(key)
+}
+
+/// Returns true if the file starts with the encrypted backup header
+pub fn is_encrypted(path: &Path) -> Result<bool, String> {
+    let mut file =
+        fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+    let mut magic = [0u8; 8];
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == MAGIC),
+        // Shorter than the magic, so certainly not encrypted
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Encrypts `source` into `destination` with a key derived from `passphrase`
+pub fn encrypt_file(source: &Path, destination: &Path, passphrase: &str) -> Result<(), String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(format!(
+            "Passphrase must be at least {} characters",
+            MIN_PASSPHRASE_LEN
+        ));
+    }
+
+    let plaintext =
+        fs::read(source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(MAGIC);
+    header.push(FORMAT_VERSION);
+    header.extend_from_slice(&M_COST_KIB.to_le_bytes());
+    header.extend_from_slice(&T_COST.to_le_bytes());
+    header.extend_from_slice(&P_COST.to_le_bytes());
+    header.extend_from_slice(&salt);
+    header.extend_from_slice(&nonce);
+
+    let key = derive_key(passphrase, &salt, M_COST_KIB, T_COST, P_COST)?;
+    let cipher = XChaCha20Poly1305::new(&key.into());
+
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: &plaintext,
+                aad: &header,
+            },
+        )
+        .map_err(|_| "Failed to encrypt backup".to_string())?;
+
+    let mut output = header;
+    output.extend_from_slice(&ciphertext);
+
+    fs::write(destination, output).map_err(|e| {
+        let _ = fs::remove_file(destination);
+        format!("Failed to write encrypted backup: {}", e)
+    })
+}
+
+/// Decrypts an encrypted backup into `destination`.
+/// A wrong passphrase and a damaged file are indistinguishable and produce the same error.
+pub fn decrypt_file(source: &Path, destination: &Path, passphrase: &str) -> Result<(), String> {
+    let data =
+        fs::read(source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+
+    if data.len() < HEADER_LEN || &data[..MAGIC.len()] != MAGIC {
+        return Err("Not an encrypted MotorMods backup".to_string());
+    }
+
+    let (header, ciphertext) = data.split_at(HEADER_LEN);
+
+    let version = header[MAGIC.len()];
+    if version != FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported encrypted backup version {}. Please update the app.",
+            version
+        ));
+    }
+
+    let read_u32 = |offset: usize| {
+        u32::from_le_bytes([
+            header[offset],
+            header[offset + 1],
+            header[offset + 2],
+            header[offset + 3],
+        ])
+    };
+    let params_at = MAGIC.len() + 1;
+    let m_cost = read_u32(params_at);
+    let t_cost = read_u32(params_at + 4);
+    let p_cost = read_u32(params_at + 8);
+
+    if m_cost > MAX_M_COST_KIB || t_cost > MAX_T_COST || p_cost > MAX_P_COST {
+        return Err("Encrypted backup header is damaged".to_string());
+    }
+
+    let salt_at = params_at + 12;
+    let salt = &header[salt_at..salt_at + SALT_LEN];
+    let nonce = XNonce::from_slice(&header[salt_at + SALT_LEN..HEADER_LEN]);
+
+    let key = derive_key(passphrase, salt, m_cost, t_cost, p_cost)?;
+    let cipher = XChaCha20Poly1305::new(&key.into());
+
+    let plaintext = cipher
+        .decrypt(
+            nonce,
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| "Incorrect passphrase, or the encrypted backup is damaged".to_string())?;
+
+    fs::write(destination, plaintext)
+        .map_err(|e| format!("Failed to write decrypted backup: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decrypt_file, encrypt_file, is_encrypted, HEADER_LEN, MAGIC};
+    use crate::test_support::temp_dir;
+    use std::fs;
+    use std::path::PathBuf;
+
+    const PASSPHRASE: &str = "correct horse battery";
+
+    /// A folder holding `plain.db` and its encrypted copy `plain.db.enc`
+    fn setup() -> (PathBuf, Vec<u8>) {
+        let dir = temp_dir("encryption");
+
+        let plaintext: Vec<u8> = (0..10_000u32).flat_map(|i| i.to_le_bytes()).collect();
+        fs::write(dir.join("plain.db"), &plaintext).unwrap();
+        encrypt_file(&dir.join("plain.db"), &dir.join("plain.db.enc"), PASSPHRASE).unwrap();
+        (dir, plaintext)
+    }
+
+    /// Flips one bit of the encrypted file and checks decryption refuses it
+    fn assert_tamper_detected(offset: usize) {
+        let (dir, _) = setup();
+        let encrypted = dir.join("plain.db.enc");
+        let mut data = fs::read(&encrypted).unwrap();
+        data[offset] ^= 0x01;
+        fs::write(&encrypted, data).unwrap();
+
+        let result = decrypt_file(&encrypted, &dir.join("out.db"), PASSPHRASE);
+        assert!(result.is_err(), "accepted a change at byte {}", offset);
+        assert!(!dir.join("out.db").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn round_trip_restores_the_plaintext() {
+        let (dir, plaintext) = setup();
+        let encrypted = dir.join("plain.db.enc");
+
+        assert!(is_encrypted(&encrypted).unwrap());
+        assert!(!is_encrypted(&dir.join("plain.db")).unwrap());
+        assert_ne!(fs::read(&encrypted).unwrap()[HEADER_LEN..], plaintext[..]);
+
+        decrypt_file(&encrypted, &dir.join("out.db"), PASSPHRASE).unwrap();
+        assert_eq!(fs::read(dir.join("out.db")).unwrap(), plaintext);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn wrong_passphrase_is_rejected() {
+        let (dir, _) = setup();
+        let err = decrypt_file(
+            &dir.join("plain.db.enc"),
+            &dir.join("out.db"),
+            "wrong horse battery",
+        )
+        .unwrap_err();
+        assert!(err.contains("Incorrect passphrase"), "{}", err);
+        assert!(!dir.join("out.db").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn short_passphrase_is_refused() {
+        let (dir, _) = setup();
+        assert!(encrypt_file(&dir.join("plain.db"), &dir.join("short.enc"), "short").is_err());
+        assert!(!dir.join("short.enc").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn tampered_header_is_rejected() {
+        // Argon2 time cost, salt and nonce
+        let params_at = MAGIC.len() + 1;
+        for offset in [params_at + 4, params_at + 12, HEADER_LEN - 1] {
+            assert_tamper_detected(offset);
+        }
+    }
+
+    #[test]
+    fn tampered_ciphertext_is_rejected() {
+        assert_tamper_detected(HEADER_LEN);
+        assert_tamper_detected(HEADER_LEN + 20_000);
+    }
+}
@@ -0,0 +1,834 @@
+use chrono::{DateTime, Duration as ChronoDuration, Local};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::archive::{self, CompressionSettings, MaterializedBackup};
+use crate::manifest::{self, BackupType};
+use crate::{read_setting, BackupResult};
+
+// ============================================
+// INCREMENTAL BACKUPS
+// ============================================
+//
+// Changes made by the frontend go through tauri-plugin-sql, so they are captured
+// with triggers that append the key of every touched row to `backup_change_log`.
+// An incremental backup stores the current version of each row logged since the
+// previous backup in the chain, plus the keys of rows that no longer exist.
+// Restoring replays the full base backup and then each increment in order.
+
+pub const INCREMENTAL_PREFIX: &str = "motormods_incr_";
+
+/// Tables captured by incremental backups, with the column that identifies a row.
+/// Settings are saved with INSERT OR REPLACE, which changes their id, so they are keyed by name.
+const TRACKED_TABLES: &[(&str, &str)] = &[
+    ("products", "id"),
+    ("invoices", "id"),
+    ("invoice_items", "id"),
+    ("settings", "key"),
+    ("stock_adjustments", "id"),
+    ("sales_returns", "id"),
+    ("return_items", "id"),
+    ("users", "id"),
+];
+
+const DEFAULT_FULL_INTERVAL_DAYS: i64 = 7;
+
+/// What the next backup should be
+pub enum NextBackup {
+    /// A full backup. `start_seq` is set in incremental mode, where it starts a new chain.
+    Full { start_seq: Option<i64> },
+    Incremental {
+        base_file: String,
+        parent_file: String,
+        from_seq: i64,
+    },
+}
+
+/// Chain position stored in the live database
+struct ChainState {
+    base_file: String,
+    last_file: String,
+    last_seq: i64,
+    base_created_at: String,
+}
+
+/// Metadata stored inside each incremental backup file
+#[derive(Debug, Clone)]
+pub struct IncrementMeta {
+    pub base_file: String,
+    pub parent_file: String,
+    pub from_seq: i64,
+    pub to_seq: i64,
+}
+
+fn table_exists(conn: &Connection, schema: &str, table: &str) -> Result<bool, String> {
+    conn.query_row(
+        &format!(
+            "SELECT COUNT(*) > 0 FROM {}.sqlite_master WHERE type='table' AND name=?1",
+            schema
+        ),
+        params![table],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to check table existence: {}", e))
+}
+
+fn trigger_name(table: &str, op: &str) -> String {
+    format!("backup_track_{}_{}", table, op)
+}
+
+/// Tracked tables present in the live database; older databases may lack some
+fn existing_tracked_tables(conn: &Connection) -> Result<Vec<(&'static str, &'static str)>, String> {
+    let mut tables = Vec::new();
+    for (table, key) in TRACKED_TABLES {
+        if table_exists(conn, "main", table)? {
+            tables.push((*table, *key));
+        }
+    }
+    Ok(tables)
+}
+
+/// True when the change log exists and every tracked table still has its triggers.
+/// Rebuilding a table (as the products migration does) drops its triggers, and any
+/// changes made before they are reinstalled would be missed.
+fn tracking_installed(conn: &Connection) -> Result<bool, String> {
+    if !table_exists(conn, "main", "backup_change_log")?
+        || !table_exists(conn, "main", "backup_chain")?
+    {
+        return Ok(false);
+    }
+
+    for (table, _) in existing_tracked_tables(conn)? {
+        for op in ["insert", "update", "delete"] {
+            let exists: bool = conn
+                .query_row(
+                    "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type='trigger' AND name=?1",
+                    params![trigger_name(table, op)],
+                    |row| row.get(0),
+                )
+                .map_err(|e| format!("Failed to check trigger: {}", e))?;
+            if !exists {
+                return Ok(false);
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+fn install_tracking(conn: &Connection) -> Result<(), String> {
+    let mut sql = String::from(
+        "CREATE TABLE IF NOT EXISTS backup_change_log (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            table_name TEXT NOT NULL,
+            row_key TEXT NOT NULL,
+            op TEXT NOT NULL,
+            changed_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE TABLE IF NOT EXISTS backup_chain (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            base_file TEXT NOT NULL,
+            last_file TEXT NOT NULL,
+            last_seq INTEGER NOT NULL,
+            base_created_at TEXT NOT NULL
+        );",
+    );
+
+    for (table, key) in existing_tracked_tables(conn)? {
+        sql.push_str(&format!(
+            "
+            CREATE TRIGGER IF NOT EXISTS {insert} AFTER INSERT ON {table} BEGIN
+                INSERT INTO backup_change_log (table_name, row_key, op) VALUES ('{table}', NEW.{key}, 'insert');
+            END;
+            CREATE TRIGGER IF NOT EXISTS {update} AFTER UPDATE ON {table} BEGIN
+                INSERT INTO backup_change_log (table_name, row_key, op) VALUES ('{table}', NEW.{key}, 'update');
+                INSERT INTO backup_change_log (table_name, row_key, op)
+                    SELECT '{table}', OLD.{key}, 'delete' WHERE OLD.{key} IS NOT NEW.{key};
+            END;
+            CREATE TRIGGER IF NOT EXISTS {delete} AFTER DELETE ON {table} BEGIN
+                INSERT INTO backup_change_log (table_name, row_key, op) VALUES ('{table}', OLD.{key}, 'delete');
+            END;",
+            table = table,
+            key = key,
+            insert = trigger_name(table, "insert"),
+            update = trigger_name(table, "update"),
+            delete = trigger_name(table, "delete"),
+        ));
+    }
+
+    conn.execute_batch(&sql)
+        .map_err(|e| format!("Failed to install change tracking: {}", e))
+}
+
+/// Removes the triggers and change log when incremental mode is switched off,
+/// so everyday writes do not keep paying for them
+fn uninstall_tracking(conn: &Connection) -> Result<(), String> {
+    if !table_exists(conn, "main", "backup_change_log")? {
+        return Ok(());
+    }
+
+    let mut sql = String::new();
+    for (table, _) in TRACKED_TABLES {
+        for op in ["insert", "update", "delete"] {
+            sql.push_str(&format!("DROP TRIGGER IF EXISTS {};", trigger_name(table, op)));
+        }
+    }
+    sql.push_str("DROP TABLE IF EXISTS backup_change_log; DROP TABLE IF EXISTS backup_chain;");
+
+    conn.execute_batch(&sql)
+        .map_err(|e| format!("Failed to remove change tracking: {}", e))
+}
+
+/// Last sequence number handed out by the change log. Read from `sqlite_sequence`
+/// rather than MAX(seq) so it never goes backwards once old entries are pruned.
+fn current_seq(conn: &Connection) -> Result<i64, String> {
+    conn.query_row(
+        "SELECT COALESCE((SELECT seq FROM main.sqlite_sequence WHERE name = 'backup_change_log'), 0)",
+        [],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to read change log: {}", e))
+}
+
+fn load_chain(conn: &Connection) -> Result<Option<ChainState>, String> {
+    conn.query_row(
+        "SELECT base_file, last_file, last_seq, base_created_at FROM backup_chain WHERE id = 1",
+        [],
+        |row| {
+            Ok(ChainState {
+                base_file: row.get(0)?,
+                last_file: row.get(1)?,
+                last_seq: row.get(2)?,
+                base_created_at: row.get(3)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| format!("Failed to read backup chain: {}", e))
+}
+
+/// Forgets the current chain so the next backup is a full one.
+/// Called after a restore or import, when the live data no longer follows the chain.
+pub fn reset_chain(conn: &Connection) -> Result<(), String> {
+    if !table_exists(conn, "main", "backup_chain")? {
+        return Ok(());
+    }
+
+    conn.execute_batch("DELETE FROM backup_chain; DELETE FROM backup_change_log;")
+        .map_err(|e| format!("Failed to reset backup chain: {}", e))
+}
+
+/// Decides whether the next backup can be an increment on the current chain.
+/// Falls back to a full backup whenever the chain cannot be trusted.
+pub fn plan_next_backup(
+    conn: &Connection,
+    backups_dir: &Path,
+    force_full: bool,
+) -> Result<NextBackup, String> {
+    // Databases created before the settings table existed only get full backups
+    let read = |key: &str| read_setting(conn, key).ok().flatten();
+
+    if read("backup_mode").as_deref() != Some("incremental") {
+        uninstall_tracking(conn)?;
+        return Ok(NextBackup::Full { start_seq: None });
+    }
+
+    if !tracking_installed(conn)? {
+        install_tracking(conn)?;
+        reset_chain(conn)?;
+        return Ok(NextBackup::Full {
+            start_seq: Some(current_seq(conn)?),
+        });
+    }
+
+    let full_backup = || -> Result<NextBackup, String> {
+        Ok(NextBackup::Full {
+            start_seq: Some(current_seq(conn)?),
+        })
+    };
+
+    let Some(chain) = load_chain(conn)? else {
+        return full_backup();
+    };
+
+    if force_full || !chain_intact(backups_dir, &chain) {
+        return full_backup();
+    }
+
+    let interval_days = read("backup_full_interval_days")
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_FULL_INTERVAL_DAYS);
+
+    let base_expired = DateTime::parse_from_rfc3339(&chain.base_created_at)
+        .map(|created| Local::now() - created.with_timezone(&Local) >= ChronoDuration::days(interval_days))
+        .unwrap_or(true);
+
+    if base_expired {
+        return full_backup();
+    }
+
+    Ok(NextBackup::Incremental {
+        base_file: chain.base_file,
+        parent_file: chain.last_file,
+        from_seq: chain.last_seq,
+    })
+}
+
+/// Follows the manifests from the newest link back to the base, checking every file is still there
+fn chain_intact(backups_dir: &Path, chain: &ChainState) -> bool {
+    let mut current = chain.last_file.clone();
+    let mut visited = HashSet::new();
+
+    while current != chain.base_file {
+        let path = backups_dir.join(&current);
+        if !visited.insert(current.clone()) || !path.is_file() {
+            return false;
+        }
+        match manifest::read_manifest(&path).and_then(|m| m.parent_file) {
+            Some(parent) => current = parent,
+            None => return false,
+        }
+    }
+
+    backups_dir.join(&chain.base_file).is_file()
+}
+
+/// Starts a new chain on a freshly written full backup
+pub fn record_full(conn: &Connection, filename: &str, start_seq: i64) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO backup_chain (id, base_file, last_file, last_seq, base_created_at)
+         VALUES (1, ?1, ?1, ?2, ?3)",
+        params![filename, start_seq, Local::now().to_rfc3339()],
+    )
+    .map_err(|e| format!("Failed to record backup chain: {}", e))?;
+
+    // Changes up to the full backup are already in it
+    conn.execute(
+        "DELETE FROM backup_change_log WHERE seq <= ?1",
+        params![start_seq],
+    )
+    .map_err(|e| format!("Failed to prune change log: {}", e))?;
+
+    Ok(())
+}
+
+fn advance_chain(conn: &Connection, filename: &str, to_seq: i64) -> Result<(), String> {
+    conn.execute(
+        "UPDATE backup_chain SET last_file = ?1, last_seq = ?2 WHERE id = 1",
+        params![filename, to_seq],
+    )
+    .map_err(|e| format!("Failed to record backup chain: {}", e))?;
+
+    conn.execute(
+        "DELETE FROM backup_change_log WHERE seq <= ?1",
+        params![to_seq],
+    )
+    .map_err(|e| format!("Failed to prune change log: {}", e))?;
+
+    Ok(())
+}
+
+/// Copies the rows changed in `(from_seq, to_seq]` into the attached `incr` database.
+/// Runs in one read transaction so the increment is a consistent snapshot.
+fn write_increment(
+    conn: &Connection,
+    base_file: &str,
+    parent_file: &str,
+    from_seq: i64,
+) -> Result<i64, String> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let to_seq = current_seq(&tx)?;
+
+    tx.execute_batch(
+        "CREATE TABLE incr.incremental_meta (key TEXT PRIMARY KEY, value TEXT);
+         CREATE TABLE incr.deleted_rows (table_name TEXT NOT NULL, row_key TEXT NOT NULL);",
+    )
+    .map_err(|e| format!("Failed to create incremental backup: {}", e))?;
+
+    let meta = [
+        ("base_file", base_file.to_string()),
+        ("parent_file", parent_file.to_string()),
+        ("from_seq", from_seq.to_string()),
+        ("to_seq", to_seq.to_string()),
+        ("created_at", Local::now().to_rfc3339()),
+    ];
+    for (key, value) in meta {
+        tx.execute(
+            "INSERT INTO incr.incremental_meta (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )
+        .map_err(|e| format!("Failed to write incremental metadata: {}", e))?;
+    }
+
+    for (table, key) in existing_tracked_tables(&tx)? {
+        tx.execute_batch(&format!(
+            "CREATE TABLE incr.{table} AS SELECT * FROM main.{table} WHERE 0",
+            table = table
+        ))
+        .map_err(|e| format!("Failed to create {} in incremental backup: {}", table, e))?;
+
+        tx.execute(
+            &format!(
+                "INSERT INTO incr.{table} SELECT * FROM main.{table} WHERE {key} IN (
+                    SELECT row_key FROM main.backup_change_log
+                    WHERE table_name = ?1 AND seq > ?2 AND seq <= ?3
+                )",
+                table = table,
+                key = key
+            ),
+            params![table, from_seq, to_seq],
+        )
+        .map_err(|e| format!("Failed to copy changed rows from {}: {}", table, e))?;
+
+        tx.execute(
+            &format!(
+                "INSERT INTO incr.deleted_rows (table_name, row_key)
+                 SELECT DISTINCT table_name, row_key FROM main.backup_change_log AS l
+                 WHERE table_name = ?1 AND seq > ?2 AND seq <= ?3
+                   AND NOT EXISTS (SELECT 1 FROM main.{table} WHERE {key} = l.row_key)",
+                table = table,
+                key = key
+            ),
+            params![table, from_seq, to_seq],
+        )
+        .map_err(|e| format!("Failed to record deleted rows from {}: {}", table, e))?;
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to finish incremental backup: {}", e))?;
+
+    Ok(to_seq)
+}
+
+/// Writes an incremental backup holding the changes since `parent_file`
+#[allow(clippy::too_many_arguments)]
+pub fn create_increment(
+    conn: &Connection,
+    backups_dir: &Path,
+    compression: CompressionSettings,
+    base_file: &str,
+    parent_file: &str,
+    from_seq: i64,
+    backup_type: BackupType,
+    created_by: Option<&str>,
+    app_version: &str,
+) -> Result<BackupResult, String> {
+    let timestamp = Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+    let backup_filename = format!(
+        "{}{}{}",
+        INCREMENTAL_PREFIX,
+        timestamp,
+        compression.compression.extension()
+    );
+    let backup_path = backups_dir.join(&backup_filename);
+
+    if backup_path.exists() {
+        return Err(format!("Backup file already exists: {}", backup_filename));
+    }
+
+    let staging = MaterializedBackup::temporary("incremental")?;
+
+    conn.execute(
+        "ATTACH DATABASE ?1 AS incr",
+        params![staging.path().to_string_lossy()],
+    )
+    .map_err(|e| format!("Failed to create incremental backup: {}", e))?;
+
+    let written = write_increment(conn, base_file, parent_file, from_seq);
+    let _ = conn.execute("DETACH DATABASE incr", []);
+    let to_seq = written?;
+
+    if let Err(e) = archive::compress_file(staging.path(), &backup_path, compression) {
+        let _ = fs::remove_file(&backup_path);
+        return Err(e);
+    }
+
+    let manifest = manifest::create_manifest_from(
+        &backup_path,
+        staging.path(),
+        backup_type,
+        created_by,
+        app_version,
+    )?;
+
+    advance_chain(conn, &backup_filename, to_seq)?;
+
+    Ok(BackupResult {
+        filename: backup_filename,
+        path: backup_path.to_string_lossy().to_string(),
+        file_size: manifest.file_size,
+        created_at: Local::now().to_rfc3339(),
+        manifest,
+    })
+}
+
+/// Reads the chain metadata of an incremental backup, or `None` for a full backup
+pub fn read_increment_meta(conn: &Connection) -> Result<Option<IncrementMeta>, String> {
+    if !table_exists(conn, "main", "incremental_meta")? {
+        return Ok(None);
+    }
+
+    let value = |key: &str| -> Result<String, String> {
+        conn.query_row(
+            "SELECT value FROM incremental_meta WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Incremental backup is missing {}: {}", key, e))
+    };
+    let number = |key: &str| -> Result<i64, String> {
+        value(key)?
+            .parse::<i64>()
+            .map_err(|e| format!("Incremental backup has an invalid {}: {}", key, e))
+    };
+
+    Ok(Some(IncrementMeta {
+        base_file: value("base_file")?,
+        parent_file: value("parent_file")?,
+        from_seq: number("from_seq")?,
+        to_seq: number("to_seq")?,
+    }))
+}
+
+fn read_meta_from_file(database_path: &Path) -> Result<Option<IncrementMeta>, String> {
+    let conn = Connection::open_with_flags(database_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open backup database: {}", e))?;
+    read_increment_meta(&conn)
+}
+
+/// Refuses to restore an increment on its own, since it only holds the changed rows
+pub fn ensure_standalone(database_path: &Path) -> Result<(), String> {
+    if read_meta_from_file(database_path)?.is_some() {
+        return Err(
+            "This is an incremental backup. Restore it with its chain so the full backup \
+             and earlier increments are applied too."
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Column names of `schema.table`, empty if the table does not exist
+pub fn table_columns(conn: &Connection, schema: &str, table: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA {}.table_info({})", schema, table))
+        .map_err(|e| format!("Failed to get table info: {}", e))?;
+
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))
+        .map_err(|e| format!("Failed to query columns: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(columns)
+}
+
+/// Applies one increment to the database open on `conn`
+fn apply_increment(conn: &Connection, increment_path: &Path) -> Result<(), String> {
+    conn.execute(
+        "ATTACH DATABASE ?1 AS incr",
+        params![increment_path.to_string_lossy()],
+    )
+    .map_err(|e| format!("Failed to open incremental backup: {}", e))?;
+
+    let applied = (|| -> Result<(), String> {
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        for (table, key) in TRACKED_TABLES {
+            if !table_exists(&tx, "main", table)? || !table_exists(&tx, "incr", table)? {
+                continue;
+            }
+
+            tx.execute(
+                &format!(
+                    "DELETE FROM main.{table} WHERE {key} IN (
+                        SELECT row_key FROM incr.deleted_rows WHERE table_name = ?1
+                    )",
+                    table = table,
+                    key = key
+                ),
+                params![table],
+            )
+            .map_err(|e| format!("Failed to apply deletions to {}: {}", table, e))?;
+
+            // Only columns both sides know about, in case the schema changed mid-chain
+            let target: HashSet<String> = table_columns(&tx, "main", table)?.into_iter().collect();
+            let columns: Vec<String> = table_columns(&tx, "incr", table)?
+                .into_iter()
+                .filter(|c| target.contains(c))
+                .collect();
+            if columns.is_empty() {
+                continue;
+            }
+
+            let columns = columns.join(", ");
+            tx.execute_batch(&format!(
+                "INSERT OR REPLACE INTO main.{table} ({columns}) SELECT {columns} FROM incr.{table}",
+                table = table,
+                columns = columns
+            ))
+            .map_err(|e| format!("Failed to apply changes to {}: {}", table, e))?;
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Failed to apply incremental backup: {}", e))
+    })();
+
+    let _ = conn.execute("DETACH DATABASE incr", []);
+    applied
+}
+
+/// Rebuilds the database as of `target_filename` by replaying its full base backup
+/// and every increment up to it. The result is a temporary file removed on drop.
+pub fn replay_chain(backups_dir: &Path, target_filename: &str) -> Result<MaterializedBackup, String> {
+    let mut increments: Vec<(IncrementMeta, MaterializedBackup)> = Vec::new();
+    let mut visited = HashSet::new();
+    let mut current = target_filename.to_string();
+
+    // Walk back from the target to the full backup the chain starts from
+    let base = loop {
+        if !visited.insert(current.clone()) {
+            return Err(format!("Backup chain loops back to {}", current));
+        }
+
+        // Parent names come from the backups themselves, so they are confined like any other
+        let path = crate::resolve_backup_file(backups_dir, &current)
+            .map_err(|e| format!("Backup chain is broken: {}", e))?;
+
+        let unpacked = archive::materialize(&path, None)?;
+        match read_meta_from_file(unpacked.path())? {
+            None => break unpacked,
+            Some(meta) => {
+                if let Some((child, _)) = increments.last() {
+                    if child.from_seq != meta.to_seq || child.base_file != meta.base_file {
+                        return Err(format!(
+                            "Backup chain is broken between {} and its parent",
+                            current
+                        ));
+                    }
+                }
+                current = meta.parent_file.clone();
+                increments.push((meta, unpacked));
+            }
+        }
+    };
+
+    if let Some((meta, _)) = increments.first() {
+        if meta.base_file != current {
+            return Err(format!(
+                "Backup chain is broken: expected base {}, found {}",
+                meta.base_file, current
+            ));
+        }
+    }
+
+    let replayed = MaterializedBackup::temporary("replayed")?;
+    replayed.copy_from(base.path())?;
+    drop(base);
+
+    let conn = Connection::open(replayed.path())
+        .map_err(|e| format!("Failed to open base backup: {}", e))?;
+
+    // Rows arrive table by table, so parents and children are briefly out of step
+    conn.execute("PRAGMA foreign_keys = OFF", [])
+        .map_err(|e| format!("Failed to disable foreign keys: {}", e))?;
+
+    for (meta, unpacked) in increments.iter().rev() {
+        apply_increment(&conn, unpacked.path()).map_err(|e| {
+            format!(
+                "Failed to replay increment (changes {}..{}): {}",
+                meta.from_seq, meta.to_seq, e
+            )
+        })?;
+    }
+
+    drop(conn);
+
+    Ok(replayed)
+}
+
+/// Makes any backup readable as a complete database. An increment is replayed
+/// with the rest of its chain, which must be in the same directory.
+pub fn materialize_complete(
+    backup_path: &Path,
+    passphrase: Option<&str>,
+) -> Result<MaterializedBackup, String> {
+    let unpacked = archive::materialize(backup_path, passphrase)?;
+    if read_meta_from_file(unpacked.path())?.is_none() {
+        return Ok(unpacked);
+    }
+    drop(unpacked);
+
+    let filename = backup_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| "Invalid backup path".to_string())?;
+    let backups_dir = backup_path
+        .parent()
+        .ok_or_else(|| "Invalid backup path".to_string())?;
+
+    replay_chain(backups_dir, &filename)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{materialize_complete, replay_chain, INCREMENTAL_PREFIX};
+    use crate::create_backup_file;
+    use crate::manifest::BackupType;
+    use crate::test_support::migrated_db;
+    use rusqlite::types::Value;
+    use rusqlite::Connection;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::thread;
+    use std::time::Duration;
+
+    /// A live database in incremental, zstd-compressed mode and an empty backups folder
+    fn setup() -> (PathBuf, PathBuf, Connection) {
+        let (db_path, conn) = migrated_db("incremental");
+        let root = db_path.parent().unwrap().to_path_buf();
+        fs::create_dir_all(root.join("backups")).unwrap();
+        conn.execute_batch(
+            "UPDATE settings SET value = 'incremental' WHERE key = 'backup_mode';
+             UPDATE settings SET value = 'zstd' WHERE key = 'backup_compression';
+             INSERT INTO products (id, name, price, quantity) VALUES
+                ('p1', 'Chain', 500, 10), ('p2', 'Spark plug', 90, 40), ('p3', 'Mirror', 250, 6);",
+        )
+        .unwrap();
+
+        (root, db_path, conn)
+    }
+
+    /// Takes a backup and returns its filename. Backup names have a one-second
+    /// resolution, so each backup after the first waits for the next second.
+    fn backup(db_path: &Path, backups_dir: &Path, first: bool) -> String {
+        if !first {
+            thread::sleep(Duration::from_millis(1100));
+        }
+        create_backup_file(
+            db_path,
+            backups_dir,
+            BackupType::Manual,
+            None,
+            "test",
+            false,
+            None,
+        )
+        .unwrap()
+        .filename
+    }
+
+    fn rows(conn: &Connection, sql: &str) -> Vec<String> {
+        let mut stmt = conn.prepare(sql).unwrap();
+        let columns = stmt.column_count();
+        stmt.query_map([], |row| {
+            Ok((0..columns)
+                .map(|i| format!("{:?}", row.get::<_, Value>(i).unwrap()))
+                .collect::<Vec<_>>()
+                .join("|"))
+        })
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect()
+    }
+
+    fn snapshot(conn: &Connection) -> Vec<Vec<String>> {
+        [
+            "SELECT * FROM products ORDER BY id",
+            "SELECT * FROM invoices ORDER BY id",
+            "SELECT * FROM invoice_items ORDER BY id",
+            "SELECT key, value FROM settings ORDER BY key",
+        ]
+        .iter()
+        .map(|sql| rows(conn, sql))
+        .collect()
+    }
+
+    #[test]
+    fn full_and_two_increments_replay_to_the_live_data() {
+        let (root, db_path, conn) = setup();
+        let backups_dir = root.join("backups");
+
+        let full = backup(&db_path, &backups_dir, true);
+        assert!(full.starts_with("motormods_backup_"));
+
+        conn.execute_batch(
+            "INSERT INTO invoices (id, customer_name, total_amount) VALUES ('i1', 'Ravi', 590);
+             INSERT INTO invoice_items (id, invoice_id, product_id, quantity, price)
+                VALUES ('ii1', 'i1', 'p1', 1, 500), ('ii2', 'i1', 'p2', 1, 90);
+             UPDATE products SET quantity = quantity - 1 WHERE id IN ('p1', 'p2');
+             DELETE FROM products WHERE id = 'p3';",
+        )
+        .unwrap();
+        let first = backup(&db_path, &backups_dir, false);
+        assert!(first.starts_with(INCREMENTAL_PREFIX));
+
+        conn.execute_batch(
+            "UPDATE products SET price = 520, name = 'Chain kit' WHERE id = 'p1';
+             DELETE FROM invoice_items WHERE id = 'ii2';
+             UPDATE invoices SET total_amount = 500 WHERE id = 'i1';
+             INSERT INTO products (id, name, price, quantity) VALUES ('p4', 'Brake pad', 300, 12);
+             UPDATE settings SET value = '14' WHERE key = 'backup_keep_daily';",
+        )
+        .unwrap();
+        let second = backup(&db_path, &backups_dir, false);
+        assert!(second.starts_with(INCREMENTAL_PREFIX));
+
+        let replayed = replay_chain(&backups_dir, &second).unwrap();
+        let restored = Connection::open(replayed.path()).unwrap();
+        assert_eq!(snapshot(&restored), snapshot(&conn));
+        assert_eq!(
+            rows(&restored, "SELECT id FROM products ORDER BY id"),
+            ["Text(\"p1\")", "Text(\"p2\")", "Text(\"p4\")"]
+        );
+        drop(restored);
+
+        // The middle of the chain replays to the data as it was then
+        let middle = materialize_complete(&backups_dir.join(&first), None).unwrap();
+        let restored = Connection::open(middle.path()).unwrap();
+        assert_eq!(
+            rows(&restored, "SELECT id, quantity FROM products ORDER BY id"),
+            ["Text(\"p1\")|Integer(9)", "Text(\"p2\")|Integer(39)"]
+        );
+        assert_eq!(rows(&restored, "SELECT id FROM invoice_items").len(), 2);
+        drop(restored);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn missing_parent_breaks_the_chain() {
+        let (root, db_path, conn) = setup();
+        let backups_dir = root.join("backups");
+
+        let full = backup(&db_path, &backups_dir, true);
+        conn.execute("UPDATE products SET quantity = 1 WHERE id = 'p1'", [])
+            .unwrap();
+        let first = backup(&db_path, &backups_dir, false);
+        conn.execute("UPDATE products SET quantity = 2 WHERE id = 'p1'", [])
+            .unwrap();
+        let second = backup(&db_path, &backups_dir, false);
+
+        fs::remove_file(backups_dir.join(&first)).unwrap();
+        let err = replay_chain(&backups_dir, &second).err().unwrap();
+        assert!(err.contains("Backup chain is broken"), "{}", err);
+        let err = materialize_complete(&backups_dir.join(&second), None)
+            .err()
+            .unwrap();
+        assert!(err.contains("Backup chain is broken"), "{}", err);
+
+        // Without its base, even the first increment cannot be replayed
+        fs::remove_file(backups_dir.join(&full)).unwrap();
+        fs::rename(backups_dir.join(&second), backups_dir.join(&first)).unwrap();
+        assert!(replay_chain(&backups_dir, &first).is_err());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
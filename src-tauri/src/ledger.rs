@@ -0,0 +1,322 @@
+use rusqlite::{Transaction, TransactionBehavior};
+use serde::Serialize;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::get_db_path;
+use crate::sales::{now_rfc3339, open_for_write, record_adjustment};
+
+// ============================================
+// STOCK LEDGER RECONCILIATION
+// ============================================
+//
+// `stock_adjustments` is meant to explain every unit in `products.quantity`, but the
+// product screens change the quantity directly and log the adjustment separately, if
+// at all, so the two drift. The expected stock of a product is its opening stock
+// plus every other adjustment; a product whose `quantity` differs is a discrepancy.
+// Repairing posts one `other` adjustment per discrepancy for the difference, so the
+// ledger sums to the stock on hand again and later drift shows up on its own.
+// `products.quantity` is never changed here: it is the counted stock the shop sells from.
+
+/// A product whose recorded stock does not match its ledger
+#[derive(Debug, Clone, Serialize)]
+pub struct StockDiscrepancy {
+    pub product_id: String,
+    pub product_name: String,
+    pub sku: Option<String>,
+    /// Sum of `opening_stock` adjustments
+    pub opening_stock: i64,
+    /// Sum of every other adjustment
+    pub movements: i64,
+    /// `opening_stock + movements`
+    pub expected: i64,
+    /// `products.quantity`
+    pub recorded: i64,
+    /// `recorded - expected`; the quantity a correcting adjustment posts
+    pub difference: i64,
+    /// False if the product never had an opening stock entry, the usual cause of a
+    /// discrepancy for products added without one
+    pub has_opening_stock: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StockReconciliation {
+    /// True if correcting adjustments were posted
+    pub applied: bool,
+    pub message: String,
+    pub products_checked: usize,
+    /// Discrepancies found, before any correction
+    pub discrepancies: Vec<StockDiscrepancy>,
+    pub adjustments_posted: usize,
+}
+
+/// Compares every product's stock with its ledger
+fn find_discrepancies(tx: &Transaction) -> Result<(usize, Vec<StockDiscrepancy>), String> {
+    let mut stmt = tx
+        .prepare(
+            "SELECT p.id, p.name, p.sku, p.quantity,
+                    COALESCE(SUM(CASE WHEN sa.adjustment_type = 'opening_stock' THEN sa.quantity END), 0),
+                    COALESCE(SUM(CASE WHEN sa.adjustment_type != 'opening_stock' THEN sa.quantity END), 0),
+                    COUNT(CASE WHEN sa.adjustment_type = 'opening_stock' THEN 1 END)
+             FROM products p
+             LEFT JOIN stock_adjustments sa ON sa.product_id = p.id
+             GROUP BY p.id
+             ORDER BY p.name COLLATE NOCASE, p.id",
+        )
+        .map_err(|e| format!("Failed to read stock ledger: {}", e))?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            let opening_stock: i64 = row.get(4)?;
+            let movements: i64 = row.get(5)?;
+            let recorded: i64 = row.get(3)?;
+            let expected = opening_stock + movements;
+            Ok(StockDiscrepancy {
+                product_id: row.get(0)?,
+                product_name: row.get(1)?,
+                sku: row.get(2)?,
+                opening_stock,
+                movements,
+                expected,
+                recorded,
+                difference: recorded - expected,
+                has_opening_stock: row.get::<_, i64>(6)? > 0,
+            })
+        })
+        .map_err(|e| format!("Failed to read stock ledger: {}", e))?;
+
+    let mut checked = 0;
+    let mut discrepancies = Vec::new();
+    for row in rows {
+        let product = row.map_err(|e| format!("Failed to read stock ledger: {}", e))?;
+        checked += 1;
+        if product.difference != 0 {
+            discrepancies.push(product);
+        }
+    }
+
+    Ok((checked, discrepancies))
+}
+
+/// Posts an `other` adjustment for each discrepancy so the ledger matches the stock
+fn post_corrections(
+    tx: &Transaction,
+    discrepancies: &[StockDiscrepancy],
+    created_by: &str,
+) -> Result<usize, String> {
+    let now = now_rfc3339();
+
+    for d in discrepancies {
+        let notes = format!(
+            "Stock reconciliation: ledger showed {}, recorded stock {} ({:+})",
+            d.expected, d.recorded, d.difference
+        );
+        record_adjustment(
+            tx,
+            &d.product_id,
+            "other",
+            d.difference,
+            &notes,
+            None,
+            created_by,
+            &now,
+        )?;
+    }
+
+    // The corrections must leave no product off its ledger
+    let remaining: i64 = tx
+        .query_row(
+            "SELECT COUNT(*) FROM products p
+             WHERE p.quantity != (SELECT COALESCE(SUM(quantity), 0) FROM stock_adjustments WHERE product_id = p.id)",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to check stock ledger: {}", e))?;
+    if remaining != 0 {
+        return Err(format!(
+            "Stock ledger still disagrees for {} product(s) after reconciling",
+            remaining
+        ));
+    }
+
+    Ok(discrepancies.len())
+}
+
+fn reconcile_stock_in(
+    db_path: &Path,
+    apply: bool,
+    created_by: &str,
+) -> Result<StockReconciliation, String> {
+    let mut conn = open_for_write(db_path)?;
+    // A repair takes the write lock up front so no sale lands between reading and posting
+    let behavior = if apply {
+        TransactionBehavior::Immediate
+    } else {
+        TransactionBehavior::Deferred
+    };
+    let tx = conn
+        .transaction_with_behavior(behavior)
+        .map_err(|e| format!("Failed to start stock reconciliation: {}", e))?;
+
+    let (products_checked, discrepancies) = find_discrepancies(&tx)?;
+
+    if discrepancies.is_empty() {
+        return Ok(StockReconciliation {
+            applied: false,
+            message: format!(
+                "Stock ledger matches recorded stock for all {} product(s)",
+                products_checked
+            ),
+            products_checked,
+            discrepancies,
+            adjustments_posted: 0,
+        });
+    }
+
+    if !apply {
+        return Ok(StockReconciliation {
+            applied: false,
+            message: format!(
+                "{} of {} product(s) differ from the stock ledger",
+                discrepancies.len(),
+                products_checked
+            ),
+            products_checked,
+            discrepancies,
+            adjustments_posted: 0,
+        });
+    }
+
+    let adjustments_posted = post_corrections(&tx, &discrepancies, created_by)?;
+    tx.commit()
+        .map_err(|e| format!("Failed to save stock reconciliation: {}", e))?;
+
+    Ok(StockReconciliation {
+        applied: true,
+        message: format!(
+            "Posted {} correcting adjustment(s); the stock ledger now matches recorded stock",
+            adjustments_posted
+        ),
+        products_checked,
+        discrepancies,
+        adjustments_posted,
+    })
+}
+
+/// Lists products whose stock differs from opening stock plus all stock adjustments.
+/// With `apply`, also posts a correcting `other` adjustment for each of them, noting
+/// the old ledger total, so the ledger sums to the recorded stock.
+#[tauri::command]
+pub async fn reconcile_stock(
+    app: AppHandle,
+    apply: Option<bool>,
+    created_by: Option<String>,
+) -> Result<StockReconciliation, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let created_by = created_by.as_deref().unwrap_or("system");
+        reconcile_stock_in(&get_db_path(&app)?, apply.unwrap_or(false), created_by)
+    })
+    .await
+    .map_err(|e| format!("Failed to reconcile stock: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::reconcile_stock_in;
+    use crate::test_support::migrated_db;
+    use rusqlite::Connection;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Brake pads: opening stock 5 and one sale, but 5 recorded. Oil filters: 3 recorded
+    /// and no ledger at all. Bulbs: opening stock 2, 2 recorded.
+    fn setup() -> (PathBuf, Connection) {
+        let (db_path, conn) = migrated_db("ledger");
+        conn.execute_batch(
+            "INSERT INTO products (id, name, price, quantity) VALUES ('pad', 'Brake pad', 450, 5);
+             INSERT INTO products (id, name, price, quantity) VALUES ('filter', 'Oil filter', 200, 3);
+             INSERT INTO products (id, name, price, quantity) VALUES ('bulb', 'Head bulb', 150, 2);
+             INSERT INTO stock_adjustments (id, product_id, adjustment_type, quantity)
+                 VALUES ('a1', 'pad', 'opening_stock', 5), ('a2', 'pad', 'sale', -1),
+                        ('a3', 'bulb', 'opening_stock', 2);",
+        )
+        .unwrap();
+
+        (db_path, conn)
+    }
+
+    fn quantities(conn: &Connection) -> Vec<(String, i64, i64)> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT p.id, p.quantity,
+                        (SELECT COALESCE(SUM(quantity), 0) FROM stock_adjustments WHERE product_id = p.id)
+                 FROM products p ORDER BY p.id",
+            )
+            .unwrap();
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap();
+        rows.collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn discrepancies_are_listed_without_changes() {
+        let (db_path, conn) = setup();
+        let before = quantities(&conn);
+
+        let report = reconcile_stock_in(&db_path, false, "tester").unwrap();
+        assert!(!report.applied);
+        assert_eq!(report.products_checked, 3);
+        assert_eq!(report.adjustments_posted, 0);
+
+        let found: Vec<(&str, i64, i64, bool)> = report
+            .discrepancies
+            .iter()
+            .map(|d| {
+                (
+                    d.product_id.as_str(),
+                    d.expected,
+                    d.difference,
+                    d.has_opening_stock,
+                )
+            })
+            .collect();
+        assert_eq!(found, vec![("pad", 4, 1, true), ("filter", 0, 3, false)]);
+        assert_eq!(quantities(&conn), before);
+
+        fs::remove_dir_all(db_path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn applying_makes_the_ledger_match_recorded_stock() {
+        let (db_path, conn) = setup();
+
+        let report = reconcile_stock_in(&db_path, true, "tester").unwrap();
+        assert!(report.applied);
+        assert_eq!(report.adjustments_posted, 2);
+
+        // Recorded stock is kept; the ledger now sums to it
+        assert_eq!(
+            quantities(&conn),
+            vec![
+                ("bulb".to_string(), 2, 2),
+                ("filter".to_string(), 3, 3),
+                ("pad".to_string(), 5, 5),
+            ]
+        );
+        let posted: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM stock_adjustments WHERE adjustment_type = 'other' AND created_by = 'tester'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(posted, 2);
+
+        let again = reconcile_stock_in(&db_path, true, "tester").unwrap();
+        assert!(!again.applied);
+        assert!(again.discrepancies.is_empty());
+
+        fs::remove_dir_all(db_path.parent().unwrap()).unwrap();
+    }
+}
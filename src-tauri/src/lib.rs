@@ -1,10 +1,39 @@
 use chrono::Local;
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, params};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 use std::process::Command;
-use tauri::{AppHandle, Manager};
+use tauri::{AppHandle, Emitter, Manager};
+
+mod archive;
+mod browser;
+mod encryption;
+mod incremental;
+mod ledger;
+mod manifest;
+mod merge;
+mod migrations;
+mod mirror;
+mod offsite;
+mod operations;
+mod portable;
+mod preview;
+mod retention;
+mod returns;
+mod sales;
+mod scheduler;
+mod schema;
+mod selective;
+mod verify;
+
+#[cfg(test)]
+mod test_support;
+
+use archive::{Compression, CompressionSettings, MaterializedBackup};
+use incremental::NextBackup;
+use manifest::{BackupManifest, BackupType};
+use operations::{Operation, OperationKind};
 
 // ============================================
 // BACKUP/RESTORE TYPES
@@ -16,6 +45,7 @@ pub struct BackupResult {
     pub path: String,
     pub file_size: u64,
     pub created_at: String,
+    pub manifest: BackupManifest,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,6 +54,8 @@ pub struct BackupFileInfo {
     pub path: String,
     pub file_size: u64,
     pub modified_at: String,
+    /// None if the file could not be read as a database
+    pub manifest: Option<BackupManifest>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -32,6 +64,33 @@ pub struct RestoreResult {
     pub message: String,
     pub records_imported: usize,
     pub safety_backup: String,
+    pub tables: Vec<TableRestoreResult>,
+    /// First errors encountered, if the restore failed
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableRestoreResult {
+    pub table: String,
+    /// False if the backup has no such table; the live rows are then kept
+    pub in_backup: bool,
+    pub imported: usize,
+    pub failed: usize,
+    /// Rows not attempted because the whole table could not be written
+    pub skipped: usize,
+}
+
+/// Limit on the error messages returned from a failed restore
+const MAX_RESTORE_ERRORS: usize = 10;
+
+/// Emitted after the live database file was overwritten, so the frontend reconnects
+const DATABASE_REPLACED_EVENT: &str = "database-replaced";
+
+#[derive(Debug, Clone, Serialize)]
+struct DatabaseReplaced {
+    /// Backup the database was replaced with
+    source: String,
+    safety_backup: String,
 }
 
 // Tables to restore in order (respecting foreign key dependencies)
@@ -68,13 +127,345 @@ fn get_backups_dir(app: &AppHandle) -> Result<PathBuf, String> {
     Ok(backups_dir)
 }
 
+/// Resolves a backup filename received from the frontend to a file in `backups_dir`.
+/// Only a plain file name is accepted: no separators, `..`, drive prefixes or absolute
+/// paths. Symlinks are refused, and the resolved file must sit directly in `backups_dir`,
+/// so a command can never reach the live database or anything else outside it.
+fn resolve_backup_file(backups_dir: &Path, backup_filename: &str) -> Result<PathBuf, String> {
+    let invalid = || format!("Invalid backup filename: {}", backup_filename);
+
+    if backup_filename.contains(['/', '\\', ':', '\0']) {
+        return Err(invalid());
+    }
+    let mut components = Path::new(backup_filename).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => {}
+        _ => return Err(invalid()),
+    }
+
+    let backup_path = backups_dir.join(backup_filename);
+    let metadata = fs::symlink_metadata(&backup_path)
+        .map_err(|_| format!("Backup file not found: {}", backup_filename))?;
+    if metadata.file_type().is_symlink() {
+        return Err(format!(
+            "Refusing to use {}: backup files cannot be symbolic links",
+            backup_filename
+        ));
+    }
+    if !metadata.is_file() {
+        return Err(format!("Backup file not found: {}", backup_filename));
+    }
+
+    let resolved = backup_path
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {}", backup_filename, e))?;
+    let backups_dir = backups_dir
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve backups folder: {}", e))?;
+    if resolved.parent() != Some(backups_dir.as_path()) {
+        return Err(invalid());
+    }
+
+    Ok(resolved)
+}
+
+/// Reads a raw value from the `settings` table, or `None` if the key is not set
+fn read_setting(conn: &Connection, key: &str) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT value FROM settings WHERE key = ?1",
+        params![key],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| format!("Failed to read setting {}: {}", key, e))
+    .map(|v| v.flatten())
+}
+
+fn app_version(app: &AppHandle) -> String {
+    app.package_info().version.to_string()
+}
+
+/// Restarts the incremental chain after the live database was replaced or rewritten.
+/// Failures are logged; the next backup then simply checks the chain again.
+fn reset_backup_chain(db_path: &Path) {
+    let reset = Connection::open(db_path)
+        .map_err(|e| format!("Failed to open database: {}", e))
+        .and_then(|conn| incremental::reset_chain(&conn));
+
+    if let Err(e) = reset {
+        eprintln!("Warning: Failed to reset backup chain: {}", e);
+    }
+}
+
+/// Copies the live database to `destination` with SQLite's backup API,
+/// so changes still in the WAL file are included
+fn snapshot_database(db_path: &Path, destination: &Path) -> Result<(), String> {
+    let conn = Connection::open(db_path)
+        .map_err(|e| format!("Failed to open source database: {}", e))?;
+    conn.busy_timeout(std::time::Duration::from_secs(5))
+        .map_err(|e| format!("Failed to set busy timeout: {}", e))?;
+
+    conn.backup(rusqlite::DatabaseName::Main, destination, None)
+        .map_err(|e| format!("Failed to copy database: {}", e))
+}
+
+/// Copies the live database to a safety backup named `<prefix><timestamp>.db`
+/// and returns its filename. Nothing is written if there is no database yet.
+fn write_safety_backup(app: &AppHandle, prefix: &str) -> Result<String, String> {
+    let db_path = get_db_path(app)?;
+    let backups_dir = get_backups_dir(app)?;
+
+    let safety_filename = format!(
+        "{}{}.db",
+        prefix,
+        Local::now().format("%Y-%m-%d_%H-%M-%S")
+    );
+    let safety_path = backups_dir.join(&safety_filename);
+
+    if db_path.exists() {
+        snapshot_database(&db_path, &safety_path)
+            .map_err(|e| format!("Failed to create safety backup: {}", e))?;
+        record_safety_manifest(app, &safety_path);
+    }
+
+    Ok(safety_filename)
+}
+
+/// Overwrites the live database with the plain database at `source`.
+/// Pages are written through SQLite's backup API rather than copying the file, so the
+/// WAL and shared-memory files stay consistent and open connections, such as the
+/// frontend's, are waited for instead of being left reading a file swapped underneath them.
+/// A cancel before the last page is copied leaves the live database unchanged.
+fn replace_live_database(
+    app: &AppHandle,
+    source: &Path,
+    source_name: &str,
+    safety_backup: &str,
+    operation: Option<&Operation>,
+) -> Result<(), String> {
+    let db_path = get_db_path(app)?;
+
+    let source_conn = Connection::open_with_flags(source, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open backup database: {}", e))?;
+    let mut conn = Connection::open(&db_path)
+        .map_err(|e| format!("Failed to open main database: {}", e))?;
+    conn.busy_timeout(std::time::Duration::from_secs(5))
+        .map_err(|e| format!("Failed to set busy timeout: {}", e))?;
+
+    {
+        // Until the backup is done, dropping it rolls back everything written to the live file
+        let restore = rusqlite::backup::Backup::new(&source_conn, &mut conn)
+            .map_err(|e| format!("Failed to initialize restore: {}", e))?;
+        copy_pages(&restore, operation)?;
+    }
+    drop(source_conn);
+
+    // A backup from an older app version is brought up to the current schema in place
+    migrations::migrate(&conn).map_err(|e| {
+        format!(
+            "Database restored but could not be upgraded to the current schema ({}). \
+             Restore the safety backup {} to go back.",
+            e, safety_backup
+        )
+    })?;
+
+    // Fold the restored pages into the main file so no older WAL content lingers beside it
+    if let Err(e) = conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(())) {
+        eprintln!("Warning: Failed to checkpoint restored database: {}", e);
+    }
+    drop(conn);
+
+    reset_backup_chain(&db_path);
+    // Backups from earlier versions may still hold the offsite secret key in `settings`
+    offsite::move_legacy_secret(&db_path);
+
+    let payload = DatabaseReplaced {
+        source: source_name.to_string(),
+        safety_backup: safety_backup.to_string(),
+    };
+    if let Err(e) = app.emit(DATABASE_REPLACED_EVENT, payload) {
+        eprintln!("Warning: Failed to notify frontend of database replacement: {}", e);
+    }
+
+    Ok(())
+}
+
+/// Writes the manifest for a safety backup. Failures are logged rather than
+/// aborting the restore that the safety backup protects.
+fn record_safety_manifest(app: &AppHandle, safety_path: &Path) {
+    if let Err(e) = manifest::create_manifest(safety_path, BackupType::Safety, None, &app_version(app)) {
+        eprintln!("Warning: Failed to write safety backup manifest: {}", e);
+    }
+}
+
+/// Writes a timestamped copy of the database into the backups directory, along with its manifest.
+/// The copy is compressed when `backup_compression` is set to zstd or gzip.
+/// In incremental mode only the changes since the previous backup are written, unless
+/// `force_full` is set or the chain has to be restarted.
+/// Shared by the `backup_database` command and the auto-backup scheduler.
+/// With an `operation`, page copy progress is reported and a cancel removes the partial file.
+fn create_backup_file(
+    db_path: &Path,
+    backups_dir: &Path,
+    backup_type: BackupType,
+    created_by: Option<&str>,
+    app_version: &str,
+    force_full: bool,
+    operation: Option<&Operation>,
+) -> Result<BackupResult, String> {
+    // Verify source database exists
+    if !db_path.exists() {
+        return Err("Database file not found".to_string());
+    }
+
+    // Use SQLite's backup API for a proper backup that handles WAL mode
+    // This ensures all data (including WAL) is included in the backup
+    let source_conn = Connection::open(db_path)
+        .map_err(|e| format!("Failed to open source database: {}", e))?;
+    source_conn
+        .busy_timeout(std::time::Duration::from_secs(5))
+        .map_err(|e| format!("Failed to set busy timeout: {}", e))?;
+
+    let compression = CompressionSettings::load(&source_conn)?;
+
+    let start_seq = match incremental::plan_next_backup(&source_conn, backups_dir, force_full)? {
+        NextBackup::Incremental {
+            base_file,
+            parent_file,
+            from_seq,
+        } => {
+            if let Some(op) = operation {
+                op.check_cancelled()?;
+            }
+            return incremental::create_increment(
+                &source_conn,
+                backups_dir,
+                compression,
+                &base_file,
+                &parent_file,
+                from_seq,
+                backup_type,
+                created_by,
+                app_version,
+            );
+        }
+        NextBackup::Full { start_seq } => start_seq,
+    };
+
+    // Generate backup filename with timestamp
+    let timestamp = Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+    let backup_filename = format!(
+        "motormods_backup_{}{}",
+        timestamp,
+        compression.compression.extension()
+    );
+    let backup_path = backups_dir.join(&backup_filename);
+
+    // Never overwrite an existing backup, which may be the base of an incremental chain
+    if backup_path.exists() {
+        return Err(format!("Backup file already exists: {}", backup_filename));
+    }
+
+    // Compressed backups are first written uncompressed to a temporary file
+    let staging = match compression.compression {
+        Compression::None => None,
+        _ => Some(MaterializedBackup::temporary("backup")?),
+    };
+    let database_path = staging.as_ref().map_or(backup_path.as_path(), |s| s.path());
+
+    let mut backup_conn = Connection::open(database_path)
+        .map_err(|e| format!("Failed to create backup database: {}", e))?;
+
+    // Use SQLite's backup API
+    let backup = rusqlite::backup::Backup::new(&source_conn, &mut backup_conn)
+        .map_err(|e| format!("Failed to initialize backup: {}", e))?;
+    
+    // Copy 100 pages per step, pausing between steps so the app can keep writing
+    let copied = copy_pages(&backup, operation);
+
+    // Release the backup handle so the checksum covers the finished file
+    drop(backup);
+    drop(backup_conn);
+
+    if let Err(e) = copied {
+        let _ = fs::remove_file(database_path);
+        return Err(e);
+    }
+
+    if staging.is_some() {
+        if let Err(e) = archive::compress_file(database_path, &backup_path, compression) {
+            let _ = fs::remove_file(&backup_path);
+            return Err(e);
+        }
+    }
+
+    let manifest = manifest::create_manifest_from(
+        &backup_path,
+        database_path,
+        backup_type,
+        created_by,
+        app_version,
+    )?;
+
+    if let Some(start_seq) = start_seq {
+        incremental::record_full(&source_conn, &backup_filename, start_seq)?;
+    }
+
+    Ok(BackupResult {
+        filename: backup_filename,
+        path: backup_path.to_string_lossy().to_string(),
+        file_size: manifest.file_size,
+        created_at: Local::now().to_rfc3339(),
+        manifest,
+    })
+}
+
+/// Runs a backup step by step, reporting progress and stopping early on cancel
+fn copy_pages(
+    backup: &rusqlite::backup::Backup,
+    operation: Option<&Operation>,
+) -> Result<(), String> {
+    use rusqlite::backup::StepResult;
+
+    loop {
+        let step = backup
+            .step(100)
+            .map_err(|e| format!("Failed to copy database: {}", e))?;
+
+        if let Some(op) = operation {
+            let progress = backup.progress();
+            let total = progress.pagecount.max(0) as u64;
+            let done = total.saturating_sub(progress.remaining.max(0) as u64);
+            op.report("copying", None, done, total);
+            op.check_cancelled()?;
+        }
+
+        match step {
+            StepResult::Done => return Ok(()),
+            _ => std::thread::sleep(std::time::Duration::from_millis(10)),
+        }
+    }
+}
+
 /// Copy all data from one table to another using rusqlite
 /// This handles arbitrary column structures dynamically
+/// Row failures are counted and their messages added to `errors`; an `Err` means the
+/// table could not be copied at all
 fn copy_table_data(
     backup_conn: &Connection,
     main_conn: &Connection,
     table_name: &str,
-) -> Result<usize, String> {
+    errors: &mut Vec<String>,
+    operation: Option<&Operation>,
+) -> Result<TableRestoreResult, String> {
+    let mut result = TableRestoreResult {
+        table: table_name.to_string(),
+        in_backup: true,
+        imported: 0,
+        failed: 0,
+        skipped: 0,
+    };
+
     // Check if table exists in backup
     let table_exists: bool = backup_conn
         .query_row(
@@ -85,7 +476,8 @@ fn copy_table_data(
         .map_err(|e| format!("Failed to check table existence: {}", e))?;
 
     if !table_exists {
-        return Ok(0);
+        result.in_backup = false;
+        return Ok(result);
     }
 
     // Get column names from backup table
@@ -100,7 +492,7 @@ fn copy_table_data(
         .collect();
 
     if columns.is_empty() {
-        return Ok(0);
+        return Ok(result);
     }
 
     let columns_str = columns.join(", ");
@@ -114,9 +506,8 @@ fn copy_table_data(
         .map_err(|e| format!("Failed to prepare select: {}", e))?;
 
     let column_count = columns.len();
-    let mut rows_data: Vec<Vec<rusqlite::types::Value>> = Vec::new();
 
-    let rows = select_stmt
+    let rows_data: Vec<Vec<rusqlite::types::Value>> = select_stmt
         .query_map([], |row| {
             let mut values: Vec<rusqlite::types::Value> = Vec::new();
             for i in 0..column_count {
@@ -125,13 +516,9 @@ fn copy_table_data(
             }
             Ok(values)
         })
-        .map_err(|e| format!("Failed to query rows: {}", e))?;
-
-    for row in rows {
-        if let Ok(values) = row {
-            rows_data.push(values);
-        }
-    }
+        .map_err(|e| format!("Failed to query rows: {}", e))?
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("Failed to read rows: {}", e))?;
 
     // Insert into main database
     let insert_sql = format!(
@@ -139,22 +526,262 @@ fn copy_table_data(
         table_name, columns_str, placeholders_str
     );
 
-    let mut count = 0;
-    for values in rows_data {
+    // Fails when nothing from this table can be written, e.g. a column the live schema lacks
+    let mut insert_stmt = main_conn
+        .prepare(&insert_sql)
+        .map_err(|e| format!("Failed to prepare insert: {}", e))?;
+
+    let total = rows_data.len() as u64;
+    for (index, values) in rows_data.iter().enumerate() {
+        if let Some(op) = operation {
+            if index % 500 == 0 {
+                op.report("importing", Some(table_name), index as u64, total);
+                op.check_cancelled()?;
+            }
+        }
+
         let params: Vec<&dyn rusqlite::ToSql> = values
             .iter()
             .map(|v| v as &dyn rusqlite::ToSql)
             .collect();
 
-        match main_conn.execute(&insert_sql, params.as_slice()) {
-            Ok(_) => count += 1,
+        match insert_stmt.execute(params.as_slice()) {
+            Ok(_) => result.imported += 1,
+            Err(e) => {
+                result.failed += 1;
+                if errors.len() < MAX_RESTORE_ERRORS {
+                    errors.push(format!("{} row {}: {}", table_name, index + 1, e));
+                }
+            }
+        }
+    }
+
+    if let Some(op) = operation {
+        op.report("importing", Some(table_name), total, total);
+    }
+
+    Ok(result)
+}
+
+/// Clears `tables` and copies them from `source_conn` in a transaction on `main_conn`.
+/// Every table is attempted so the report is complete, but the transaction is only
+/// committed if nothing failed. An `Err` means the transaction could not be ended cleanly.
+/// A cancelled `operation` stops at the next check and rolls everything back.
+fn replace_tables(
+    source_conn: &Connection,
+    main_conn: &Connection,
+    tables: &[&str],
+    errors: &mut Vec<String>,
+    operation: Option<&Operation>,
+) -> Result<Vec<TableRestoreResult>, String> {
+    let mut results = Vec::new();
+
+    // Tables the backup does not have keep their live rows
+    let mut present = Vec::new();
+    for table in tables {
+        let exists: bool = source_conn
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type='table' AND name=?1",
+                params![table],
+                |row| row.get(0),
+            )
+            .unwrap_or(false);
+        if exists {
+            present.push(*table);
+        }
+    }
+
+    // Clear tables first (reverse order for foreign keys)
+    for table in present.iter().rev() {
+        if let Err(e) = main_conn.execute(&format!("DELETE FROM {}", table), []) {
+            errors.push(format!("{}: could not clear table: {}", table, e));
+        }
+    }
+
+    // Import data in forward order
+    for table in tables {
+        if operation.is_some_and(|op| op.is_cancelled()) {
+            errors.insert(0, operations::CANCELLED.to_string());
+            break;
+        }
+
+        match copy_table_data(source_conn, main_conn, table, errors, operation) {
+            Ok(result) => {
+                println!(
+                    "[Restore] Imported {} rows into {} ({} failed)",
+                    result.imported, table, result.failed
+                );
+                results.push(result);
+            }
+            Err(e) if e == operations::CANCELLED => {
+                errors.insert(0, e);
+                break;
+            }
             Err(e) => {
-                eprintln!("Warning: Failed to insert row into {}: {}", table_name, e);
+                let skipped = source_conn
+                    .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                        row.get::<_, i64>(0)
+                    })
+                    .map_or(0, |n| n as usize);
+                errors.push(format!("{}: {}", table, e));
+                results.push(TableRestoreResult {
+                    table: table.to_string(),
+                    in_backup: true,
+                    imported: 0,
+                    failed: 0,
+                    skipped,
+                });
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        main_conn
+            .execute("ROLLBACK", [])
+            .map_err(|e| format!("Failed to roll back restore: {}", e))?;
+        return Ok(results);
+    }
+
+    if let Err(e) = main_conn.execute("COMMIT", []) {
+        errors.push(format!("Failed to commit transaction: {}", e));
+        main_conn
+            .execute("ROLLBACK", [])
+            .map_err(|e| format!("Failed to roll back restore: {}", e))?;
+    }
+
+    Ok(results)
+}
+
+/// Replaces the live data in `tables` with the contents of a verified, uncompressed backup database.
+/// `tables` must be a subset of `DATA_TABLES`; they are processed in `DATA_TABLES` order.
+/// A safety backup of the current database is taken first.
+///
+/// The restore is all-or-nothing: if any row or table fails, nothing is kept and the
+/// result has `success: false` with per-table counts and the first errors. Should the
+/// rollback itself fail, the data is put back from the safety backup.
+fn import_backup_data(
+    app: &AppHandle,
+    backup_db: &Path,
+    tables: &[&str],
+    operation: Option<&Operation>,
+) -> Result<RestoreResult, String> {
+    let db_path = get_db_path(app)?;
+
+    if let Some(op) = operation {
+        op.check_cancelled()?;
+    }
+
+    // Backups from older app versions are brought up to the current schema first
+    let upgraded = schema::upgraded_copy(backup_db)?;
+    let backup_db = upgraded.as_ref().map_or(backup_db, |u| u.path());
+
+    // Create a safety backup first
+    let safety_filename = write_safety_backup(app, "pre_restore_safety_")?;
+
+    let tables: Vec<&str> = DATA_TABLES
+        .iter()
+        .copied()
+        .filter(|t| tables.contains(t))
+        .collect();
+
+    // Open both databases
+    let backup_conn = Connection::open(backup_db)
+        .map_err(|e| format!("Failed to open backup database: {}", e))?;
+    
+    let main_conn = Connection::open(&db_path)
+        .map_err(|e| format!("Failed to open main database: {}", e))?;
+
+    // Disable foreign keys for the import
+    main_conn
+        .execute("PRAGMA foreign_keys = OFF", [])
+        .map_err(|e| format!("Failed to disable foreign keys: {}", e))?;
+
+    main_conn
+        .execute("BEGIN TRANSACTION", [])
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let mut errors = Vec::new();
+    let outcome = replace_tables(&backup_conn, &main_conn, &tables, &mut errors, operation);
+
+    let tables_report = match outcome {
+        Ok(report) => report,
+        Err(e) => {
+            // The transaction could not be rolled back, so the live data may be half restored
+            errors.push(e);
+            let safety_path = get_backups_dir(app)?.join(&safety_filename);
+            if let Err(e) = revert_to_safety(&main_conn, &safety_path, &tables) {
+                let _ = main_conn.execute("PRAGMA foreign_keys = ON", []);
+                return Err(format!(
+                    "Restore failed and the automatic revert also failed ({}). \
+                     Restore the safety backup {} manually.",
+                    e, safety_filename
+                ));
             }
+            Vec::new()
         }
+    };
+
+    // Re-enable foreign keys
+    let _ = main_conn.execute("PRAGMA foreign_keys = ON", []);
+
+    let total_imported = tables_report.iter().map(|t| t.imported).sum();
+
+    // A cancelled restore was rolled back, so the live database is as it was
+    if errors.first().is_some_and(|e| e == operations::CANCELLED) {
+        return Err(operations::CANCELLED.to_string());
+    }
+
+    if !errors.is_empty() {
+        errors.truncate(MAX_RESTORE_ERRORS);
+        return Ok(RestoreResult {
+            success: false,
+            message: format!(
+                "Restore failed and no changes were kept: {}",
+                errors[0]
+            ),
+            records_imported: 0,
+            safety_backup: safety_filename,
+            tables: tables_report,
+            errors,
+        });
+    }
+
+    // The restore rewrote whole tables, so the incremental chain starts over
+    if let Err(e) = incremental::reset_chain(&main_conn) {
+        eprintln!("Warning: Failed to reset backup chain: {}", e);
     }
 
-    Ok(count)
+    Ok(RestoreResult {
+        success: true,
+        message: format!("Successfully restored {} records from backup", total_imported),
+        records_imported: total_imported,
+        safety_backup: safety_filename,
+        tables: tables_report,
+        errors,
+    })
+}
+
+/// Puts `tables` back from the safety backup taken before a failed restore
+fn revert_to_safety(main_conn: &Connection, safety_path: &Path, tables: &[&str]) -> Result<(), String> {
+    // Leave any half-finished transaction before starting the revert's own
+    let _ = main_conn.execute("ROLLBACK", []);
+
+    let safety_conn = Connection::open(safety_path)
+        .map_err(|e| format!("Failed to open safety backup: {}", e))?;
+
+    main_conn
+        .execute("BEGIN TRANSACTION", [])
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    // The revert itself cannot be cancelled
+    let mut errors = Vec::new();
+    replace_tables(&safety_conn, main_conn, tables, &mut errors, None)?;
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
 }
 
 // ============================================
@@ -168,60 +795,55 @@ fn greet(name: &str) -> String {
 
 /// Creates a backup of the database and returns detailed information
 /// Uses SQLite's backup API to ensure a consistent backup even with WAL mode
+/// In incremental mode, `full` forces a full backup that starts a new chain
+/// Runs in the background, emitting `operation-progress` events for `operation_id`
 #[tauri::command]
-fn backup_database(app: AppHandle) -> Result<BackupResult, String> {
-    let db_path = get_db_path(&app)?;
-    let backups_dir = get_backups_dir(&app)?;
-
-    // Verify source database exists
-    if !db_path.exists() {
-        return Err("Database file not found".to_string());
-    }
-
-    // Generate backup filename with timestamp
-    let timestamp = Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
-    let backup_filename = format!("motormods_backup_{}.db", timestamp);
-    let backup_path = backups_dir.join(&backup_filename);
-
-    // Use SQLite's backup API for a proper backup that handles WAL mode
-    // This ensures all data (including WAL) is included in the backup
-    let source_conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open source database: {}", e))?;
-    
-    let mut backup_conn = Connection::open(&backup_path)
-        .map_err(|e| format!("Failed to create backup database: {}", e))?;
-
-    // Use SQLite's backup API
-    let backup = rusqlite::backup::Backup::new(&source_conn, &mut backup_conn)
-        .map_err(|e| format!("Failed to initialize backup: {}", e))?;
-    
-    // Run the backup (copy all pages, -1 means copy all at once)
-    backup.run_to_completion(100, std::time::Duration::from_millis(10), None)
-        .map_err(|e| format!("Failed to complete backup: {}", e))?;
-
-    // Get file size
-    let metadata =
-        fs::metadata(&backup_path).map_err(|e| format!("Failed to get backup metadata: {}", e))?;
-
-    Ok(BackupResult {
-        filename: backup_filename,
-        path: backup_path.to_string_lossy().to_string(),
-        file_size: metadata.len(),
-        created_at: Local::now().to_rfc3339(),
+async fn backup_database(
+    app: AppHandle,
+    backup_type: Option<String>,
+    created_by: Option<String>,
+    full: Option<bool>,
+    operation_id: Option<String>,
+) -> Result<BackupResult, String> {
+    operations::run_blocking(app, operation_id, OperationKind::Backup, move |app, op| {
+        let _lock = operations::lock(app, "Creating a backup", None)?;
+        let db_path = get_db_path(app)?;
+        let backups_dir = get_backups_dir(app)?;
+
+        let result = create_backup_file(
+            &db_path,
+            &backups_dir,
+            BackupType::parse(backup_type.as_deref()),
+            created_by.as_deref(),
+            &app_version(app),
+            full.unwrap_or(false),
+            Some(op),
+        )?;
+
+        mirror::mirror_backup(&db_path, &backups_dir, Path::new(&result.path), Some(op));
+        offsite::queue_backup(&db_path, &backups_dir, Path::new(&result.path));
+        Ok(result)
     })
+    .await
 }
 
 /// Lists all backup files in the backups directory
+/// Runs in the background: backups without a manifest are unpacked and hashed to make one
 #[tauri::command]
-fn list_backups(app: AppHandle) -> Result<Vec<BackupFileInfo>, String> {
+async fn list_backups(app: AppHandle) -> Result<Vec<BackupFileInfo>, String> {
     let backups_dir = get_backups_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || list_backup_files(&backups_dir))
+        .await
+        .map_err(|e| format!("Failed to list backups: {}", e))
+}
 
+fn list_backup_files(backups_dir: &Path) -> Vec<BackupFileInfo> {
     let mut backups: Vec<BackupFileInfo> = Vec::new();
 
-    if let Ok(entries) = fs::read_dir(&backups_dir) {
+    if let Ok(entries) = fs::read_dir(backups_dir) {
         for entry in entries.flatten() {
             let path = entry.path();
-            if path.extension().map_or(false, |ext| ext == "db") {
+            if archive::is_backup_file(&path) {
                 if let Ok(metadata) = fs::metadata(&path) {
                     let modified = metadata
                         .modified()
@@ -231,6 +853,14 @@ fn list_backups(app: AppHandle) -> Result<Vec<BackupFileInfo>, String> {
                         })
                         .unwrap_or_else(|_| "Unknown".to_string());
 
+                    let manifest = match manifest::load_or_compute(&path) {
+                        Ok(m) => Some(m),
+                        Err(e) => {
+                            eprintln!("Warning: No manifest for {}: {}", path.display(), e);
+                            None
+                        }
+                    };
+
                     backups.push(BackupFileInfo {
                         filename: path
                             .file_name()
@@ -239,6 +869,7 @@ fn list_backups(app: AppHandle) -> Result<Vec<BackupFileInfo>, String> {
                         path: path.to_string_lossy().to_string(),
                         file_size: metadata.len(),
                         modified_at: modified,
+                        manifest,
                     });
                 }
             }
@@ -248,115 +879,155 @@ fn list_backups(app: AppHandle) -> Result<Vec<BackupFileInfo>, String> {
     // Sort by modified date descending
     backups.sort_by(|a, b| b.modified_at.cmp(&a.modified_at));
 
-    Ok(backups)
+    backups
 }
 
 /// Restores the database from a backup file
+/// Refuses backups that fail verification unless `force` is set
+/// Runs in the background, emitting `operation-progress` events for `operation_id`
 #[tauri::command]
-fn restore_database(app: AppHandle, backup_filename: String) -> Result<String, String> {
-    let db_path = get_db_path(&app)?;
-    let backups_dir = get_backups_dir(&app)?;
-    let backup_path = backups_dir.join(&backup_filename);
-
-    // Verify backup exists
-    if !backup_path.exists() {
-        return Err(format!("Backup file not found: {}", backup_filename));
-    }
-
-    // Create a safety backup of current database before restore
-    let safety_filename = format!(
-        "pre_restore_safety_{}.db",
-        Local::now().format("%Y-%m-%d_%H-%M-%S")
-    );
-    let safety_path = backups_dir.join(&safety_filename);
-
-    if db_path.exists() {
-        fs::copy(&db_path, &safety_path)
-            .map_err(|e| format!("Failed to create safety backup: {}", e))?;
-    }
-
-    // Perform the restore
-    fs::copy(&backup_path, &db_path).map_err(|e| format!("Failed to restore database: {}", e))?;
-
-    Ok(format!(
-        "Database restored from {}. Safety backup created: {}",
-        backup_filename, safety_filename
-    ))
+async fn restore_database(
+    app: AppHandle,
+    backup_filename: String,
+    force: Option<bool>,
+    operation_id: Option<String>,
+) -> Result<String, String> {
+    operations::run_blocking(app, operation_id, OperationKind::Restore, move |app, op| {
+        let backups_dir = get_backups_dir(app)?;
+        let backup_path = resolve_backup_file(&backups_dir, &backup_filename)?;
+
+        let _lock = operations::lock(
+            app,
+            &format!("Restoring {}", backup_filename),
+            Some(&backup_filename),
+        )?;
+
+        op.report("preparing", None, 0, 1);
+        let unpacked = archive::materialize(&backup_path, None)?;
+        incremental::ensure_standalone(unpacked.path())?;
+        verify::ensure_verified(&backup_path, unpacked.path(), force)?;
+        migrations::ensure_file_supported(unpacked.path())?;
+        op.check_cancelled()?;
+
+        // Create a safety backup of current database before restore
+        let safety_filename = write_safety_backup(app, "pre_restore_safety_")?;
+
+        // Perform the restore
+        replace_live_database(app, unpacked.path(), &backup_filename, &safety_filename, Some(op))?;
+
+        Ok(format!(
+            "Database restored from {}. Safety backup created: {}",
+            backup_filename, safety_filename
+        ))
+    })
+    .await
 }
 
 /// Restores from an external backup file path
+/// Refuses backups that fail verification unless `force` is set
+/// Encrypted exports need their `passphrase`
+/// Runs in the background, emitting `operation-progress` events for `operation_id`
 #[tauri::command]
-fn import_backup(app: AppHandle, source_path: String) -> Result<String, String> {
-    let db_path = get_db_path(&app)?;
-    let backups_dir = get_backups_dir(&app)?;
-    let source = PathBuf::from(&source_path);
-
-    // Verify source exists and is a .db file
-    if !source.exists() {
-        return Err("Source backup file not found".to_string());
-    }
-
-    if source.extension().map_or(true, |ext| ext != "db") {
-        return Err("Invalid backup file. Expected .db file".to_string());
-    }
-
-    // Create a safety backup first
-    let safety_filename = format!(
-        "pre_import_safety_{}.db",
-        Local::now().format("%Y-%m-%d_%H-%M-%S")
-    );
-    let safety_path = backups_dir.join(&safety_filename);
+async fn import_backup(
+    app: AppHandle,
+    source_path: String,
+    force: Option<bool>,
+    passphrase: Option<String>,
+    operation_id: Option<String>,
+) -> Result<String, String> {
+    operations::run_blocking(app, operation_id, OperationKind::Restore, move |app, op| {
+        let source = PathBuf::from(&source_path);
 
-    if db_path.exists() {
-        fs::copy(&db_path, &safety_path)
-            .map_err(|e| format!("Failed to create safety backup: {}", e))?;
-    }
+        // Verify source exists and is a backup file
+        if !source.exists() {
+            return Err("Source backup file not found".to_string());
+        }
 
-    // Restore from external file
-    fs::copy(&source, &db_path).map_err(|e| format!("Failed to import backup: {}", e))?;
+        if !archive::is_backup_file(&source) {
+            return Err("Invalid backup file. Expected .db, .db.zst, .db.gz or .enc file".to_string());
+        }
 
-    Ok(format!(
-        "Database imported from external backup. Safety backup created: {}",
-        safety_filename
-    ))
+        let _lock = operations::lock(
+            app,
+            &format!("Importing {}", operations::file_label(&source)),
+            None,
+        )?;
+
+        // Decrypt and decompress before anything touches the live database,
+        // so a wrong passphrase fails without side effects
+        op.report("preparing", None, 0, 1);
+        let unpacked = archive::materialize(&source, passphrase.as_deref())?;
+        incremental::ensure_standalone(unpacked.path())?;
+        verify::ensure_verified(&source, unpacked.path(), force)?;
+        migrations::ensure_file_supported(unpacked.path())?;
+        op.check_cancelled()?;
+
+        // Create a safety backup first
+        let safety_filename = write_safety_backup(app, "pre_import_safety_")?;
+
+        // Restore from external file
+        replace_live_database(app, unpacked.path(), &source_path, &safety_filename, Some(op))?;
+
+        Ok(format!(
+            "Database imported from external backup. Safety backup created: {}",
+            safety_filename
+        ))
+    })
+    .await
 }
 
 /// Exports a backup to a specified destination
+/// Compressed backups are exported as-is, so the destination should keep the same extension
+/// With a `passphrase`, the export is encrypted and should be saved with an `.enc` extension
 #[tauri::command]
 fn export_backup(
     app: AppHandle,
     backup_filename: String,
     destination_path: String,
+    passphrase: Option<String>,
 ) -> Result<String, String> {
     let backups_dir = get_backups_dir(&app)?;
-    let backup_path = backups_dir.join(&backup_filename);
+    let backup_path = resolve_backup_file(&backups_dir, &backup_filename)?;
     let destination = PathBuf::from(&destination_path);
 
-    if !backup_path.exists() {
-        return Err(format!("Backup file not found: {}", backup_filename));
+    if !archive::is_backup_file(&backup_path) {
+        return Err("Can only export .db, .db.zst or .db.gz backup files".to_string());
     }
 
-    fs::copy(&backup_path, &destination).map_err(|e| format!("Failed to export backup: {}", e))?;
+    let _lock = operations::lock(
+        &app,
+        &format!("Exporting {}", backup_filename),
+        Some(&backup_filename),
+    )?;
 
-    Ok(format!("Backup exported to: {}", destination_path))
+    match passphrase.as_deref().filter(|p| !p.is_empty()) {
+        Some(passphrase) => {
+            encryption::encrypt_file(&backup_path, &destination, passphrase)?;
+            Ok(format!("Encrypted backup exported to: {}", destination_path))
+        }
+        None => {
+            fs::copy(&backup_path, &destination)
+                .map_err(|e| format!("Failed to export backup: {}", e))?;
+            Ok(format!("Backup exported to: {}", destination_path))
+        }
+    }
 }
 
 /// Deletes a specific backup file
 #[tauri::command]
 fn delete_backup(app: AppHandle, backup_filename: String) -> Result<String, String> {
     let backups_dir = get_backups_dir(&app)?;
-    let backup_path = backups_dir.join(&backup_filename);
+    let backup_path = resolve_backup_file(&backups_dir, &backup_filename)?;
 
-    if !backup_path.exists() {
-        return Err(format!("Backup file not found: {}", backup_filename));
+    // Safety check: don't allow deleting anything but backup files
+    if !archive::is_backup_file(&backup_path) {
+        return Err("Can only delete .db, .db.zst, .db.gz or .enc backup files".to_string());
     }
 
-    // Safety check: don't allow deleting non-.db files
-    if backup_path.extension().map_or(true, |ext| ext != "db") {
-        return Err("Can only delete .db backup files".to_string());
-    }
+    let _lock = operations::lock_for_delete(&app, &backup_filename)?;
 
     fs::remove_file(&backup_path).map_err(|e| format!("Failed to delete backup: {}", e))?;
+    manifest::remove_manifest(&backup_path);
 
     Ok(format!("Backup deleted: {}", backup_filename))
 }
@@ -372,11 +1043,7 @@ fn get_backups_path(app: AppHandle) -> Result<String, String> {
 #[tauri::command]
 fn get_backup_file_path(app: AppHandle, backup_filename: String) -> Result<String, String> {
     let backups_dir = get_backups_dir(&app)?;
-    let backup_path = backups_dir.join(&backup_filename);
-
-    if !backup_path.exists() {
-        return Err(format!("Backup file not found: {}", backup_filename));
-    }
+    let backup_path = resolve_backup_file(&backups_dir, &backup_filename)?;
 
     Ok(backup_path.to_string_lossy().to_string())
 }
@@ -385,116 +1052,102 @@ fn get_backup_file_path(app: AppHandle, backup_filename: String) -> Result<Strin
 #[tauri::command]
 fn create_safety_backup(app: AppHandle) -> Result<String, String> {
     let db_path = get_db_path(&app)?;
-    let backups_dir = get_backups_dir(&app)?;
 
     if !db_path.exists() {
         return Err("Database file not found".to_string());
     }
 
-    let safety_filename = format!(
-        "pre_import_safety_{}.db",
-        Local::now().format("%Y-%m-%d_%H-%M-%S")
-    );
-    let safety_path = backups_dir.join(&safety_filename);
-
-    fs::copy(&db_path, &safety_path)
-        .map_err(|e| format!("Failed to create safety backup: {}", e))?;
-
-    Ok(safety_filename)
+    let _lock = operations::lock(&app, "Creating a safety backup", None)?;
+    write_safety_backup(&app, "pre_import_safety_")
 }
 
 /// Restores database by importing data from a backup file
 /// This uses rusqlite directly to handle the data import properly
 /// Much more robust than file replacement - works without app restart
+/// Refuses backups that fail verification unless `force` is set
+/// Encrypted exports need their `passphrase`
+/// Runs in the background, emitting `operation-progress` events for `operation_id`
 #[tauri::command]
-fn restore_data_from_backup(app: AppHandle, backup_path: String) -> Result<RestoreResult, String> {
-    let db_path = get_db_path(&app)?;
-    let backups_dir = get_backups_dir(&app)?;
-    let backup_file = PathBuf::from(&backup_path);
+async fn restore_data_from_backup(
+    app: AppHandle,
+    backup_path: String,
+    force: Option<bool>,
+    passphrase: Option<String>,
+    operation_id: Option<String>,
+) -> Result<RestoreResult, String> {
+    operations::run_blocking(app, operation_id, OperationKind::Restore, move |app, op| {
+        restore_data(app, &PathBuf::from(&backup_path), force, passphrase.as_deref(), op)
+    })
+    .await
+}
+
+/// Restores database by importing data from a backup file in the backups directory
+#[tauri::command]
+async fn restore_data_from_backup_file(
+    app: AppHandle,
+    backup_filename: String,
+    force: Option<bool>,
+    passphrase: Option<String>,
+    operation_id: Option<String>,
+) -> Result<RestoreResult, String> {
+    operations::run_blocking(app, operation_id, OperationKind::Restore, move |app, op| {
+        let backup_path = resolve_backup_file(&get_backups_dir(app)?, &backup_filename)?;
+        restore_data(app, &backup_path, force, passphrase.as_deref(), op)
+    })
+    .await
+}
 
+fn restore_data(
+    app: &AppHandle,
+    backup_file: &Path,
+    force: Option<bool>,
+    passphrase: Option<&str>,
+    operation: &Operation,
+) -> Result<RestoreResult, String> {
     // Verify backup exists
     if !backup_file.exists() {
-        return Err(format!("Backup file not found: {}", backup_path));
-    }
-
-    // Create a safety backup first
-    let safety_filename = format!(
-        "pre_restore_safety_{}.db",
-        Local::now().format("%Y-%m-%d_%H-%M-%S")
-    );
-    let safety_path = backups_dir.join(&safety_filename);
-
-    if db_path.exists() {
-        fs::copy(&db_path, &safety_path)
-            .map_err(|e| format!("Failed to create safety backup: {}", e))?;
+        return Err(format!("Backup file not found: {}", backup_file.display()));
     }
 
-    // Open both databases
-    let backup_conn = Connection::open(&backup_file)
-        .map_err(|e| format!("Failed to open backup database: {}", e))?;
-    
-    let main_conn = Connection::open(&db_path)
-        .map_err(|e| format!("Failed to open main database: {}", e))?;
-
-    // Disable foreign keys for the import
-    main_conn
-        .execute("PRAGMA foreign_keys = OFF", [])
-        .map_err(|e| format!("Failed to disable foreign keys: {}", e))?;
-
-    // Start a transaction
-    main_conn
-        .execute("BEGIN TRANSACTION", [])
-        .map_err(|e| format!("Failed to start transaction: {}", e))?;
-
-    let mut total_imported = 0;
+    let name = operations::file_label(backup_file);
+    let _lock = operations::lock(app, &format!("Restoring {}", name), Some(&name))?;
 
-    // Clear and import each table
-    for table in DATA_TABLES.iter().rev() {
-        // Clear table first (reverse order for foreign keys)
-        if let Err(e) = main_conn.execute(&format!("DELETE FROM {}", table), []) {
-            eprintln!("Warning: Could not clear table {}: {}", table, e);
-        }
-    }
+    operation.report("preparing", None, 0, 1);
+    let unpacked = archive::materialize(backup_file, passphrase)?;
+    incremental::ensure_standalone(unpacked.path())?;
+    verify::ensure_verified(backup_file, unpacked.path(), force)?;
 
-    // Import data in forward order
-    for table in DATA_TABLES.iter() {
-        match copy_table_data(&backup_conn, &main_conn, table) {
-            Ok(count) => {
-                println!("[Restore] Imported {} rows into {}", count, table);
-                total_imported += count;
-            }
-            Err(e) => {
-                eprintln!("Warning: Error importing {}: {}", table, e);
-                // Continue with other tables
-            }
-        }
-    }
-
-    // Commit the transaction
-    if let Err(e) = main_conn.execute("COMMIT", []) {
-        // Try to rollback
-        let _ = main_conn.execute("ROLLBACK", []);
-        return Err(format!("Failed to commit transaction: {}", e));
-    }
-
-    // Re-enable foreign keys
-    let _ = main_conn.execute("PRAGMA foreign_keys = ON", []);
-
-    Ok(RestoreResult {
-        success: true,
-        message: format!("Successfully restored {} records from backup", total_imported),
-        records_imported: total_imported,
-        safety_backup: safety_filename,
-    })
+    import_backup_data(app, unpacked.path(), DATA_TABLES, Some(operation))
 }
 
-/// Restores database by importing data from a backup file in the backups directory
+/// Restores an incremental backup by replaying its full base backup and every
+/// increment up to it. Full backups are restored as they are.
+/// Refuses a chain whose result fails verification unless `force` is set
+/// Runs in the background, emitting `operation-progress` events for `operation_id`
 #[tauri::command]
-fn restore_data_from_backup_file(app: AppHandle, backup_filename: String) -> Result<RestoreResult, String> {
-    let backups_dir = get_backups_dir(&app)?;
-    let backup_path = backups_dir.join(&backup_filename);
-    
-    restore_data_from_backup(app, backup_path.to_string_lossy().to_string())
+async fn restore_backup_chain(
+    app: AppHandle,
+    backup_filename: String,
+    force: Option<bool>,
+    operation_id: Option<String>,
+) -> Result<RestoreResult, String> {
+    operations::run_blocking(app, operation_id, OperationKind::Restore, move |app, op| {
+        let backups_dir = get_backups_dir(app)?;
+        let backup_path = resolve_backup_file(&backups_dir, &backup_filename)?;
+
+        let _lock = operations::lock(
+            app,
+            &format!("Restoring {}", backup_filename),
+            Some(&backup_filename),
+        )?;
+
+        op.report("preparing", None, 0, 1);
+        let replayed = incremental::replay_chain(&backups_dir, &backup_filename)?;
+        verify::ensure_verified(&backup_path, replayed.path(), force)?;
+
+        import_backup_data(app, replayed.path(), DATA_TABLES, Some(op))
+    })
+    .await
 }
 
 #[tauri::command]
@@ -656,6 +1309,17 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_process::init())
+        .manage(operations::OperationLock::default())
+        .manage(migrations::StartupMigration::default())
+        .setup(|app| {
+            // The schema is migrated before the frontend opens the database
+            let db_path = get_db_path(app.handle())?;
+            archive::init_temp_dir(&app.path().app_config_dir()?)?;
+            migrations::run_at_startup(&db_path, &app.state::<migrations::StartupMigration>());
+            offsite::move_legacy_secret(&db_path);
+            scheduler::start(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             backup_database,
@@ -669,9 +1333,145 @@ pub fn run() {
             create_safety_backup,
             restore_data_from_backup,
             restore_data_from_backup_file,
+            restore_backup_chain,
+            preview::preview_restore,
+            selective::restore_tables_from_backup,
+            selective::recover_records,
+            operations::cancel_operation,
+            operations::get_running_operation,
+            migrations::get_schema_version,
+            retention::apply_backup_retention,
+            verify::verify_backup,
+            mirror::get_mirror_status,
+            offsite::list_offsite_backups,
+            offsite::download_offsite_backup,
+            offsite::get_offsite_status,
+            offsite::set_offsite_credentials,
+            offsite::retry_offsite_uploads,
+            browser::browse_backup_invoices,
+            browser::browse_backup_products,
+            browser::browse_backup_returns,
+            browser::close_backup_browser,
+            merge::merge_import,
+            portable::export_portable_archive,
+            portable::import_portable_archive,
+            portable::convert_portable_archive,
+            sales::create_invoice,
+            returns::create_sales_return,
+            returns::cancel_sales_return,
+            returns::amend_sales_return,
+            ledger::reconcile_stock,
             print_receipt,
             print_pdf_silent
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::resolve_backup_file;
+    use crate::test_support::temp_dir;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// An app config folder with a live database and a backups folder holding one backup
+    fn setup() -> (PathBuf, PathBuf) {
+        let root = temp_dir("paths");
+        let backups_dir = root.join("backups");
+        fs::create_dir_all(&backups_dir).unwrap();
+        fs::write(root.join("motormods.db"), b"live").unwrap();
+        fs::write(
+            backups_dir.join("motormods_backup_2025-01-01_10-00-00.db"),
+            b"backup",
+        )
+        .unwrap();
+        (root, backups_dir)
+    }
+
+    #[test]
+    fn resolves_plain_backup_filename() {
+        let (root, backups_dir) = setup();
+        let resolved =
+            resolve_backup_file(&backups_dir, "motormods_backup_2025-01-01_10-00-00.db").unwrap();
+        assert_eq!(
+            resolved,
+            backups_dir
+                .canonicalize()
+                .unwrap()
+                .join("motormods_backup_2025-01-01_10-00-00.db")
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn rejects_parent_directory_traversal() {
+        let (root, backups_dir) = setup();
+        for name in [
+            "../motormods.db",
+            "..",
+            ".",
+            "./../motormods.db",
+            "sub/../../motormods.db",
+        ] {
+            assert!(
+                resolve_backup_file(&backups_dir, name).is_err(),
+                "accepted {}",
+                name
+            );
+        }
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn rejects_absolute_paths() {
+        let (root, backups_dir) = setup();
+        let live_db = root.join("motormods.db");
+        assert!(resolve_backup_file(&backups_dir, &live_db.to_string_lossy()).is_err());
+        assert!(resolve_backup_file(&backups_dir, "/etc/passwd").is_err());
+        assert!(resolve_backup_file(&backups_dir, "C:\\Windows\\win.ini").is_err());
+        assert!(resolve_backup_file(&backups_dir, "C:motormods.db").is_err());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn rejects_separators_and_empty_names() {
+        let (root, backups_dir) = setup();
+        for name in ["", "sub/backup.db", "..\\motormods.db", "backup.db\0.txt"] {
+            assert!(
+                resolve_backup_file(&backups_dir, name).is_err(),
+                "accepted {:?}",
+                name
+            );
+        }
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn rejects_missing_files_and_directories() {
+        let (root, backups_dir) = setup();
+        fs::create_dir(backups_dir.join("folder.db")).unwrap();
+        assert!(resolve_backup_file(&backups_dir, "missing.db").is_err());
+        assert!(resolve_backup_file(&backups_dir, "folder.db").is_err());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let (root, backups_dir) = setup();
+        symlink(root.join("motormods.db"), backups_dir.join("linked.db")).unwrap();
+        symlink(
+            backups_dir.join("motormods_backup_2025-01-01_10-00-00.db"),
+            backups_dir.join("inside.db"),
+        )
+        .unwrap();
+
+        assert!(resolve_backup_file(&backups_dir, "linked.db").is_err());
+        assert!(resolve_backup_file(&backups_dir, "inside.db").is_err());
+        assert!(root.join("motormods.db").exists());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,302 @@
+use chrono::{DateTime, Local};
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::archive::{self, Compression};
+use crate::incremental;
+use crate::DATA_TABLES;
+
+// ============================================
+// BACKUP MANIFESTS
+// ============================================
+
+/// Bumped whenever fields are added to the manifest format
+const MANIFEST_FORMAT_VERSION: u32 = 3;
+
+const MANIFEST_SUFFIX: &str = ".manifest.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupType {
+    Auto,
+    Manual,
+    Safety,
+    /// Older backups whose manifest was computed after the fact
+    Unknown,
+}
+
+impl BackupType {
+    pub fn parse(value: Option<&str>) -> BackupType {
+        match value {
+            Some("auto") => BackupType::Auto,
+            Some("safety") => BackupType::Safety,
+            _ => BackupType::Manual,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupKind {
+    #[default]
+    Full,
+    /// Only the rows changed since `parent_file`; restored by replaying the chain from `base_file`
+    Incremental,
+}
+
+/// Describes what is inside a backup file.
+/// Stored next to the backup as `<backup filename>.manifest.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format_version: u32,
+    pub filename: String,
+    /// Hex-encoded SHA-256 of the backup file as stored on disk
+    pub sha256: String,
+    pub file_size: u64,
+    /// Compression of the stored file; manifests from format 1 are always uncompressed
+    #[serde(default)]
+    pub compression: Compression,
+    pub created_at: String,
+    pub backup_type: BackupType,
+    pub created_by: Option<String>,
+    /// App version that wrote the backup; unknown for manifests computed on demand
+    pub app_version: Option<String>,
+    /// SQLite `PRAGMA user_version` of the backed-up database
+    pub schema_version: i64,
+    /// Row counts for each table in `DATA_TABLES` present in the backup (after decompression)
+    pub table_counts: BTreeMap<String, i64>,
+    /// Manifests before format 3 only describe full backups
+    #[serde(default)]
+    pub kind: BackupKind,
+    #[serde(default)]
+    pub base_file: Option<String>,
+    #[serde(default)]
+    pub parent_file: Option<String>,
+    /// The file's size no longer matches this manifest: it was truncated or appended to
+    /// since the manifest was written. Set when loading; the sidecar is left as it was.
+    #[serde(default)]
+    pub stale: bool,
+}
+
+/// What `inspect_database` learns from opening a backup
+struct DatabaseInfo {
+    schema_version: i64,
+    table_counts: BTreeMap<String, i64>,
+    increment: Option<incremental::IncrementMeta>,
+}
+
+impl DatabaseInfo {
+    fn kind(&self) -> BackupKind {
+        if self.increment.is_some() {
+            BackupKind::Incremental
+        } else {
+            BackupKind::Full
+        }
+    }
+}
+
+/// Returns the sidecar manifest path for a backup file
+pub fn manifest_path(backup_path: &Path) -> PathBuf {
+    let mut name = backup_path
+        .file_name()
+        .map(|n| n.to_os_string())
+        .unwrap_or_default();
+    name.push(MANIFEST_SUFFIX);
+    backup_path.with_file_name(name)
+}
+
+/// Computes the hex-encoded SHA-256 of a file without loading it into memory
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file =
+        fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Reads `PRAGMA user_version`, per-table row counts and any incremental chain metadata
+fn inspect_database(db_path: &Path) -> Result<DatabaseInfo, String> {
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open backup database: {}", e))?;
+
+    let schema_version: i64 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read schema version: {}", e))?;
+
+    let mut table_counts = BTreeMap::new();
+    for table in DATA_TABLES {
+        let exists: bool = conn
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type='table' AND name=?1",
+                [table],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to check table existence: {}", e))?;
+
+        if exists {
+            let count: i64 = conn
+                .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))
+                .map_err(|e| format!("Failed to count rows in {}: {}", table, e))?;
+            table_counts.insert(table.to_string(), count);
+        }
+    }
+
+    Ok(DatabaseInfo {
+        schema_version,
+        table_counts,
+        increment: incremental::read_increment_meta(&conn)?,
+    })
+}
+
+fn filename_of(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Builds and writes the manifest for a freshly created backup
+pub fn create_manifest(
+    backup_path: &Path,
+    backup_type: BackupType,
+    created_by: Option<&str>,
+    app_version: &str,
+) -> Result<BackupManifest, String> {
+    let unpacked = archive::materialize(backup_path, None)?;
+    create_manifest_from(backup_path, unpacked.path(), backup_type, created_by, app_version)
+}
+
+/// Like `create_manifest`, for callers that still have the uncompressed database at hand.
+/// The checksum and size describe `backup_path`; schema and row counts come from `database_path`.
+pub fn create_manifest_from(
+    backup_path: &Path,
+    database_path: &Path,
+    backup_type: BackupType,
+    created_by: Option<&str>,
+    app_version: &str,
+) -> Result<BackupManifest, String> {
+    let info = inspect_database(database_path)?;
+    let file_size = fs::metadata(backup_path)
+        .map_err(|e| format!("Failed to get backup metadata: {}", e))?
+        .len();
+
+    let manifest = BackupManifest {
+        format_version: MANIFEST_FORMAT_VERSION,
+        filename: filename_of(backup_path),
+        sha256: sha256_file(backup_path)?,
+        file_size,
+        compression: Compression::detect(backup_path)?,
+        created_at: Local::now().to_rfc3339(),
+        backup_type,
+        created_by: created_by.map(|s| s.to_string()),
+        app_version: Some(app_version.to_string()),
+        schema_version: info.schema_version,
+        kind: info.kind(),
+        base_file: info.increment.as_ref().map(|m| m.base_file.clone()),
+        parent_file: info.increment.as_ref().map(|m| m.parent_file.clone()),
+        table_counts: info.table_counts,
+        stale: false,
+    };
+
+    write_manifest(backup_path, &manifest)?;
+    Ok(manifest)
+}
+
+pub fn write_manifest(backup_path: &Path, manifest: &BackupManifest) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    fs::write(manifest_path(backup_path), json)
+        .map_err(|e| format!("Failed to write manifest: {}", e))
+}
+
+/// Reads the stored manifest for a backup, if there is one
+pub fn read_manifest(backup_path: &Path) -> Option<BackupManifest> {
+    let raw = fs::read_to_string(manifest_path(backup_path)).ok()?;
+    serde_json::from_str(&raw).ok()
+}
+
+/// Returns the stored manifest, or computes (and caches) one for backups made before
+/// manifests existed. A stored manifest whose size no longer matches the file is returned
+/// marked `stale`, never recomputed: its checksum is what verification compares against.
+pub fn load_or_compute(backup_path: &Path) -> Result<BackupManifest, String> {
+    let metadata =
+        fs::metadata(backup_path).map_err(|e| format!("Failed to get backup metadata: {}", e))?;
+
+    if manifest_path(backup_path).exists() {
+        let mut manifest = read_manifest(backup_path)
+            .ok_or_else(|| format!("Unreadable manifest for {}", filename_of(backup_path)))?;
+        manifest.stale = manifest.file_size != metadata.len();
+        return Ok(manifest);
+    }
+
+    let filename = filename_of(backup_path);
+    let backup_type = if filename.starts_with("pre_restore_safety_")
+        || filename.starts_with("pre_import_safety_")
+    {
+        BackupType::Safety
+    } else {
+        BackupType::Unknown
+    };
+
+    let created_at = metadata
+        .modified()
+        .map(|t| DateTime::<Local>::from(t).to_rfc3339())
+        .unwrap_or_else(|_| "Unknown".to_string());
+
+    let unpacked = archive::materialize(backup_path, None)?;
+    let info = inspect_database(unpacked.path())?;
+
+    let manifest = BackupManifest {
+        format_version: MANIFEST_FORMAT_VERSION,
+        filename,
+        sha256: sha256_file(backup_path)?,
+        file_size: metadata.len(),
+        compression: Compression::detect(backup_path)?,
+        created_at,
+        backup_type,
+        created_by: None,
+        app_version: None,
+        schema_version: info.schema_version,
+        kind: info.kind(),
+        base_file: info.increment.as_ref().map(|m| m.base_file.clone()),
+        parent_file: info.increment.as_ref().map(|m| m.parent_file.clone()),
+        table_counts: info.table_counts,
+        stale: false,
+    };
+
+    // Caching is best effort; the manifest is still returned if the directory is read-only
+    if let Err(e) = write_manifest(backup_path, &manifest) {
+        eprintln!("Warning: {}", e);
+    }
+
+    Ok(manifest)
+}
+
+/// Deletes the sidecar manifest of a backup that is being removed
+pub fn remove_manifest(backup_path: &Path) {
+    let path = manifest_path(backup_path);
+    if path.exists() {
+        if let Err(e) = fs::remove_file(&path) {
+            eprintln!("Warning: Failed to delete manifest {}: {}", path.display(), e);
+        }
+    }
+}
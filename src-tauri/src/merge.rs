@@ -0,0 +1,832 @@
+use rusqlite::types::Value;
+use rusqlite::{params, Connection, DatabaseName, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::operations::{self, Operation, OperationKind};
+use crate::{archive, get_db_path, incremental, schema, verify, write_safety_backup};
+
+// ============================================
+// MERGE IMPORT FROM ANOTHER TERMINAL
+// ============================================
+//
+// Folds the sales recorded on another MotorMods database (e.g. a laptop used at a
+// roadside event) into the live one without replacing anything. Rows are matched by
+// their UUID: new rows are copied, identical rows are skipped and rows whose content
+// differs are reported as conflicts and left alone. Invoices and returns are merged
+// together with their line items, or not at all.
+//
+// Every stock change the app makes is logged in `stock_adjustments` with its signed
+// quantity, so product stock is updated from the merged adjustments rather than from
+// the invoice and return items, which would count each sale twice. Adjustments of an
+// invoice or return that was left out (their `reference_id`) are left out with it.
+
+/// Parent tables merged together with their line items: (parent, child, column referencing the parent)
+const MERGED_GROUPS: &[(&str, &str, &str)] = &[
+    ("invoices", "invoice_items", "invoice_id"),
+    ("sales_returns", "return_items", "return_id"),
+];
+
+const MAX_REPORTED_CONFLICTS: usize = 200;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MergeConflict {
+    pub table: String,
+    pub id: String,
+    pub reason: String,
+    /// Columns whose values differ between the two databases
+    pub columns: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MergeTableResult {
+    pub table: String,
+    pub imported: usize,
+    pub already_present: usize,
+    pub conflicts: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StockChange {
+    pub product_id: String,
+    pub product_name: Option<String>,
+    pub delta: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MergeReport {
+    pub success: bool,
+    /// Nothing was written; the report shows what a merge would do
+    pub dry_run: bool,
+    pub message: String,
+    pub safety_backup: Option<String>,
+    pub tables: Vec<MergeTableResult>,
+    /// Products only the other database had, copied with its stock level
+    pub products_added: Vec<String>,
+    pub stock_changes: Vec<StockChange>,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// How a row of the other database relates to the live one
+enum Presence {
+    Missing,
+    Same,
+    Different(Vec<String>),
+}
+
+struct Merger<'a> {
+    conn: &'a Connection,
+    columns: BTreeMap<&'static str, Vec<String>>,
+    tables: BTreeMap<&'static str, MergeTableResult>,
+    conflicts: Vec<MergeConflict>,
+    conflict_count: usize,
+    products_added: Vec<String>,
+    stock_deltas: BTreeMap<String, i64>,
+    /// Invoices and returns not merged, with their table
+    skipped_groups: HashMap<String, String>,
+}
+
+impl<'a> Merger<'a> {
+    fn new(conn: &'a Connection) -> Result<Self, String> {
+        let mut columns = BTreeMap::new();
+        let mut tables = BTreeMap::new();
+
+        for table in [
+            "products",
+            "invoices",
+            "invoice_items",
+            "sales_returns",
+            "return_items",
+            "stock_adjustments",
+        ] {
+            let live = incremental::table_columns(conn, "main", table)?;
+            let common: Vec<String> = incremental::table_columns(conn, "other", table)?
+                .into_iter()
+                .filter(|c| live.contains(c))
+                .collect();
+            columns.insert(table, common);
+
+            if table != "products" {
+                tables.insert(
+                    table,
+                    MergeTableResult {
+                        table: table.to_string(),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+
+        Ok(Merger {
+            conn,
+            columns,
+            tables,
+            conflicts: Vec::new(),
+            conflict_count: 0,
+            products_added: Vec::new(),
+            stock_deltas: BTreeMap::new(),
+            skipped_groups: HashMap::new(),
+        })
+    }
+
+    fn has_table(&self, table: &str) -> bool {
+        self.columns
+            .get(table)
+            .is_some_and(|c| c.iter().any(|name| name == "id"))
+    }
+
+    fn ids(&self, table: &str, filter: Option<(&str, &str)>) -> Result<Vec<String>, String> {
+        let (sql, args): (String, Vec<&str>) = match filter {
+            Some((column, value)) => (
+                format!(
+                    "SELECT id FROM other.{} WHERE {} = ?1 ORDER BY rowid",
+                    table, column
+                ),
+                vec![value],
+            ),
+            None => (
+                format!("SELECT id FROM other.{} ORDER BY rowid", table),
+                vec![],
+            ),
+        };
+
+        let mut stmt = self
+            .conn
+            .prepare(&sql)
+            .map_err(|e| format!("Failed to read {}: {}", table, e))?;
+        let ids = stmt
+            .query_map(rusqlite::params_from_iter(args), |row| row.get(0))
+            .map_err(|e| format!("Failed to read {}: {}", table, e))?
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(|e| format!("Failed to read {}: {}", table, e))?;
+        Ok(ids)
+    }
+
+    fn row(&self, schema: &str, table: &str, id: &str) -> Result<Option<Vec<Value>>, String> {
+        let columns = &self.columns[table];
+        let sql = format!(
+            "SELECT {} FROM {}.{} WHERE id = ?1",
+            columns.join(", "),
+            schema,
+            table
+        );
+
+        self.conn
+            .query_row(&sql, params![id], |row| {
+                (0..columns.len())
+                    .map(|i| row.get::<_, Value>(i))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .optional()
+            .map_err(|e| format!("Failed to read {} {}: {}", table, id, e))
+    }
+
+    fn compare(&self, table: &str, id: &str) -> Result<Presence, String> {
+        let Some(live) = self.row("main", table, id)? else {
+            return Ok(Presence::Missing);
+        };
+        let other = self.row("other", table, id)?.unwrap_or_default();
+
+        let differing: Vec<String> = self.columns[table]
+            .iter()
+            .zip(live.iter().zip(other.iter()))
+            .filter(|(_, (l, o))| l != o)
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        Ok(if differing.is_empty() {
+            Presence::Same
+        } else {
+            Presence::Different(differing)
+        })
+    }
+
+    fn copy(&self, table: &str, id: &str) -> Result<(), String> {
+        let columns = self.columns[table].join(", ");
+        self.conn
+            .execute(
+                &format!(
+                    "INSERT INTO main.{t} ({c}) SELECT {c} FROM other.{t} WHERE id = ?1",
+                    t = table,
+                    c = columns
+                ),
+                params![id],
+            )
+            .map(|_| ())
+            .map_err(|e| format!("Failed to import {} {}: {}", table, id, e))
+    }
+
+    fn value(&self, table: &str, column: &str, id: &str) -> Result<Option<String>, String> {
+        self.conn
+            .query_row(
+                &format!("SELECT {} FROM other.{} WHERE id = ?1", column, table),
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map(|v| v.flatten())
+            .map_err(|e| format!("Failed to read {} {}: {}", table, id, e))
+    }
+
+    fn exists_live(&self, table: &str, id: &str) -> Result<bool, String> {
+        self.conn
+            .query_row(
+                &format!("SELECT 1 FROM main.{} WHERE id = ?1", table),
+                params![id],
+                |_| Ok(()),
+            )
+            .optional()
+            .map(|r| r.is_some())
+            .map_err(|e| format!("Failed to look up {} {}: {}", table, id, e))
+    }
+
+    fn conflict(&mut self, table: &str, id: &str, reason: &str, columns: Vec<String>) {
+        if let Some(result) = self.tables.get_mut(table) {
+            result.conflicts += 1;
+        }
+        self.conflict_count += 1;
+        if self.conflicts.len() < MAX_REPORTED_CONFLICTS {
+            self.conflicts.push(MergeConflict {
+                table: table.to_string(),
+                id: id.to_string(),
+                reason: reason.to_string(),
+                columns,
+            });
+        }
+    }
+
+    /// Reports an invoice or return that is not merged, so its stock adjustments are skipped too
+    fn reject_group(&mut self, parent: &str, id: &str, reason: &str, columns: Vec<String>) {
+        self.conflict(parent, id, reason, columns);
+        self.skipped_groups
+            .insert(id.to_string(), parent.to_string());
+    }
+
+    fn count(&mut self, table: &str, imported: bool) {
+        if let Some(result) = self.tables.get_mut(table) {
+            if imported {
+                result.imported += 1;
+            } else {
+                result.already_present += 1;
+            }
+        }
+    }
+
+    /// Makes sure a referenced product exists, copying it from the other database if
+    /// only that one has it. Returns false if neither has it.
+    fn ensure_product(&mut self, product_id: &str) -> Result<bool, String> {
+        if self.exists_live("products", product_id)? {
+            return Ok(true);
+        }
+        if self.row("other", "products", product_id)?.is_none() {
+            return Ok(false);
+        }
+
+        self.copy("products", product_id)?;
+        self.products_added.push(product_id.to_string());
+        Ok(true)
+    }
+
+    /// Merges a parent row together with its line items
+    fn merge_group(
+        &mut self,
+        parent: &str,
+        child: &str,
+        column: &str,
+        id: &str,
+    ) -> Result<(), String> {
+        let children = if self.has_table(child) {
+            self.ids(child, Some((column, id)))?
+        } else {
+            Vec::new()
+        };
+
+        match self.compare(parent, id)? {
+            Presence::Different(columns) => {
+                self.reject_group(parent, id, "Exists with different content", columns);
+                return Ok(());
+            }
+            Presence::Same => {
+                self.count(parent, false);
+                // Items added to or changed on an invoice both databases already have
+                // cannot be merged without rewriting that invoice
+                let mut rejected = false;
+                for child_id in &children {
+                    match self.compare(child, child_id)? {
+                        Presence::Same => self.count(child, false),
+                        Presence::Missing => {
+                            self.conflict(
+                                child,
+                                child_id,
+                                &format!("Belongs to {} {}, which already exists", parent, id),
+                                Vec::new(),
+                            );
+                            rejected = true;
+                        }
+                        Presence::Different(columns) => {
+                            self.conflict(
+                                child,
+                                child_id,
+                                "Exists with different content",
+                                columns,
+                            );
+                            rejected = true;
+                        }
+                    }
+                }
+                if rejected {
+                    self.skipped_groups
+                        .insert(id.to_string(), parent.to_string());
+                }
+                return Ok(());
+            }
+            Presence::Missing => {}
+        }
+
+        // A new group is only imported if all of its items can be
+        for child_id in &children {
+            if self.exists_live(child, child_id)? {
+                self.reject_group(
+                    parent,
+                    id,
+                    &format!("Its item {} already exists under another record", child_id),
+                    Vec::new(),
+                );
+                return Ok(());
+            }
+        }
+
+        if parent == "sales_returns" {
+            let invoice = self.value(parent, "invoice_id", id)?.unwrap_or_default();
+            if !self.exists_live("invoices", &invoice)? {
+                self.reject_group(
+                    parent,
+                    id,
+                    &format!("Its invoice {} is not in this database", invoice),
+                    Vec::new(),
+                );
+                return Ok(());
+            }
+
+            // Return numbers are a per-day sequence, so two terminals can hand out the same one
+            let return_no = self.value(parent, "return_no", id)?.unwrap_or_default();
+            let taken = self
+                .conn
+                .query_row(
+                    "SELECT 1 FROM main.sales_returns WHERE return_no = ?1",
+                    params![return_no],
+                    |_| Ok(()),
+                )
+                .optional()
+                .map_err(|e| format!("Failed to look up return {}: {}", return_no, e))?
+                .is_some();
+            if taken {
+                self.reject_group(
+                    parent,
+                    id,
+                    &format!("Return number {} is already used here", return_no),
+                    vec!["return_no".to_string()],
+                );
+                return Ok(());
+            }
+        }
+
+        for child_id in &children {
+            let product = self
+                .value(child, "product_id", child_id)?
+                .unwrap_or_default();
+            if !self.ensure_product(&product)? {
+                self.reject_group(
+                    parent,
+                    id,
+                    &format!("References unknown product {}", product),
+                    Vec::new(),
+                );
+                return Ok(());
+            }
+        }
+
+        self.copy(parent, id)?;
+        self.count(parent, true);
+        for child_id in &children {
+            self.copy(child, child_id)?;
+            self.count(child, true);
+        }
+
+        Ok(())
+    }
+
+    fn merge_adjustment(&mut self, id: &str) -> Result<(), String> {
+        match self.compare("stock_adjustments", id)? {
+            Presence::Same => {
+                self.count("stock_adjustments", false);
+                return Ok(());
+            }
+            Presence::Different(columns) => {
+                self.conflict(
+                    "stock_adjustments",
+                    id,
+                    "Exists with different content",
+                    columns,
+                );
+                return Ok(());
+            }
+            Presence::Missing => {}
+        }
+
+        // Stock moved by an invoice or return left out of the merge does not move here either
+        let reference = self.value("stock_adjustments", "reference_id", id)?;
+        if let Some(reference) = reference {
+            if let Some(parent) = self.skipped_groups.get(&reference).cloned() {
+                self.conflict(
+                    "stock_adjustments",
+                    id,
+                    &format!("Belongs to {} {}, which was not merged", parent, reference),
+                    Vec::new(),
+                );
+                return Ok(());
+            }
+        }
+
+        let product = self
+            .value("stock_adjustments", "product_id", id)?
+            .unwrap_or_default();
+        if !self.ensure_product(&product)? {
+            self.conflict(
+                "stock_adjustments",
+                id,
+                &format!("References unknown product {}", product),
+                Vec::new(),
+            );
+            return Ok(());
+        }
+
+        self.copy("stock_adjustments", id)?;
+        self.count("stock_adjustments", true);
+
+        // Products copied in this merge already carry the other terminal's stock level
+        if !self.products_added.contains(&product) {
+            let quantity: i64 = self
+                .conn
+                .query_row(
+                    "SELECT quantity FROM other.stock_adjustments WHERE id = ?1",
+                    params![id],
+                    |row| row.get(0),
+                )
+                .map_err(|e| format!("Failed to read stock adjustment {}: {}", id, e))?;
+            *self.stock_deltas.entry(product).or_default() += quantity;
+        }
+
+        Ok(())
+    }
+
+    fn apply_stock_changes(&self) -> Result<Vec<StockChange>, String> {
+        let mut changes = Vec::new();
+
+        for (product_id, delta) in &self.stock_deltas {
+            if *delta == 0 {
+                continue;
+            }
+            self.conn
+                .execute(
+                    "UPDATE main.products SET quantity = quantity + ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
+                    params![delta, product_id],
+                )
+                .map_err(|e| format!("Failed to update stock of {}: {}", product_id, e))?;
+
+            let product_name = self
+                .conn
+                .query_row(
+                    "SELECT name FROM main.products WHERE id = ?1",
+                    params![product_id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| format!("Failed to read product {}: {}", product_id, e))?;
+
+            changes.push(StockChange {
+                product_id: product_id.clone(),
+                product_name,
+                delta: *delta,
+            });
+        }
+
+        Ok(changes)
+    }
+
+    /// Moves `last_sale_date` forward for products sold on the other terminal
+    fn update_last_sale_dates(&self, imported_invoices: &HashSet<String>) -> Result<(), String> {
+        if !self.columns["products"]
+            .iter()
+            .any(|c| c == "last_sale_date")
+        {
+            return Ok(());
+        }
+
+        for invoice in imported_invoices {
+            self.conn
+                .execute(
+                    "UPDATE main.products SET last_sale_date = i.created_at
+                     FROM (SELECT ii.product_id, inv.created_at FROM main.invoice_items ii
+                           JOIN main.invoices inv ON inv.id = ii.invoice_id
+                           WHERE inv.id = ?1) AS i
+                     WHERE main.products.id = i.product_id
+                       AND (main.products.last_sale_date IS NULL OR main.products.last_sale_date < i.created_at)",
+                    params![invoice],
+                )
+                .map_err(|e| format!("Failed to update last sale dates: {}", e))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Merges the attached `other` database into `conn`. Stops with the cancellation error
+/// between rows; the caller's transaction is then rolled back.
+fn run_merge(conn: &Connection, operation: Option<&Operation>) -> Result<MergeReport, String> {
+    let mut merger = Merger::new(conn)?;
+    let progress = |table: &str, done: usize, total: usize| -> Result<(), String> {
+        if let Some(op) = operation {
+            op.report("importing", Some(table), done as u64, total as u64);
+            op.check_cancelled()?;
+        }
+        Ok(())
+    };
+
+    let mut imported_invoices = HashSet::new();
+    for (parent, child, column) in MERGED_GROUPS {
+        if !merger.has_table(parent) {
+            continue;
+        }
+        let ids = merger.ids(parent, None)?;
+        for (i, id) in ids.iter().enumerate() {
+            progress(parent, i, ids.len())?;
+            let before = merger.tables[parent].imported;
+            merger.merge_group(parent, child, column, id)?;
+            if *parent == "invoices" && merger.tables[parent].imported > before {
+                imported_invoices.insert(id.clone());
+            }
+        }
+        progress(parent, ids.len(), ids.len())?;
+    }
+
+    if merger.has_table("stock_adjustments") {
+        let ids = merger.ids("stock_adjustments", None)?;
+        for (i, id) in ids.iter().enumerate() {
+            progress("stock_adjustments", i, ids.len())?;
+            merger.merge_adjustment(id)?;
+        }
+        progress("stock_adjustments", ids.len(), ids.len())?;
+    }
+
+    let stock_changes = merger.apply_stock_changes()?;
+    merger.update_last_sale_dates(&imported_invoices)?;
+
+    let imported: usize = merger.tables.values().map(|t| t.imported).sum();
+    let mut message = format!(
+        "Merged {} new records ({} invoices, {} returns)",
+        imported, merger.tables["invoices"].imported, merger.tables["sales_returns"].imported
+    );
+    if merger.conflict_count > 0 {
+        message.push_str(&format!(
+            ". {} conflicting records were left unchanged",
+            merger.conflict_count
+        ));
+    }
+
+    Ok(MergeReport {
+        success: true,
+        dry_run: false,
+        message,
+        safety_backup: None,
+        tables: [
+            "invoices",
+            "invoice_items",
+            "sales_returns",
+            "return_items",
+            "stock_adjustments",
+        ]
+        .iter()
+        .filter_map(|t| merger.tables.remove(t))
+        .collect(),
+        products_added: merger.products_added,
+        stock_changes,
+        conflicts: merger.conflicts,
+    })
+}
+
+fn open_live(db_path: &Path) -> Result<Connection, String> {
+    let conn =
+        Connection::open(db_path).map_err(|e| format!("Failed to open main database: {}", e))?;
+    conn.busy_timeout(Duration::from_secs(5))
+        .map_err(|e| format!("Failed to set busy timeout: {}", e))?;
+    Ok(conn)
+}
+
+/// Merges invoices, returns and stock adjustments from another MotorMods database into
+/// the live one, matching rows by id and updating product stock from the merged
+/// adjustments. With `dry_run` the merge runs on a temporary copy of the live database
+/// and only the report is returned.
+/// Refuses files that fail verification unless `force` is set.
+/// Runs in the background, emitting `operation-progress` events for `operation_id`
+#[tauri::command]
+pub async fn merge_import(
+    app: AppHandle,
+    source_path: String,
+    dry_run: Option<bool>,
+    force: Option<bool>,
+    passphrase: Option<String>,
+    operation_id: Option<String>,
+) -> Result<MergeReport, String> {
+    operations::run_blocking(app, operation_id, OperationKind::Restore, move |app, op| {
+        merge_file(
+            app,
+            &source_path,
+            dry_run.unwrap_or(false),
+            force,
+            passphrase.as_deref(),
+            op,
+        )
+    })
+    .await
+}
+
+fn merge_file(
+    app: &AppHandle,
+    source_path: &str,
+    dry_run: bool,
+    force: Option<bool>,
+    passphrase: Option<&str>,
+    operation: &Operation,
+) -> Result<MergeReport, String> {
+    let source = Path::new(source_path);
+    if !source.is_file() {
+        return Err(format!("File not found: {}", source_path));
+    }
+
+    // A dry run merges into a temporary copy of the live database and writes nothing
+    // to the live one, so it can run alongside other operations
+    let _lock = if dry_run {
+        None
+    } else {
+        Some(operations::lock(
+            app,
+            &format!("Merging {}", operations::file_label(source)),
+            None,
+        )?)
+    };
+
+    let db_path = get_db_path(app)?;
+    if source.canonicalize().ok() == db_path.canonicalize().ok() {
+        return Err("Cannot merge the live database into itself".to_string());
+    }
+
+    operation.report("preparing", None, 0, 1);
+    let unpacked = incremental::materialize_complete(source, passphrase)?;
+    verify::ensure_verified(source, unpacked.path(), force)?;
+
+    let upgraded = schema::upgraded_copy(unpacked.path())?;
+    let other_db = upgraded.as_ref().map_or(unpacked.path(), |u| u.path());
+
+    let live = open_live(&db_path)?;
+    let preview = if dry_run {
+        let copy = archive::MaterializedBackup::temporary("merge_preview")?;
+        live.backup(DatabaseName::Main, copy.path(), None)
+            .map_err(|e| format!("Failed to copy live database: {}", e))?;
+        Some(copy)
+    } else {
+        None
+    };
+    let conn = match &preview {
+        Some(copy) => {
+            drop(live);
+            Connection::open(copy.path())
+                .map_err(|e| format!("Failed to open copy of live database: {}", e))?
+        }
+        None => live,
+    };
+
+    conn.execute(
+        "ATTACH DATABASE ?1 AS other",
+        params![other_db.to_string_lossy()],
+    )
+    .map_err(|e| format!("Failed to attach database to merge: {}", e))?;
+
+    operation.check_cancelled()?;
+    let safety_backup = if dry_run {
+        None
+    } else {
+        Some(write_safety_backup(app, "pre_import_safety_")?)
+    };
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let mut report = run_merge(&tx, Some(operation))?;
+
+    if dry_run {
+        report.dry_run = true;
+        report.message = format!("Dry run: {}", report.message);
+    } else {
+        tx.commit()
+            .map_err(|e| format!("Failed to commit merge: {}", e))?;
+        report.safety_backup = safety_backup;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run_merge;
+    use crate::archive::MaterializedBackup;
+    use crate::migrations::migrate;
+    use rusqlite::{params, Connection};
+
+    /// A migrated database holding one product with 10 in stock and one invoice for it
+    fn shop(conn: &Connection) {
+        migrate(conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO products (id, name, price, quantity, created_at, updated_at)
+                 VALUES ('p1', 'Brake pad', 100, 10, '2026-10-01', '2026-10-01');
+             INSERT INTO invoices (id, customer_name, total_amount, created_at)
+                 VALUES ('inv1', 'Ravi', 200, '2026-10-01');
+             INSERT INTO invoice_items (id, invoice_id, product_id, quantity, price, created_at)
+                 VALUES ('item1', 'inv1', 'p1', 2, 100, '2026-10-01');",
+        )
+        .unwrap();
+    }
+
+    fn add_return(conn: &Connection, id: &str, return_no: &str) {
+        conn.execute(
+            "INSERT INTO sales_returns (id, return_no, invoice_id, return_date, reason, total_amount)
+             VALUES (?1, ?2, 'inv1', '2026-10-02', 'defective', 100)",
+            params![id, return_no],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO return_items (id, return_id, product_id, quantity, rate, line_total)
+             VALUES (?1, ?2, 'p1', 1, 100, 100)",
+            params![format!("{}-item", id), id],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO stock_adjustments (id, product_id, adjustment_type, quantity, notes, reference_id, created_at)
+             VALUES (?1, 'p1', 'return', 1, ?2, ?3, '2026-10-02')",
+            params![format!("{}-adj", id), format!("Return {}: Brake pad", return_no), id],
+        )
+        .unwrap();
+        conn.execute("UPDATE products SET quantity = quantity + 1", [])
+            .unwrap();
+    }
+
+    #[test]
+    fn stock_of_a_rejected_return_is_not_merged() {
+        let live = Connection::open_in_memory().unwrap();
+        shop(&live);
+        add_return(&live, "ret-live", "RET-20261002-001");
+
+        let laptop_file = MaterializedBackup::temporary("merge_test").unwrap();
+        {
+            let laptop = Connection::open(laptop_file.path()).unwrap();
+            shop(&laptop);
+            add_return(&laptop, "ret-laptop", "RET-20261002-001");
+        }
+
+        live.execute(
+            "ATTACH DATABASE ?1 AS other",
+            params![laptop_file.path().to_string_lossy()],
+        )
+        .unwrap();
+        let report = run_merge(&live, None).unwrap();
+
+        let quantity: i64 = live
+            .query_row(
+                "SELECT quantity FROM main.products WHERE id = 'p1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(quantity, 11);
+
+        let copied: i64 = live
+            .query_row(
+                "SELECT COUNT(*) FROM main.stock_adjustments WHERE id = 'ret-laptop-adj'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(copied, 0);
+        assert!(report
+            .conflicts
+            .iter()
+            .any(|c| c.table == "sales_returns" && c.id == "ret-laptop"));
+        assert!(report
+            .conflicts
+            .iter()
+            .any(|c| c.table == "stock_adjustments" && c.id == "ret-laptop-adj"));
+    }
+}
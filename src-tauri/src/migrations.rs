@@ -0,0 +1,782 @@
+use rusqlite::{Connection, OpenFlags, Transaction};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+// ============================================
+// SCHEMA MIGRATIONS
+// ============================================
+//
+// The backend owns the database schema. Migrations run in order, each in its own
+// transaction, and the version reached is stored in `PRAGMA user_version`, so a
+// migration never runs twice. The live database is migrated at startup and after a
+// full restore; older backups are migrated on a temporary copy before their rows are
+// imported (see schema.rs).
+//
+// Databases created before versioning have `user_version` 0 and any subset of the
+// early schema, so the first migrations only create or add what is missing.
+// New migrations are appended; released ones are never edited or reordered.
+// The incremental backup change log is not listed here: it is installed and removed
+// with its triggers when the backup mode changes (see incremental.rs).
+
+/// A single step of the schema history. `version` is the `user_version` it leaves behind.
+struct Migration {
+    version: u32,
+    description: &'static str,
+    apply: fn(&Transaction) -> Result<(), String>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create core tables",
+        apply: create_core_tables,
+    },
+    Migration {
+        version: 2,
+        description: "add columns introduced after the first release",
+        apply: add_later_columns,
+    },
+    Migration {
+        version: 3,
+        description: "make products.sku optional",
+        apply: make_products_sku_nullable,
+    },
+    Migration {
+        version: 4,
+        description: "create indexes",
+        apply: create_indexes,
+    },
+    Migration {
+        version: 5,
+        description: "add default settings and administrator",
+        apply: seed_defaults,
+    },
+    Migration {
+        version: 6,
+        description: "create backup mirror and offsite upload tables",
+        apply: create_backup_status_tables,
+    },
+    Migration {
+        version: 7,
+        description: "track who cancelled or amended a sales return",
+        apply: add_return_change_tracking,
+    },
+    Migration {
+        version: 8,
+        description: "add safety backup retention settings",
+        apply: add_safety_backup_settings,
+    },
+    Migration {
+        version: 9,
+        description: "link stock adjustments to their invoice or return",
+        apply: add_adjustment_references,
+    },
+];
+
+/// Schema version this build of the app creates and understands
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+const CORE_TABLES: &str = "
+    CREATE TABLE IF NOT EXISTS products (
+      id TEXT PRIMARY KEY,
+      name TEXT NOT NULL,
+      sku TEXT,
+      category TEXT,
+      price REAL NOT NULL,
+      quantity INTEGER NOT NULL DEFAULT 0,
+      barcode TEXT,
+      purchase_price REAL DEFAULT 0,
+      reorder_level INTEGER DEFAULT 5,
+      max_stock INTEGER,
+      last_sale_date TEXT,
+      fsn_classification TEXT,
+      created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+      updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+    );
+
+    CREATE TABLE IF NOT EXISTS invoices (
+      id TEXT PRIMARY KEY,
+      customer_name TEXT NOT NULL,
+      customer_phone TEXT,
+      discount_amount REAL NOT NULL DEFAULT 0,
+      total_amount REAL NOT NULL,
+      payment_mode TEXT DEFAULT 'cash',
+      is_return INTEGER DEFAULT 0,
+      original_invoice_id TEXT,
+      return_reason TEXT,
+      created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+      FOREIGN KEY(original_invoice_id) REFERENCES invoices(id)
+    );
+
+    CREATE TABLE IF NOT EXISTS invoice_items (
+      id TEXT PRIMARY KEY,
+      invoice_id TEXT NOT NULL,
+      product_id TEXT NOT NULL,
+      quantity INTEGER NOT NULL,
+      price REAL NOT NULL,
+      cost_price REAL NOT NULL DEFAULT 0,
+      created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+      FOREIGN KEY(invoice_id) REFERENCES invoices(id),
+      FOREIGN KEY(product_id) REFERENCES products(id)
+    );
+
+    CREATE TABLE IF NOT EXISTS settings (
+      id INTEGER PRIMARY KEY,
+      key TEXT UNIQUE NOT NULL,
+      value TEXT,
+      updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+    );
+
+    CREATE TABLE IF NOT EXISTS stock_adjustments (
+      id TEXT PRIMARY KEY,
+      product_id TEXT NOT NULL,
+      adjustment_type TEXT NOT NULL,
+      quantity INTEGER NOT NULL,
+      notes TEXT,
+      created_by TEXT DEFAULT 'system',
+      created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+      FOREIGN KEY(product_id) REFERENCES products(id)
+    );
+
+    CREATE TABLE IF NOT EXISTS sales_returns (
+      id TEXT PRIMARY KEY,
+      return_no TEXT UNIQUE NOT NULL,
+      invoice_id TEXT NOT NULL,
+      return_date TEXT NOT NULL,
+      reason TEXT NOT NULL,
+      total_amount REAL NOT NULL,
+      notes TEXT,
+      status TEXT DEFAULT 'completed',
+      created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+      updated_at TEXT DEFAULT CURRENT_TIMESTAMP,
+      FOREIGN KEY(invoice_id) REFERENCES invoices(id)
+    );
+
+    CREATE TABLE IF NOT EXISTS return_items (
+      id TEXT PRIMARY KEY,
+      return_id TEXT NOT NULL,
+      product_id TEXT NOT NULL,
+      quantity INTEGER NOT NULL,
+      rate REAL NOT NULL,
+      line_total REAL NOT NULL,
+      FOREIGN KEY(return_id) REFERENCES sales_returns(id) ON DELETE CASCADE,
+      FOREIGN KEY(product_id) REFERENCES products(id)
+    );
+
+    CREATE TABLE IF NOT EXISTS backup_log (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      backup_file TEXT NOT NULL,
+      backup_date TEXT NOT NULL,
+      backup_type TEXT DEFAULT 'auto',
+      file_size INTEGER,
+      status TEXT DEFAULT 'success',
+      notes TEXT,
+      created_at TEXT DEFAULT CURRENT_TIMESTAMP
+    );
+
+    CREATE TABLE IF NOT EXISTS users (
+      id TEXT PRIMARY KEY,
+      username TEXT UNIQUE NOT NULL,
+      password_hash TEXT NOT NULL,
+      role TEXT NOT NULL DEFAULT 'staff',
+      name TEXT NOT NULL,
+      is_active INTEGER DEFAULT 1,
+      created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+      updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+    );
+";
+
+fn create_core_tables(tx: &Transaction) -> Result<(), String> {
+    tx.execute_batch(CORE_TABLES)
+        .map_err(|e| format!("Failed to create tables: {}", e))
+}
+
+/// Columns added after the first release, checked one by one since databases from
+/// before versioning may have any of them
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("products", "category", "TEXT"),
+    ("products", "barcode", "TEXT"),
+    ("products", "purchase_price", "REAL DEFAULT 0"),
+    ("products", "reorder_level", "INTEGER DEFAULT 5"),
+    ("products", "max_stock", "INTEGER"),
+    ("products", "last_sale_date", "TEXT"),
+    ("products", "fsn_classification", "TEXT"),
+    ("invoices", "discount_amount", "REAL NOT NULL DEFAULT 0"),
+    ("invoices", "customer_phone", "TEXT"),
+    ("invoices", "payment_mode", "TEXT DEFAULT 'cash'"),
+    ("invoices", "is_return", "INTEGER DEFAULT 0"),
+    ("invoices", "original_invoice_id", "TEXT"),
+    ("invoices", "return_reason", "TEXT"),
+    ("invoice_items", "cost_price", "REAL NOT NULL DEFAULT 0"),
+];
+
+fn add_later_columns(tx: &Transaction) -> Result<(), String> {
+    for (table, column, definition) in ADDED_COLUMNS {
+        let columns = crate::incremental::table_columns(tx, "main", table)?;
+        if columns.iter().any(|c| c == column) {
+            continue;
+        }
+        tx.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )
+        .map_err(|e| format!("Failed to add {}.{}: {}", table, column, e))?;
+    }
+    Ok(())
+}
+
+/// Recreates `products` with a nullable `sku`; SQLite cannot drop a NOT NULL constraint
+const PRODUCTS_SKU_NULLABLE: &str = "
+    ALTER TABLE products RENAME TO products_old;
+
+    CREATE TABLE products (
+      id TEXT PRIMARY KEY,
+      name TEXT NOT NULL,
+      sku TEXT,
+      category TEXT,
+      price REAL NOT NULL,
+      quantity INTEGER NOT NULL DEFAULT 0,
+      barcode TEXT,
+      purchase_price REAL DEFAULT 0,
+      reorder_level INTEGER DEFAULT 5,
+      max_stock INTEGER,
+      last_sale_date TEXT,
+      fsn_classification TEXT,
+      created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+      updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+    );
+
+    INSERT INTO products (id, name, sku, category, price, quantity, barcode, purchase_price, reorder_level, max_stock, last_sale_date, fsn_classification, created_at, updated_at)
+    SELECT id, name, sku, category, price, quantity, barcode, COALESCE(purchase_price, 0), COALESCE(reorder_level, 5), max_stock, last_sale_date, fsn_classification, created_at, updated_at
+    FROM products_old;
+
+    DROP TABLE products_old;
+";
+
+fn make_products_sku_nullable(tx: &Transaction) -> Result<(), String> {
+    let sku_not_null: bool = tx
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('products') WHERE name = 'sku' AND \"notnull\" = 1",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to inspect products table: {}", e))?;
+
+    if !sku_not_null {
+        return Ok(());
+    }
+
+    tx.execute_batch(PRODUCTS_SKU_NULLABLE)
+        .map_err(|e| format!("Failed to rebuild products table: {}", e))
+}
+
+const INDEXES: &str = "
+    CREATE INDEX IF NOT EXISTS idx_products_sku ON products(sku);
+    CREATE INDEX IF NOT EXISTS idx_products_category ON products(category);
+    CREATE INDEX IF NOT EXISTS idx_products_barcode ON products(barcode);
+    CREATE INDEX IF NOT EXISTS idx_products_last_sale_date ON products(last_sale_date);
+    CREATE INDEX IF NOT EXISTS idx_invoice_items_invoice ON invoice_items(invoice_id);
+    CREATE INDEX IF NOT EXISTS idx_invoice_items_product ON invoice_items(product_id);
+    CREATE INDEX IF NOT EXISTS idx_invoices_created_at ON invoices(created_at);
+    CREATE INDEX IF NOT EXISTS idx_stock_adjustments_product ON stock_adjustments(product_id);
+    CREATE INDEX IF NOT EXISTS idx_stock_adjustments_date ON stock_adjustments(created_at);
+    CREATE INDEX IF NOT EXISTS idx_stock_adjustments_type ON stock_adjustments(adjustment_type);
+    CREATE INDEX IF NOT EXISTS idx_sales_returns_invoice ON sales_returns(invoice_id);
+    CREATE INDEX IF NOT EXISTS idx_sales_returns_date ON sales_returns(return_date);
+    CREATE INDEX IF NOT EXISTS idx_return_items_return ON return_items(return_id);
+    CREATE INDEX IF NOT EXISTS idx_backup_log_date ON backup_log(backup_date);
+    CREATE INDEX IF NOT EXISTS idx_users_username ON users(username);
+    CREATE INDEX IF NOT EXISTS idx_users_role ON users(role);
+";
+
+fn create_indexes(tx: &Transaction) -> Result<(), String> {
+    tx.execute_batch(INDEXES)
+        .map_err(|e| format!("Failed to create indexes: {}", e))
+}
+
+const DEFAULT_SETTINGS: &[(&str, &str)] = &[
+    ("low_stock_method", "reorder_level"),
+    ("low_stock_percentage", "20"),
+    ("low_stock_days_supply", "15"),
+    ("non_moving_threshold_days", "120"),
+    ("auto_backup_enabled", "1"),
+    ("auto_backup_time", "23:00"),
+    ("auto_backup_frequency", "daily"),
+    ("auto_backup_day", "0"),
+    ("backup_retention_days", "30"),
+    ("backup_keep_daily", "7"),
+    ("backup_keep_weekly", "4"),
+    ("backup_keep_monthly", "12"),
+    ("backup_compression", "none"),
+    ("backup_compression_level", "3"),
+    ("backup_mode", "full"),
+    ("backup_full_interval_days", "7"),
+];
+
+/// Default admin: username "admin", password "admin123" (SHA-256 hash)
+const DEFAULT_ADMIN_HASH: &str = "240be518fabd2724ddb6f04eeb1da5967448d7e831c08c8fa822809f74c720a9";
+
+fn seed_defaults(tx: &Transaction) -> Result<(), String> {
+    for (key, value) in DEFAULT_SETTINGS {
+        tx.execute(
+            "INSERT OR IGNORE INTO settings (key, value) VALUES (?1, ?2)",
+            [key, value],
+        )
+        .map_err(|e| format!("Failed to add default setting {}: {}", key, e))?;
+    }
+
+    tx.execute(
+        "INSERT INTO users (id, username, password_hash, role, name)
+         SELECT 'user-admin-default', 'admin', ?1, 'admin', 'Administrator'
+         WHERE NOT EXISTS (SELECT 1 FROM users)",
+        [DEFAULT_ADMIN_HASH],
+    )
+    .map(|_| ())
+    .map_err(|e| format!("Failed to add default administrator: {}", e))
+}
+
+fn create_backup_status_tables(tx: &Transaction) -> Result<(), String> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS backup_mirror_status (
+            destination TEXT PRIMARY KEY,
+            last_attempt_at TEXT,
+            last_success_at TEXT,
+            last_file TEXT,
+            last_error TEXT
+        );
+        CREATE TABLE IF NOT EXISTS offsite_upload_queue (
+            filename TEXT PRIMARY KEY,
+            queued_at TEXT NOT NULL,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            last_attempt_at TEXT,
+            last_error TEXT
+        );",
+    )
+    .map_err(|e| format!("Failed to create backup status tables: {}", e))
+}
+
+fn add_return_change_tracking(tx: &Transaction) -> Result<(), String> {
+    tx.execute_batch(
+        "ALTER TABLE sales_returns ADD COLUMN cancelled_by TEXT;
+         ALTER TABLE sales_returns ADD COLUMN cancelled_at TEXT;
+         ALTER TABLE sales_returns ADD COLUMN amended_by TEXT;
+         ALTER TABLE sales_returns ADD COLUMN amended_at TEXT;
+         INSERT OR IGNORE INTO settings (key, value) VALUES ('return_grace_hours', '24');",
+    )
+    .map_err(|e| format!("Failed to add return tracking columns: {}", e))
+}
+
+/// Safety backups used to share `backup_retention_days` with the backup log; they start
+/// from that value so an existing setup keeps them as long as before
+fn add_safety_backup_settings(tx: &Transaction) -> Result<(), String> {
+    tx.execute_batch(
+        "INSERT OR IGNORE INTO settings (key, value)
+         SELECT 'safety_backup_max_age_days',
+                COALESCE((SELECT value FROM settings WHERE key = 'backup_retention_days'), '30');
+         INSERT OR IGNORE INTO settings (key, value) VALUES ('safety_backup_keep_count', '3');",
+    )
+    .map_err(|e| format!("Failed to add safety backup settings: {}", e))
+}
+
+/// Adds `stock_adjustments.reference_id`, the invoice or sales return a movement belongs to.
+/// Earlier movements only named it in their notes ("Invoice 1A2B3C4D", "Return RET-...:",
+/// "Cancelled return RET-...", "Amended return RET-...:"); those are linked where the
+/// note matches exactly one record.
+fn add_adjustment_references(tx: &Transaction) -> Result<(), String> {
+    tx.execute_batch(
+        "ALTER TABLE stock_adjustments ADD COLUMN reference_id TEXT;
+         CREATE INDEX IF NOT EXISTS idx_stock_adjustments_reference ON stock_adjustments(reference_id);
+
+         UPDATE stock_adjustments SET reference_id = (
+             SELECT MIN(i.id) FROM invoices i
+             WHERE UPPER(SUBSTR(i.id, 1, 8)) = SUBSTR(stock_adjustments.notes, 9)
+             HAVING COUNT(*) = 1
+         )
+         WHERE adjustment_type = 'sale' AND notes LIKE 'Invoice %';
+
+         UPDATE stock_adjustments SET reference_id = (
+             SELECT MIN(r.id) FROM sales_returns r
+             WHERE stock_adjustments.notes LIKE 'Return ' || r.return_no || ':%'
+                OR stock_adjustments.notes = 'Cancelled return ' || r.return_no
+                OR stock_adjustments.notes LIKE 'Amended return ' || r.return_no || ':%'
+             HAVING COUNT(*) = 1
+         )
+         WHERE adjustment_type IN ('return', 'manual_deduction') AND reference_id IS NULL;",
+    )
+    .map_err(|e| format!("Failed to link stock adjustments: {}", e))
+}
+
+// ============================================
+// RUNNING MIGRATIONS
+// ============================================
+
+/// Reads the schema version stored in the database
+pub fn schema_version(conn: &Connection) -> Result<u32, String> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read schema version: {}", e))
+}
+
+/// Fails if the database was written by a newer app version with a schema this one does not know
+pub fn ensure_supported(conn: &Connection) -> Result<u32, String> {
+    let version = schema_version(conn)?;
+    if version > latest_version() {
+        return Err(format!(
+            "This database was created by a newer version of the app (schema version {}; \
+             this version supports up to {}). Update the app to open it.",
+            version,
+            latest_version()
+        ));
+    }
+    Ok(version)
+}
+
+/// Same as `ensure_supported`, for a database file that is not open yet
+pub fn ensure_file_supported(database_path: &Path) -> Result<u32, String> {
+    let conn = Connection::open_with_flags(database_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    ensure_supported(&conn)
+}
+
+/// Descriptions of the migrations a database at `version` still needs, in order
+pub fn pending_descriptions(version: u32) -> Vec<String> {
+    MIGRATIONS
+        .iter()
+        .filter(|m| m.version > version)
+        .map(|m| format!("v{}: {}", m.version, m.description))
+        .collect()
+}
+
+/// Brings the database up to `latest_version()`. Each migration commits on its own, so a
+/// failure leaves the database at the last version that completed.
+/// Returns the versions that were applied.
+pub fn migrate(conn: &Connection) -> Result<Vec<u32>, String> {
+    let current = ensure_supported(conn)?;
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.version > current).collect();
+    if pending.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Table rebuilds must not rewrite or enforce the foreign keys that point at the
+    // table being replaced; both pragmas are no-ops inside a transaction, so set them here
+    conn.execute_batch("PRAGMA foreign_keys = OFF; PRAGMA legacy_alter_table = ON;")
+        .map_err(|e| format!("Failed to prepare database for migration: {}", e))?;
+
+    let mut applied = Vec::new();
+    let mut result = Ok(());
+    for migration in pending {
+        result = apply(conn, migration);
+        if result.is_err() {
+            break;
+        }
+        applied.push(migration.version);
+    }
+
+    let _ = conn.execute_batch("PRAGMA legacy_alter_table = OFF; PRAGMA foreign_keys = ON;");
+    result.map(|_| applied)
+}
+
+/// Runs one migration and records its version in the same transaction
+fn apply(conn: &Connection, migration: &Migration) -> Result<(), String> {
+    println!(
+        "[Schema] Migrating to version {}: {}",
+        migration.version, migration.description
+    );
+
+    let failed = |e: String| {
+        format!(
+            "Migration to schema version {} failed: {}",
+            migration.version, e
+        )
+    };
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| failed(e.to_string()))?;
+    (migration.apply)(&tx).map_err(failed)?;
+    tx.pragma_update(None, "user_version", migration.version)
+        .map_err(|e| failed(e.to_string()))?;
+    tx.commit().map_err(|e| failed(e.to_string()))
+}
+
+/// Opens (creating if needed) and migrates the database file at `db_path`
+pub fn migrate_file(db_path: &Path) -> Result<Vec<u32>, String> {
+    if let Some(parent) = db_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create database folder: {}", e))?;
+    }
+
+    let conn = Connection::open(db_path).map_err(|e| format!("Failed to open database: {}", e))?;
+    conn.busy_timeout(std::time::Duration::from_secs(5))
+        .map_err(|e| format!("Failed to set busy timeout: {}", e))?;
+    migrate(&conn)
+}
+
+// ============================================
+// STARTUP
+// ============================================
+
+/// Outcome of the migration run at startup, kept so the frontend can refuse to use a
+/// database that could not be brought up to date
+#[derive(Default)]
+pub struct StartupMigration {
+    error: Mutex<Option<String>>,
+}
+
+/// Migrates the live database when the app starts. A failure is logged and recorded
+/// rather than aborting startup, so the window can show it.
+pub fn run_at_startup(db_path: &Path, state: &StartupMigration) {
+    match migrate_file(db_path) {
+        Ok(applied) if !applied.is_empty() => {
+            println!("[Schema] Database migrated to version {}", latest_version());
+        }
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("Error: Database migration failed: {}", e);
+            if let Ok(mut error) = state.error.lock() {
+                *error = Some(e);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaVersion {
+    /// Version stored in the live database
+    pub version: u32,
+    /// Version this build of the app migrates to
+    pub latest: u32,
+}
+
+/// Reports the live database's schema version. Fails if the startup migration failed or
+/// the database is newer than this app understands, in which case it must not be used.
+#[tauri::command]
+pub fn get_schema_version(
+    app: tauri::AppHandle,
+    startup: tauri::State<'_, StartupMigration>,
+) -> Result<SchemaVersion, String> {
+    if let Some(error) = startup.error.lock().ok().and_then(|e| e.clone()) {
+        return Err(error);
+    }
+
+    let db_path = crate::get_db_path(&app)?;
+    let version = ensure_file_supported(&db_path)?;
+    if version < latest_version() {
+        return Err(format!(
+            "The database is at schema version {} but this version of the app needs {}. \
+             Restart the app to upgrade it.",
+            version,
+            latest_version()
+        ));
+    }
+
+    Ok(SchemaVersion {
+        version,
+        latest: latest_version(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{latest_version, migrate, schema_version};
+    use rusqlite::Connection;
+
+    /// Schema of a database the first release created, before versioning: `sku` is
+    /// required and several later columns are missing
+    const LEGACY_SCHEMA: &str = "
+        CREATE TABLE products (
+          id TEXT PRIMARY KEY,
+          name TEXT NOT NULL,
+          sku TEXT NOT NULL,
+          price REAL NOT NULL,
+          quantity INTEGER NOT NULL DEFAULT 0,
+          created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+          updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE TABLE invoices (
+          id TEXT PRIMARY KEY,
+          customer_name TEXT NOT NULL,
+          total_amount REAL NOT NULL,
+          created_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE TABLE invoice_items (
+          id TEXT PRIMARY KEY,
+          invoice_id TEXT NOT NULL,
+          product_id TEXT NOT NULL,
+          quantity INTEGER NOT NULL,
+          price REAL NOT NULL,
+          created_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE TABLE settings (
+          id INTEGER PRIMARY KEY,
+          key TEXT UNIQUE NOT NULL,
+          value TEXT,
+          updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE TABLE stock_adjustments (
+          id TEXT PRIMARY KEY,
+          product_id TEXT NOT NULL,
+          adjustment_type TEXT NOT NULL,
+          quantity INTEGER NOT NULL,
+          notes TEXT,
+          created_by TEXT DEFAULT 'system',
+          created_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE TABLE sales_returns (
+          id TEXT PRIMARY KEY,
+          return_no TEXT UNIQUE NOT NULL,
+          invoice_id TEXT NOT NULL,
+          return_date TEXT NOT NULL,
+          reason TEXT NOT NULL,
+          total_amount REAL NOT NULL,
+          notes TEXT,
+          status TEXT DEFAULT 'completed',
+          created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+          updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE TABLE users (
+          id TEXT PRIMARY KEY,
+          username TEXT UNIQUE NOT NULL,
+          password_hash TEXT NOT NULL,
+          role TEXT NOT NULL DEFAULT 'staff',
+          name TEXT NOT NULL,
+          is_active INTEGER DEFAULT 1,
+          created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+          updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
+
+        INSERT INTO products (id, name, sku, price, quantity) VALUES ('p1', 'Brake pad', 'BP-1', 450, 7);
+        INSERT INTO invoices (id, customer_name, total_amount) VALUES ('a1b2c3d4-0000', 'Ravi', 900);
+        INSERT INTO invoice_items (id, invoice_id, product_id, quantity, price)
+            VALUES ('item1', 'a1b2c3d4-0000', 'p1', 2, 450);
+        INSERT INTO sales_returns (id, return_no, invoice_id, return_date, reason, total_amount)
+            VALUES ('ret1', 'RET-20250301-001', 'a1b2c3d4-0000', '2025-03-01', 'defective', 450);
+        INSERT INTO stock_adjustments (id, product_id, adjustment_type, quantity, notes) VALUES
+            ('adj1', 'p1', 'sale', -2, 'Invoice A1B2C3D4'),
+            ('adj2', 'p1', 'return', 1, 'Return RET-20250301-001: Brake pad'),
+            ('adj3', 'p1', 'other', 1, 'Counted');
+        INSERT INTO settings (key, value) VALUES ('backup_retention_days', '14');
+        INSERT INTO users (id, username, password_hash, role, name)
+            VALUES ('u1', 'owner', 'hash', 'admin', 'Owner');
+    ";
+
+    fn schema(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare("SELECT sql FROM sqlite_master WHERE sql IS NOT NULL ORDER BY name")
+            .unwrap();
+        let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+        rows.collect::<Result<_, _>>().unwrap()
+    }
+
+    fn text(conn: &Connection, sql: &str) -> Option<String> {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn empty_database_is_brought_to_latest_version() {
+        let conn = Connection::open_in_memory().unwrap();
+        let applied = migrate(&conn).unwrap();
+
+        assert_eq!(applied, (1..=latest_version()).collect::<Vec<_>>());
+        assert_eq!(schema_version(&conn).unwrap(), latest_version());
+        assert_eq!(
+            text(&conn, "SELECT username FROM users").as_deref(),
+            Some("admin")
+        );
+        assert_eq!(
+            text(
+                &conn,
+                "SELECT value FROM settings WHERE key = 'return_grace_hours'"
+            )
+            .as_deref(),
+            Some("24")
+        );
+    }
+
+    #[test]
+    fn legacy_database_is_upgraded_in_place() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(LEGACY_SCHEMA).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), 0);
+
+        migrate(&conn).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), latest_version());
+
+        // Rows survive the products rebuild and gain the later columns' defaults
+        let (quantity, purchase_price): (i64, f64) = conn
+            .query_row(
+                "SELECT quantity, purchase_price FROM products WHERE id = 'p1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((quantity, purchase_price), (7, 0.0));
+        conn.execute(
+            "INSERT INTO products (id, name, price) VALUES ('p2', 'No SKU', 10)",
+            [],
+        )
+        .unwrap();
+
+        // Existing users and settings are kept; defaults only fill gaps
+        assert_eq!(
+            text(&conn, "SELECT group_concat(username) FROM users").as_deref(),
+            Some("owner")
+        );
+        assert_eq!(
+            text(
+                &conn,
+                "SELECT value FROM settings WHERE key = 'safety_backup_max_age_days'"
+            )
+            .as_deref(),
+            Some("14")
+        );
+        assert_eq!(
+            text(
+                &conn,
+                "SELECT value FROM settings WHERE key = 'low_stock_method'"
+            )
+            .as_deref(),
+            Some("reorder_level")
+        );
+
+        // Movements are linked to the records their notes name
+        let reference = |id: &str| {
+            text(
+                &conn,
+                &format!(
+                    "SELECT reference_id FROM stock_adjustments WHERE id = '{}'",
+                    id
+                ),
+            )
+        };
+        assert_eq!(reference("adj1").as_deref(), Some("a1b2c3d4-0000"));
+        assert_eq!(reference("adj2").as_deref(), Some("ret1"));
+        assert_eq!(reference("adj3"), None);
+    }
+
+    #[test]
+    fn second_run_changes_nothing() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(LEGACY_SCHEMA).unwrap();
+        migrate(&conn).unwrap();
+        let before = schema(&conn);
+        let settings = text(
+            &conn,
+            "SELECT group_concat(key || '=' || value) FROM settings",
+        );
+
+        assert_eq!(migrate(&conn).unwrap(), Vec::<u32>::new());
+        assert_eq!(schema(&conn), before);
+        assert_eq!(
+            text(
+                &conn,
+                "SELECT group_concat(key || '=' || value) FROM settings"
+            ),
+            settings
+        );
+        assert_eq!(schema_version(&conn).unwrap(), latest_version());
+    }
+}
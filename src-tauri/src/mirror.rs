@@ -0,0 +1,418 @@
+use chrono::{SecondsFormat, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::operations::Operation;
+use crate::{archive, manifest, retention};
+use crate::{get_db_path, read_setting};
+
+// ============================================
+// BACKUP MIRRORS
+// ============================================
+//
+// Every backup is also copied to the folders listed in the
+// `backup_mirror_destinations` setting (a JSON array of paths), such as a USB
+// drive, a second disk or a network share. A destination that is not mounted is
+// skipped and recorded as unavailable; the backup itself still succeeds, and the
+// backups it missed are copied the next time the destination is reachable.
+// Each destination is pruned with the same retention policy as the backups folder,
+// and backups that policy would delete locally are never copied.
+
+const DESTINATIONS_SETTING: &str = "backup_mirror_destinations";
+
+/// Suffix of a file still being copied, so an interrupted copy never looks like a backup
+const PARTIAL_SUFFIX: &str = ".partial";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirrorStatus {
+    pub destination: String,
+    /// The folder exists right now (removable drives and network shares may not be mounted)
+    pub available: bool,
+    pub free_bytes: Option<u64>,
+    pub last_attempt_at: Option<String>,
+    pub last_success_at: Option<String>,
+    /// Backup copied by the last successful mirror run
+    pub last_file: Option<String>,
+    /// Why the last attempt failed, cleared by the next success
+    pub last_error: Option<String>,
+}
+
+/// Reads the configured destinations, ignoring blank entries
+fn load_destinations(conn: &Connection) -> Result<Vec<String>, String> {
+    let Some(raw) = read_setting(conn, DESTINATIONS_SETTING)? else {
+        return Ok(Vec::new());
+    };
+    if raw.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let destinations: Vec<String> = serde_json::from_str(&raw)
+        .map_err(|e| format!("Invalid {} setting: {}", DESTINATIONS_SETTING, e))?;
+
+    Ok(destinations
+        .into_iter()
+        .map(|d| d.trim().to_string())
+        .filter(|d| !d.is_empty())
+        .collect())
+}
+
+fn record_status(
+    conn: &Connection,
+    destination: &str,
+    outcome: &Result<String, String>,
+) -> Result<(), String> {
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+
+    let result = match outcome {
+        Ok(file) => conn.execute(
+            "INSERT INTO backup_mirror_status (destination, last_attempt_at, last_success_at, last_file, last_error)
+             VALUES (?1, ?2, ?2, ?3, NULL)
+             ON CONFLICT(destination) DO UPDATE SET
+                last_attempt_at = excluded.last_attempt_at,
+                last_success_at = excluded.last_success_at,
+                last_file = excluded.last_file,
+                last_error = NULL",
+            params![destination, now, file],
+        ),
+        Err(e) => conn.execute(
+            "INSERT INTO backup_mirror_status (destination, last_attempt_at, last_error)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(destination) DO UPDATE SET
+                last_attempt_at = excluded.last_attempt_at,
+                last_error = excluded.last_error",
+            params![destination, now, e],
+        ),
+    };
+
+    result
+        .map(|_| ())
+        .map_err(|e| format!("Failed to record mirror status: {}", e))
+}
+
+fn file_name(path: &Path) -> Result<String, String> {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| format!("Invalid backup path: {}", path.display()))
+}
+
+/// Copies `source` into `dest_dir` under a temporary name and renames it into place
+fn copy_into(source: &Path, dest_dir: &Path) -> Result<(), String> {
+    let name = file_name(source)?;
+    let target = dest_dir.join(&name);
+    let partial = dest_dir.join(format!("{}{}", name, PARTIAL_SUFFIX));
+
+    let copied = fs::copy(source, &partial)
+        .map_err(|e| format!("Failed to copy {}: {}", name, e))
+        .and_then(|_| {
+            fs::rename(&partial, &target)
+                .map_err(|e| format!("Failed to finish copying {}: {}", name, e))
+        });
+
+    if copied.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    copied
+}
+
+/// Local backups kept by retention that the destination does not have yet, `latest`
+/// first so it is copied even if the destination fills up
+fn pending_files(
+    backups_dir: &Path,
+    dest: &Path,
+    latest: &Path,
+    kept: &HashSet<String>,
+) -> Vec<PathBuf> {
+    let mut pending = vec![latest.to_path_buf()];
+
+    let mut missing: Vec<PathBuf> = fs::read_dir(backups_dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| archive::is_backup_file(path) && path != latest)
+                .filter(|path| {
+                    path.file_name().is_some_and(|name| {
+                        kept.contains(name.to_string_lossy().as_ref()) && !dest.join(name).exists()
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Oldest first, so incremental chains are copied base before increments
+    missing.sort_by_key(|path| fs::metadata(path).and_then(|m| m.modified()).ok());
+    pending.extend(missing);
+    pending
+}
+
+/// Copies the backup and any backups the destination missed. Returns the number of files copied.
+fn mirror_to(
+    dest: &Path,
+    backups_dir: &Path,
+    latest: &Path,
+    kept: &HashSet<String>,
+) -> Result<usize, String> {
+    if !dest.is_dir() {
+        return Err("Destination not available. Is the drive connected?".to_string());
+    }
+
+    let same_folder = match (dest.canonicalize(), backups_dir.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    };
+    if same_folder {
+        return Err("Destination is the backups folder itself".to_string());
+    }
+
+    let mut copied = 0;
+    for file in pending_files(backups_dir, dest, latest, kept) {
+        let sidecar = manifest::manifest_path(&file);
+        let needed = fs::metadata(&file)
+            .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?
+            .len()
+            + fs::metadata(&sidecar).map(|m| m.len()).unwrap_or(0);
+
+        let free =
+            fs2::available_space(dest).map_err(|e| format!("Failed to check free space: {}", e))?;
+        if free < needed {
+            return Err(format!(
+                "Not enough free space for {} ({} bytes needed, {} available)",
+                file_name(&file)?,
+                needed,
+                free
+            ));
+        }
+
+        copy_into(&file, dest)?;
+        if sidecar.exists() {
+            copy_into(&sidecar, dest)?;
+        }
+        copied += 1;
+    }
+
+    Ok(copied)
+}
+
+/// Deletes the backups in a destination that the retention policy no longer keeps.
+/// Failures are logged; other files in the destination are never touched.
+fn prune_destination(conn: &Connection, dest: &Path) {
+    match retention::apply_retention(conn, dest, false) {
+        Ok(report) => {
+            for filename in &report.deleted {
+                println!(
+                    "[Mirror] Deleted old backup {} from {}",
+                    filename,
+                    dest.display()
+                );
+            }
+            for failure in &report.failed {
+                eprintln!(
+                    "[Mirror] Failed to delete {} from {}",
+                    failure,
+                    dest.display()
+                );
+            }
+        }
+        Err(e) => eprintln!("[Mirror] Retention failed for {}: {}", dest.display(), e),
+    }
+}
+
+/// Copies a freshly created backup to every configured destination and records the
+/// outcome of each. Failures are logged and never fail the backup.
+pub fn mirror_backup(
+    db_path: &Path,
+    backups_dir: &Path,
+    backup_path: &Path,
+    operation: Option<&Operation>,
+) {
+    if let Err(e) = try_mirror_backup(db_path, backups_dir, backup_path, operation) {
+        eprintln!("[Mirror] {}", e);
+    }
+}
+
+fn try_mirror_backup(
+    db_path: &Path,
+    backups_dir: &Path,
+    backup_path: &Path,
+    operation: Option<&Operation>,
+) -> Result<(), String> {
+    let conn = Connection::open(db_path).map_err(|e| format!("Failed to open database: {}", e))?;
+    conn.busy_timeout(Duration::from_secs(5))
+        .map_err(|e| format!("Failed to set busy timeout: {}", e))?;
+
+    let destinations = load_destinations(&conn)?;
+    if destinations.is_empty() {
+        return Ok(());
+    }
+
+    let filename = file_name(backup_path)?;
+    let total = destinations.len() as u64;
+    let kept: HashSet<String> = retention::apply_retention(&conn, backups_dir, true)?
+        .kept
+        .into_iter()
+        .collect();
+
+    for (i, destination) in destinations.iter().enumerate() {
+        if let Some(op) = operation {
+            op.report("mirroring", None, i as u64, total);
+        }
+
+        let outcome = mirror_to(Path::new(destination), backups_dir, backup_path, &kept);
+        match &outcome {
+            Ok(copied) => {
+                println!("[Mirror] Copied {} file(s) to {}", copied, destination);
+                prune_destination(&conn, Path::new(destination));
+            }
+            Err(e) => eprintln!("[Mirror] Skipped {}: {}", destination, e),
+        }
+
+        record_status(&conn, destination, &outcome.map(|_| filename.clone()))?;
+    }
+
+    if let Some(op) = operation {
+        op.report("mirroring", None, total, total);
+    }
+
+    Ok(())
+}
+
+/// Reports every configured mirror destination: whether it is reachable now,
+/// its free space, and the outcome of the last mirror run
+#[tauri::command]
+pub fn get_mirror_status(app: AppHandle) -> Result<Vec<MirrorStatus>, String> {
+    let db_path = get_db_path(&app)?;
+    if !db_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let conn = Connection::open(&db_path).map_err(|e| format!("Failed to open database: {}", e))?;
+    let destinations = load_destinations(&conn)?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT last_attempt_at, last_success_at, last_file, last_error
+             FROM backup_mirror_status WHERE destination = ?1",
+        )
+        .map_err(|e| format!("Failed to read mirror status: {}", e))?;
+
+    let mut statuses = Vec::new();
+
+    for destination in destinations {
+        let path = Path::new(&destination);
+        let available = path.is_dir();
+
+        let mut status = MirrorStatus {
+            destination: destination.clone(),
+            available,
+            free_bytes: if available {
+                fs2::available_space(path).ok()
+            } else {
+                None
+            },
+            last_attempt_at: None,
+            last_success_at: None,
+            last_file: None,
+            last_error: None,
+        };
+
+        let recorded = stmt.query_row(params![destination], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        });
+        if let Ok((attempt, success, file, error)) = recorded {
+            status.last_attempt_at = attempt;
+            status.last_success_at = success;
+            status.last_file = file;
+            status.last_error = error;
+        }
+
+        statuses.push(status);
+    }
+
+    Ok(statuses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mirror_to, prune_destination};
+    use crate::test_support::temp_dir;
+    use crate::{migrations, retention};
+    use chrono::{Duration as ChronoDuration, Local};
+    use rusqlite::Connection;
+    use std::collections::HashSet;
+    use std::fs;
+    use std::path::Path;
+
+    /// Writes a regular backup taken `days_ago` at `time`
+    fn write_backup(dir: &Path, days_ago: i64, time: &str) -> String {
+        let date = Local::now().date_naive() - ChronoDuration::days(days_ago);
+        let name = format!("motormods_backup_{}_{}.db", date.format("%Y-%m-%d"), time);
+        fs::write(dir.join(&name), name.as_bytes()).unwrap();
+        name
+    }
+
+    fn backups_in(dir: &Path) -> HashSet<String> {
+        fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|name| name.starts_with("motormods_backup_"))
+            .collect()
+    }
+
+    #[test]
+    fn destination_holds_only_what_retention_keeps() {
+        let root = temp_dir("mirror");
+        let backups_dir = root.join("backups");
+        let dest = root.join("usb");
+        fs::create_dir_all(&backups_dir).unwrap();
+        fs::create_dir_all(&dest).unwrap();
+
+        let conn = Connection::open_in_memory().unwrap();
+        migrations::migrate(&conn).unwrap();
+
+        // Forty days of backups, more than the default policy keeps
+        let mut latest = String::new();
+        for days_ago in (0..40).rev() {
+            latest = write_backup(&backups_dir, days_ago, "12-00-00");
+        }
+        // Earlier backups of the same days, mirrored before they were pruned locally
+        let mut stale = Vec::new();
+        for days_ago in 0..40 {
+            stale.push(write_backup(&dest, days_ago, "01-00-00"));
+        }
+        fs::write(dest.join("notes.txt"), b"not a backup").unwrap();
+
+        let kept: HashSet<String> = retention::apply_retention(&conn, &backups_dir, true)
+            .unwrap()
+            .kept
+            .into_iter()
+            .collect();
+        assert!(kept.len() < 40);
+        assert!(kept.contains(&latest));
+
+        let copied = mirror_to(&dest, &backups_dir, &backups_dir.join(&latest), &kept).unwrap();
+        assert_eq!(copied, kept.len());
+        let mut expected = kept.clone();
+        expected.extend(stale.iter().cloned());
+        assert_eq!(backups_in(&dest), expected);
+
+        prune_destination(&conn, &dest);
+        assert_eq!(backups_in(&dest), kept);
+        assert!(dest.join("notes.txt").exists());
+
+        // Nothing left to copy or prune on the next run
+        assert_eq!(
+            mirror_to(&dest, &backups_dir, &backups_dir.join(&latest), &kept).unwrap(),
+            1
+        );
+        prune_destination(&conn, &dest);
+        assert_eq!(backups_in(&dest), kept);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,283 @@
+use chrono::{DateTime, Local, SecondsFormat};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+// ============================================
+// LONG-RUNNING OPERATIONS (PROGRESS + CANCELLATION)
+// ============================================
+
+/// Emitted while a backup or restore runs
+pub const PROGRESS_EVENT: &str = "operation-progress";
+
+/// Error returned by an operation stopped with `cancel_operation`
+pub const CANCELLED: &str = "Operation cancelled. No changes were made to the database.";
+
+/// Progress events are throttled so a fast copy does not flood the frontend
+const MIN_REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Cancellation flags of the operations currently running, by id
+fn active() -> &'static Mutex<HashMap<String, Arc<AtomicBool>>> {
+    static ACTIVE: OnceLock<Mutex<HashMap<String, Arc<AtomicBool>>>> = OnceLock::new();
+    ACTIVE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OperationKind {
+    Backup,
+    Restore,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationProgress {
+    pub operation_id: String,
+    pub kind: OperationKind,
+    /// `preparing`, `copying` (backup pages), `mirroring` (backup destinations),
+    /// `exporting` (portable archive tables), `importing` (restore rows) or `finished`
+    pub phase: String,
+    /// Table being imported, for restores
+    pub table: Option<String>,
+    pub done: u64,
+    pub total: u64,
+}
+
+/// A running backup or restore that reports progress and can be cancelled.
+/// Unregistered when dropped.
+pub struct Operation {
+    app: AppHandle,
+    id: String,
+    kind: OperationKind,
+    cancelled: Arc<AtomicBool>,
+    last_report: Mutex<Option<Instant>>,
+}
+
+impl Operation {
+    /// Registers an operation under the id chosen by the frontend, or a generated one
+    pub fn start(app: AppHandle, id: Option<String>, kind: OperationKind) -> Self {
+        let id = id
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| format!("op-{}", NEXT_ID.fetch_add(1, Ordering::Relaxed)));
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        if let Ok(mut active) = active().lock() {
+            active.insert(id.clone(), cancelled.clone());
+        }
+
+        Operation {
+            app,
+            id,
+            kind,
+            cancelled,
+            last_report: Mutex::new(None),
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Returns the cancellation error if a cancel was requested
+    pub fn check_cancelled(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err(CANCELLED.to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Emits a progress event. Intermediate updates are throttled; the last one
+    /// of a step (`done == total`) is always sent.
+    pub fn report(&self, phase: &str, table: Option<&str>, done: u64, total: u64) {
+        if let Ok(mut last) = self.last_report.lock() {
+            let due = last.is_none_or(|t| t.elapsed() >= MIN_REPORT_INTERVAL);
+            if !due && done < total {
+                return;
+            }
+            *last = Some(Instant::now());
+        }
+
+        let progress = OperationProgress {
+            operation_id: self.id.clone(),
+            kind: self.kind,
+            phase: phase.to_string(),
+            table: table.map(|t| t.to_string()),
+            done,
+            total,
+        };
+
+        if let Err(e) = self.app.emit(PROGRESS_EVENT, progress) {
+            eprintln!("Warning: Failed to emit progress: {}", e);
+        }
+    }
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        if let Ok(mut active) = active().lock() {
+            active.remove(&self.id);
+        }
+    }
+}
+
+// ============================================
+// OPERATION LOCK
+// ============================================
+//
+// Backups, restores, imports and deletions share the backups folder and the live
+// database, so only one of them runs at a time. The lock is managed Tauri state; a
+// command that finds it taken fails straight away with what is running and since
+// when, rather than waiting behind it.
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunningOperation {
+    /// What is running, e.g. "Restoring motormods_backup_2025-01-01.db"
+    pub activity: String,
+    /// RFC 3339, local time
+    pub started_at: String,
+    /// Backup file the operation reads from, which cannot be deleted until it finishes
+    pub file: Option<String>,
+}
+
+impl RunningOperation {
+    /// Start time as shown in messages, e.g. `14:05:32`
+    fn started_time(&self) -> String {
+        DateTime::parse_from_rfc3339(&self.started_at)
+            .map(|t| t.format("%H:%M:%S").to_string())
+            .unwrap_or_else(|_| self.started_at.clone())
+    }
+
+    fn busy_error(&self) -> String {
+        format!(
+            "Another operation is in progress: {} (started at {}). Try again when it finishes.",
+            self.activity,
+            self.started_time()
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct OperationLock {
+    running: Arc<Mutex<Option<RunningOperation>>>,
+}
+
+/// Holds the operation lock; released when dropped
+pub struct OperationGuard {
+    running: Arc<Mutex<Option<RunningOperation>>>,
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        if let Ok(mut running) = self.running.lock() {
+            *running = None;
+        }
+    }
+}
+
+impl OperationLock {
+    /// Takes the lock for `activity`, or explains what holds it
+    pub fn acquire(&self, activity: &str, file: Option<&str>) -> Result<OperationGuard, String> {
+        let mut running = self
+            .running
+            .lock()
+            .map_err(|_| "Operation lock is unavailable".to_string())?;
+
+        if let Some(current) = running.as_ref() {
+            return Err(current.busy_error());
+        }
+
+        *running = Some(RunningOperation {
+            activity: activity.to_string(),
+            started_at: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+            file: file.map(|f| f.to_string()),
+        });
+
+        Ok(OperationGuard {
+            running: self.running.clone(),
+        })
+    }
+
+    /// The operation holding the lock, if any
+    pub fn current(&self) -> Option<RunningOperation> {
+        self.running.lock().ok().and_then(|r| r.clone())
+    }
+}
+
+/// File name of a backup path, as recorded in the lock and shown in messages
+pub fn file_label(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// Takes the app's operation lock for `activity`. `file` names the backup being read.
+pub fn lock(app: &AppHandle, activity: &str, file: Option<&str>) -> Result<OperationGuard, String> {
+    app.state::<OperationLock>().acquire(activity, file)
+}
+
+/// Takes the operation lock to delete `filename`, refusing with a specific reason if the
+/// running operation is reading that very file
+pub fn lock_for_delete(app: &AppHandle, filename: &str) -> Result<OperationGuard, String> {
+    let state = app.state::<OperationLock>();
+
+    if let Some(current) = state.current() {
+        if current.file.as_deref() == Some(filename) {
+            return Err(format!(
+                "Cannot delete {}: it is in use ({}, started at {}).",
+                filename,
+                current.activity,
+                current.started_time()
+            ));
+        }
+    }
+
+    state.acquire(&format!("Deleting {}", filename), None)
+}
+
+/// Reports the backup, restore or maintenance operation currently running, if any
+#[tauri::command]
+pub fn get_running_operation(lock: tauri::State<'_, OperationLock>) -> Option<RunningOperation> {
+    lock.current()
+}
+
+/// Runs `task` on a blocking thread so the window stays responsive while it works
+pub async fn run_blocking<R, F>(
+    app: AppHandle,
+    operation_id: Option<String>,
+    kind: OperationKind,
+    task: F,
+) -> Result<R, String>
+where
+    R: Send + 'static,
+    F: FnOnce(&AppHandle, &Operation) -> Result<R, String> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(move || {
+        let operation = Operation::start(app.clone(), operation_id, kind);
+        let result = task(&app, &operation);
+        operation.report("finished", None, 1, 1);
+        result
+    })
+    .await
+    .map_err(|e| format!("Operation failed: {}", e))?
+}
+
+/// Asks a running backup or restore to stop. Returns false if it already finished.
+#[tauri::command]
+pub fn cancel_operation(operation_id: String) -> bool {
+    match active().lock() {
+        Ok(active) => match active.get(&operation_id) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        },
+        Err(_) => false,
+    }
+}
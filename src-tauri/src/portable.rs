@@ -0,0 +1,969 @@
+use chrono::{SecondsFormat, Utc};
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number};
+use std::fs::{self, File};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::archive::MaterializedBackup;
+use crate::operations::{self, OperationKind};
+use crate::{app_version, get_db_path, import_backup_data, offsite, RestoreResult, DATA_TABLES};
+
+// ============================================
+// PORTABLE ARCHIVE (CSV / JSON)
+// ============================================
+//
+// A zip with one CSV or JSON file per table in `DATA_TABLES` and a `manifest.json`
+// describing each table's columns, types and foreign keys, so the data can be read
+// by spreadsheets and scripts and brought back after editing.
+//
+// CSV files have a header row of column names; an empty cell is NULL (or an empty
+// string in a required text column). JSON files hold an array of objects keyed by
+// column name.
+//
+// Imports are checked against the live database's schema: every value must fit its
+// column's type and every foreign key must resolve. The archive is loaded into a
+// temporary database first, so nothing is changed unless the whole archive is valid.
+//
+// Archives are plain zips, so credentials stay out of them: the `users` table with its
+// password hashes, and the settings in `PRIVATE_SETTINGS`. An import keeps the live
+// users and the live values of those settings.
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Bumped when the archive layout changes in a way older versions cannot read
+const FORMAT_VERSION: u32 = 1;
+
+/// Problems listed before an import gives up
+const MAX_IMPORT_ERRORS: usize = 20;
+
+/// Tables never exported: `users` holds password hashes
+const PRIVATE_TABLES: &[&str] = &["users"];
+
+/// Settings never exported because they are credentials
+const PRIVATE_SETTINGS: &[&str] = &["offsite_access_key", offsite::LEGACY_SECRET_SETTING];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveFormat {
+    Csv,
+    Json,
+}
+
+impl ArchiveFormat {
+    fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value.unwrap_or("csv") {
+            "csv" => Ok(ArchiveFormat::Csv),
+            "json" => Ok(ArchiveFormat::Json),
+            other => Err(format!("Unknown archive format: {}", other)),
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Csv => "csv",
+            ArchiveFormat::Json => "json",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveColumn {
+    pub name: String,
+    /// Declared SQLite type, e.g. `TEXT` or `INTEGER`
+    #[serde(rename = "type")]
+    pub data_type: String,
+    pub not_null: bool,
+    pub primary_key: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveForeignKey {
+    pub column: String,
+    pub references_table: String,
+    pub references_column: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveTable {
+    pub name: String,
+    /// Path of the table's file inside the zip
+    pub file: String,
+    pub rows: usize,
+    pub columns: Vec<ArchiveColumn>,
+    pub foreign_keys: Vec<ArchiveForeignKey>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub format_version: u32,
+    pub app_version: String,
+    pub created_at: String,
+    pub format: ArchiveFormat,
+    pub tables: Vec<ArchiveTable>,
+}
+
+// ============================================
+// SCHEMA
+// ============================================
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![table],
+        |_| Ok(()),
+    )
+    .optional()
+    .map(|r| r.is_some())
+    .map_err(|e| format!("Failed to look up table {}: {}", table, e))
+}
+
+/// Columns of `table` as declared in the database
+fn describe_columns(conn: &Connection, table: &str) -> Result<Vec<ArchiveColumn>, String> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA table_info({})", table))
+        .map_err(|e| format!("Failed to read columns of {}: {}", table, e))?;
+
+    let columns = stmt
+        .query_map([], |row| {
+            Ok(ArchiveColumn {
+                name: row.get(1)?,
+                data_type: row.get(2)?,
+                not_null: row.get::<_, i64>(3)? != 0,
+                primary_key: row.get::<_, i64>(5)? != 0,
+            })
+        })
+        .map_err(|e| format!("Failed to read columns of {}: {}", table, e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read columns of {}: {}", table, e))?;
+
+    Ok(columns)
+}
+
+fn describe_foreign_keys(conn: &Connection, table: &str) -> Result<Vec<ArchiveForeignKey>, String> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA foreign_key_list({})", table))
+        .map_err(|e| format!("Failed to read foreign keys of {}: {}", table, e))?;
+
+    let keys = stmt
+        .query_map([], |row| {
+            Ok(ArchiveForeignKey {
+                references_table: row.get(2)?,
+                column: row.get(3)?,
+                references_column: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+            })
+        })
+        .map_err(|e| format!("Failed to read foreign keys of {}: {}", table, e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read foreign keys of {}: {}", table, e))?;
+
+    Ok(keys)
+}
+
+/// Column names that have a DEFAULT, which is used when an imported value is missing
+fn columns_with_defaults(conn: &Connection, table: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA table_info({})", table))
+        .map_err(|e| format!("Failed to read columns of {}: {}", table, e))?;
+
+    let names = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(1)?, row.get::<_, Option<String>>(4)?))
+        })
+        .map_err(|e| format!("Failed to read columns of {}: {}", table, e))?
+        .filter_map(|r| match r {
+            Ok((name, Some(_))) => Some(Ok(name)),
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read columns of {}: {}", table, e))?;
+
+    Ok(names)
+}
+
+/// SQLite's type affinity for a declared column type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Affinity {
+    Integer,
+    Text,
+    Blob,
+    Real,
+    Numeric,
+}
+
+impl Affinity {
+    fn of(declared: &str) -> Self {
+        let t = declared.to_ascii_uppercase();
+        if t.contains("INT") {
+            Affinity::Integer
+        } else if t.contains("CHAR") || t.contains("CLOB") || t.contains("TEXT") {
+            Affinity::Text
+        } else if t.contains("BLOB") || t.is_empty() {
+            Affinity::Blob
+        } else if t.contains("REAL") || t.contains("FLOA") || t.contains("DOUB") {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+}
+
+/// Whether a row must have a value for `column`. A text primary key is required even
+/// though SQLite would accept NULL; an INTEGER one is assigned automatically.
+fn is_required(column: &ArchiveColumn, defaults: &[String]) -> bool {
+    if defaults.contains(&column.name) {
+        return false;
+    }
+    column.not_null || (column.primary_key && Affinity::of(&column.data_type) != Affinity::Integer)
+}
+
+// ============================================
+// EXPORT
+// ============================================
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// A value as a CSV cell; NULL is an empty cell and blobs are hex
+fn csv_cell(value: ValueRef) -> String {
+    match value {
+        ValueRef::Null => String::new(),
+        ValueRef::Integer(n) => n.to_string(),
+        ValueRef::Real(n) => n.to_string(),
+        ValueRef::Text(t) => String::from_utf8_lossy(t).to_string(),
+        ValueRef::Blob(b) => to_hex(b),
+    }
+}
+
+fn json_value(value: ValueRef) -> serde_json::Value {
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(n) => serde_json::Value::from(n),
+        ValueRef::Real(n) => serde_json::Value::from(n),
+        ValueRef::Text(t) => serde_json::Value::from(String::from_utf8_lossy(t).to_string()),
+        ValueRef::Blob(b) => serde_json::Value::from(to_hex(b)),
+    }
+}
+
+/// Writes the rows of `table` to `out` and returns how many there were
+fn write_table<W: Write>(
+    conn: &Connection,
+    table: &str,
+    columns: &[ArchiveColumn],
+    format: ArchiveFormat,
+    out: &mut W,
+) -> Result<usize, String> {
+    let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+    let private: &[&str] = if table == "settings" {
+        PRIVATE_SETTINGS
+    } else {
+        &[]
+    };
+    let filter = if private.is_empty() {
+        String::new()
+    } else {
+        let placeholders: Vec<String> = (1..=private.len()).map(|n| format!("?{}", n)).collect();
+        format!(" WHERE key NOT IN ({})", placeholders.join(", "))
+    };
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM {}{} ORDER BY rowid",
+            names.join(", "),
+            table,
+            filter
+        ))
+        .map_err(|e| format!("Failed to read {}: {}", table, e))?;
+    let mut rows = stmt
+        .query(params_from_iter(private))
+        .map_err(|e| format!("Failed to read {}: {}", table, e))?;
+
+    let mut count = 0;
+    let write_err = |e: &dyn std::fmt::Display| format!("Failed to write {}: {}", table, e);
+
+    match format {
+        ArchiveFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            writer.write_record(&names).map_err(|e| write_err(&e))?;
+
+            while let Some(row) = rows
+                .next()
+                .map_err(|e| format!("Failed to read {}: {}", table, e))?
+            {
+                let record = (0..names.len())
+                    .map(|i| row.get_ref(i).map(csv_cell))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| format!("Failed to read {}: {}", table, e))?;
+                writer.write_record(&record).map_err(|e| write_err(&e))?;
+                count += 1;
+            }
+
+            writer.flush().map_err(|e| write_err(&e))?;
+        }
+        ArchiveFormat::Json => {
+            // Written row by row so large tables are not held in memory
+            out.write_all(b"[").map_err(|e| write_err(&e))?;
+
+            while let Some(row) = rows
+                .next()
+                .map_err(|e| format!("Failed to read {}: {}", table, e))?
+            {
+                let mut object = Map::new();
+                for (i, name) in names.iter().enumerate() {
+                    let value = row
+                        .get_ref(i)
+                        .map_err(|e| format!("Failed to read {}: {}", table, e))?;
+                    object.insert(name.to_string(), json_value(value));
+                }
+
+                if count > 0 {
+                    out.write_all(b",").map_err(|e| write_err(&e))?;
+                }
+                out.write_all(b"\n  ").map_err(|e| write_err(&e))?;
+                serde_json::to_writer(&mut *out, &object).map_err(|e| write_err(&e))?;
+                count += 1;
+            }
+
+            out.write_all(b"\n]\n").map_err(|e| write_err(&e))?;
+        }
+    }
+
+    Ok(count)
+}
+
+/// Writes every table in `DATA_TABLES` that the database has, except `PRIVATE_TABLES`,
+/// to a portable archive at `destination`
+fn write_archive(
+    db_path: &Path,
+    destination: &Path,
+    format: ArchiveFormat,
+    app_version: &str,
+    operation: &operations::Operation,
+) -> Result<ArchiveManifest, String> {
+    let conn = Connection::open(db_path).map_err(|e| format!("Failed to open database: {}", e))?;
+
+    // A single read transaction, so every table comes from the same moment
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let partial = PathBuf::from(format!("{}.partial", destination.display()));
+    let file = File::create(&partial).map_err(|e| format!("Failed to create archive: {}", e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut manifest = ArchiveManifest {
+        format_version: FORMAT_VERSION,
+        app_version: app_version.to_string(),
+        created_at: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        format,
+        tables: Vec::new(),
+    };
+
+    let written = (|| {
+        let total = DATA_TABLES.len() as u64;
+        for (i, table) in DATA_TABLES.iter().enumerate() {
+            operation.check_cancelled()?;
+            operation.report("exporting", Some(table), i as u64, total);
+
+            if PRIVATE_TABLES.contains(table) || !table_exists(&tx, table)? {
+                continue;
+            }
+
+            let columns = describe_columns(&tx, table)?;
+            let foreign_keys = describe_foreign_keys(&tx, table)?;
+            let file_name = format!("{}.{}", table, format.extension());
+
+            zip.start_file(file_name.as_str(), options)
+                .map_err(|e| format!("Failed to write archive: {}", e))?;
+            let rows = write_table(&tx, table, &columns, format, &mut zip)?;
+
+            manifest.tables.push(ArchiveTable {
+                name: table.to_string(),
+                file: file_name,
+                rows,
+                columns,
+                foreign_keys,
+            });
+        }
+
+        zip.start_file(MANIFEST_FILE, options)
+            .map_err(|e| format!("Failed to write archive: {}", e))?;
+        serde_json::to_writer_pretty(&mut zip, &manifest)
+            .map_err(|e| format!("Failed to write archive manifest: {}", e))?;
+        zip.finish()
+            .map_err(|e| format!("Failed to write archive: {}", e))?;
+
+        fs::rename(&partial, destination).map_err(|e| format!("Failed to save archive: {}", e))
+    })();
+
+    if written.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    written?;
+
+    Ok(manifest)
+}
+
+// ============================================
+// IMPORT
+// ============================================
+
+/// A value read from an archive, before it is checked against its column
+enum Cell {
+    /// An empty CSV cell
+    Empty,
+    Null,
+    Text(String),
+    Number(Number),
+    Bool(bool),
+}
+
+/// Converts a cell to the type of its column, or explains why it does not fit
+fn convert(cell: Cell, column: &ArchiveColumn) -> Result<Value, String> {
+    let affinity = Affinity::of(&column.data_type);
+
+    let value = match cell {
+        Cell::Empty if affinity == Affinity::Text && column.not_null => Value::Text(String::new()),
+        Cell::Empty | Cell::Null => Value::Null,
+        Cell::Bool(b) => match affinity {
+            Affinity::Text => Value::Text(b.to_string()),
+            _ => Value::Integer(b as i64),
+        },
+        Cell::Number(n) => match affinity {
+            Affinity::Text => Value::Text(n.to_string()),
+            Affinity::Real => Value::Real(n.as_f64().unwrap_or_default()),
+            _ => match n.as_i64() {
+                Some(i) => Value::Integer(i),
+                None => whole_or_real(n.as_f64().unwrap_or_default(), affinity)?,
+            },
+        },
+        Cell::Text(text) => match affinity {
+            Affinity::Text => Value::Text(text),
+            Affinity::Blob => match from_hex(text.trim()) {
+                Some(bytes) => Value::Blob(bytes),
+                None => Value::Text(text),
+            },
+            Affinity::Integer | Affinity::Real | Affinity::Numeric => {
+                let trimmed = text.trim();
+                if let Ok(i) = trimmed.parse::<i64>() {
+                    if affinity == Affinity::Real {
+                        Value::Real(i as f64)
+                    } else {
+                        Value::Integer(i)
+                    }
+                } else if let Ok(f) = trimmed.parse::<f64>() {
+                    whole_or_real(f, affinity)?
+                } else {
+                    return Err(format!("\"{}\" is not a number", text));
+                }
+            }
+        },
+    };
+
+    Ok(value)
+}
+
+fn whole_or_real(f: f64, affinity: Affinity) -> Result<Value, String> {
+    if affinity == Affinity::Real {
+        Ok(Value::Real(f))
+    } else if f.fract() == 0.0 && f.abs() < i64::MAX as f64 {
+        Ok(Value::Integer(f as i64))
+    } else if affinity == Affinity::Integer {
+        Err(format!("{} is not a whole number", f))
+    } else {
+        Ok(Value::Real(f))
+    }
+}
+
+/// Rows of a table file as (column, cell) pairs
+fn read_rows<R: Read>(
+    reader: R,
+    format: ArchiveFormat,
+    file: &str,
+) -> Result<Vec<Vec<(String, Cell)>>, String> {
+    match format {
+        ArchiveFormat::Csv => {
+            let mut csv_reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+            let headers: Vec<String> = csv_reader
+                .headers()
+                .map_err(|e| format!("{}: {}", file, e))?
+                .iter()
+                // Spreadsheets often save a byte order mark in front of the first header
+                .map(|h| h.trim_start_matches('\u{feff}').trim().to_string())
+                .collect();
+
+            let mut rows = Vec::new();
+            for (i, record) in csv_reader.records().enumerate() {
+                let record = record.map_err(|e| format!("{}: {}", file, e))?;
+                if record.len() > headers.len() {
+                    return Err(format!("{} line {}: more cells than columns", file, i + 2));
+                }
+                // Blank lines some editors leave at the end
+                if record.iter().all(str::is_empty) {
+                    continue;
+                }
+                rows.push(
+                    headers
+                        .iter()
+                        .zip(record.iter())
+                        .map(|(name, cell)| {
+                            let cell = if cell.is_empty() {
+                                Cell::Empty
+                            } else {
+                                Cell::Text(cell.to_string())
+                            };
+                            (name.clone(), cell)
+                        })
+                        .collect(),
+                );
+            }
+            Ok(rows)
+        }
+        ArchiveFormat::Json => {
+            let objects: Vec<Map<String, serde_json::Value>> =
+                serde_json::from_reader(reader).map_err(|e| format!("{}: {}", file, e))?;
+
+            objects
+                .into_iter()
+                .enumerate()
+                .map(|(i, object)| {
+                    object
+                        .into_iter()
+                        .map(|(name, value)| {
+                            let cell = match value {
+                                serde_json::Value::Null => Cell::Null,
+                                serde_json::Value::Bool(b) => Cell::Bool(b),
+                                serde_json::Value::Number(n) => Cell::Number(n),
+                                serde_json::Value::String(s) => Cell::Text(s),
+                                _ => {
+                                    return Err(format!(
+                                        "{} row {}: {} must be a plain value",
+                                        file,
+                                        i + 1,
+                                        name
+                                    ))
+                                }
+                            };
+                            Ok((name, cell))
+                        })
+                        .collect()
+                })
+                .collect()
+        }
+    }
+}
+
+fn read_manifest<R: Read + Seek>(zip: &mut ZipArchive<R>) -> Result<ArchiveManifest, String> {
+    let file = zip
+        .by_name(MANIFEST_FILE)
+        .map_err(|_| format!("Not a portable archive: {} is missing", MANIFEST_FILE))?;
+    let manifest: ArchiveManifest =
+        serde_json::from_reader(file).map_err(|e| format!("Invalid archive manifest: {}", e))?;
+
+    if manifest.format_version > FORMAT_VERSION {
+        return Err(format!(
+            "This archive was made by a newer version of the app ({}). Update to import it.",
+            manifest.app_version
+        ));
+    }
+
+    Ok(manifest)
+}
+
+/// Loads the rows of one archived table into `conn`, collecting every value that does not fit
+fn load_table(
+    conn: &Connection,
+    table: &str,
+    file: &str,
+    rows: Vec<Vec<(String, Cell)>>,
+    errors: &mut Vec<String>,
+) -> Result<usize, String> {
+    let columns = describe_columns(conn, table)?;
+    let defaults = columns_with_defaults(conn, table)?;
+    let mut loaded = 0;
+
+    for (i, row) in rows.into_iter().enumerate() {
+        if errors.len() >= MAX_IMPORT_ERRORS {
+            break;
+        }
+        // Line numbers for CSV count the header row, as a spreadsheet shows them
+        let position = if file.ends_with(".csv") {
+            format!("{} line {}", file, i + 2)
+        } else {
+            format!("{} row {}", file, i + 1)
+        };
+
+        let mut names = Vec::new();
+        let mut values = Vec::new();
+        let mut valid = true;
+
+        for (name, cell) in row {
+            let Some(column) = columns.iter().find(|c| c.name == name) else {
+                errors.push(format!("{}: unknown column {}", position, name));
+                valid = false;
+                continue;
+            };
+
+            match convert(cell, column) {
+                // Missing values fall back to the column default
+                Ok(Value::Null) if defaults.contains(&column.name) => {}
+                Ok(Value::Null) if is_required(column, &defaults) => {
+                    errors.push(format!("{}: {} is required", position, name));
+                    valid = false;
+                }
+                Ok(value) => {
+                    names.push(name);
+                    values.push(value);
+                }
+                Err(e) => {
+                    errors.push(format!("{}: {}: {}", position, name, e));
+                    valid = false;
+                }
+            }
+        }
+
+        if !valid {
+            continue;
+        }
+        if let Some(missing) = columns
+            .iter()
+            .find(|c| is_required(c, &defaults) && !names.contains(&c.name))
+        {
+            errors.push(format!("{}: {} is required", position, missing.name));
+            continue;
+        }
+
+        let placeholders: Vec<String> = (1..=names.len()).map(|n| format!("?{}", n)).collect();
+        let sql = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            table,
+            names.join(", "),
+            placeholders.join(", ")
+        );
+        match conn.execute(&sql, params_from_iter(values)) {
+            Ok(_) => loaded += 1,
+            Err(e) => errors.push(format!("{}: {}", position, e)),
+        }
+    }
+
+    Ok(loaded)
+}
+
+/// Lists rows whose foreign keys point at nothing
+fn foreign_key_errors(conn: &Connection) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare("PRAGMA foreign_key_check")
+        .map_err(|e| format!("Failed to check foreign keys: {}", e))?;
+
+    let violations = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<i64>>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to check foreign keys: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to check foreign keys: {}", e))?;
+
+    let mut errors = Vec::new();
+    for (table, rowid, parent, fk_index) in violations.into_iter().take(MAX_IMPORT_ERRORS) {
+        let foreign_keys = describe_foreign_keys(conn, &table)?;
+        let column = foreign_keys
+            .get(fk_index as usize)
+            .map_or("?".to_string(), |k| k.column.clone());
+        let (id, value): (Option<String>, Option<String>) = conn
+            .query_row(
+                &format!(
+                    "SELECT CAST(id AS TEXT), CAST({} AS TEXT) FROM {} WHERE rowid = ?1",
+                    column, table
+                ),
+                params![rowid],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap_or((None, None));
+
+        errors.push(format!(
+            "{} {}: {} {} does not exist in {}",
+            table,
+            id.unwrap_or_default(),
+            column,
+            value.unwrap_or_default(),
+            parent
+        ));
+    }
+
+    Ok(errors)
+}
+
+/// Builds a database with the live schema from a portable archive and checks it.
+/// Returns the database and the archived tables, in `DATA_TABLES` order.
+///
+/// With `keep_live_rows`, tables missing from the archive are checked against the live
+/// rows they will keep, then left out of the returned database. Otherwise they are empty.
+fn load_archive(
+    live_db: &Path,
+    source: &Path,
+    keep_live_rows: bool,
+    operation: &operations::Operation,
+) -> Result<(MaterializedBackup, Vec<&'static str>), String> {
+    let file = File::open(source).map_err(|e| format!("Failed to open archive: {}", e))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("Not a valid zip archive: {}", e))?;
+    let manifest = read_manifest(&mut zip)?;
+
+    if let Some(unknown) = manifest
+        .tables
+        .iter()
+        .find(|t| !DATA_TABLES.contains(&t.name.as_str()))
+    {
+        return Err(format!("Archive contains unknown table: {}", unknown.name));
+    }
+
+    let loaded = MaterializedBackup::temporary("portable")?;
+    let conn = Connection::open(loaded.path())
+        .map_err(|e| format!("Failed to create import database: {}", e))?;
+    conn.execute(
+        "ATTACH DATABASE ?1 AS live",
+        params![live_db.to_string_lossy()],
+    )
+    .map_err(|e| format!("Failed to open database: {}", e))?;
+
+    // Rows are loaded in any order and their foreign keys checked all at once afterwards
+    conn.execute("PRAGMA foreign_keys = OFF", [])
+        .map_err(|e| format!("Failed to disable foreign keys: {}", e))?;
+
+    // Every data table gets the live table's definition, so types, defaults and
+    // foreign keys are checked exactly as the app would
+    for table in DATA_TABLES {
+        let sql: Option<String> = conn
+            .query_row(
+                "SELECT sql FROM live.sqlite_master WHERE type = 'table' AND name = ?1",
+                params![table],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to read schema of {}: {}", table, e))?;
+        let Some(sql) = sql else {
+            continue;
+        };
+        conn.execute(&sql, [])
+            .map_err(|e| format!("Failed to create {}: {}", table, e))?;
+    }
+
+    let mut archived = Vec::new();
+    let mut errors = Vec::new();
+    let total = DATA_TABLES.len() as u64;
+
+    conn.execute("BEGIN TRANSACTION", [])
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    for (i, table) in DATA_TABLES.iter().enumerate() {
+        operation.check_cancelled()?;
+        operation.report("preparing", Some(table), i as u64, total);
+
+        let Some(entry) = manifest.tables.iter().find(|t| t.name == *table) else {
+            if keep_live_rows && table_exists(&conn, table)? {
+                conn.execute(
+                    &format!("INSERT INTO main.{t} SELECT * FROM live.{t}", t = table),
+                    [],
+                )
+                .map_err(|e| format!("Failed to read {}: {}", table, e))?;
+            }
+            continue;
+        };
+
+        if !table_exists(&conn, table)? {
+            return Err(format!("This database has no {} table", table));
+        }
+
+        let rows = {
+            let file = zip
+                .by_name(&entry.file)
+                .map_err(|_| format!("Archive is missing {}", entry.file))?;
+            read_rows(file, manifest.format, &entry.file)?
+        };
+
+        load_table(&conn, table, &entry.file, rows, &mut errors)?;
+        archived.push(*table);
+
+        // Archives leave credentials out, so the live ones are kept unless the archive has them
+        if *table == "settings" && keep_live_rows {
+            let placeholders: Vec<String> = (1..=PRIVATE_SETTINGS.len())
+                .map(|n| format!("?{}", n))
+                .collect();
+            conn.execute(
+                &format!(
+                    "INSERT INTO main.settings (key, value, updated_at)
+                     SELECT key, value, updated_at FROM live.settings
+                     WHERE key IN ({}) AND key NOT IN (SELECT key FROM main.settings)",
+                    placeholders.join(", ")
+                ),
+                params_from_iter(PRIVATE_SETTINGS),
+            )
+            .map_err(|e| format!("Failed to keep settings: {}", e))?;
+        }
+
+        if errors.len() >= MAX_IMPORT_ERRORS {
+            break;
+        }
+    }
+
+    conn.execute("COMMIT", [])
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    if errors.is_empty() {
+        errors = foreign_key_errors(&conn)?;
+    }
+    if !errors.is_empty() {
+        errors.truncate(MAX_IMPORT_ERRORS);
+        return Err(format!(
+            "The archive was not imported:\n{}",
+            errors.join("\n")
+        ));
+    }
+
+    // Only the archived tables replace live data
+    for table in DATA_TABLES {
+        if keep_live_rows && !archived.contains(table) && table_exists(&conn, table)? {
+            conn.execute(&format!("DROP TABLE main.{}", table), [])
+                .map_err(|e| format!("Failed to drop {}: {}", table, e))?;
+        }
+    }
+
+    conn.execute("DETACH DATABASE live", [])
+        .map_err(|e| format!("Failed to close database: {}", e))?;
+    drop(conn);
+
+    Ok((loaded, archived))
+}
+
+// ============================================
+// COMMANDS
+// ============================================
+
+/// Exports every data table to a zip of CSV (`format: "csv"`, the default) or JSON files
+/// with a `manifest.json` describing the schema. Users and credential settings are left out.
+/// Runs in the background, emitting `operation-progress` events for `operation_id`
+#[tauri::command]
+pub async fn export_portable_archive(
+    app: AppHandle,
+    destination_path: String,
+    format: Option<String>,
+    operation_id: Option<String>,
+) -> Result<ArchiveManifest, String> {
+    let format = ArchiveFormat::parse(format.as_deref())?;
+
+    operations::run_blocking(app, operation_id, OperationKind::Backup, move |app, op| {
+        let _lock = operations::lock(app, "Exporting data archive", None)?;
+        let db_path = get_db_path(app)?;
+        write_archive(
+            &db_path,
+            Path::new(&destination_path),
+            format,
+            &app_version(app),
+            op,
+        )
+    })
+    .await
+}
+
+/// Replaces the live data of every table in a portable archive with the archive's rows.
+/// Tables the archive does not have are left alone. Nothing is changed unless every
+/// value fits its column and every foreign key resolves. A safety backup is taken first.
+/// Runs in the background, emitting `operation-progress` events for `operation_id`
+#[tauri::command]
+pub async fn import_portable_archive(
+    app: AppHandle,
+    source_path: String,
+    operation_id: Option<String>,
+) -> Result<RestoreResult, String> {
+    operations::run_blocking(app, operation_id, OperationKind::Restore, move |app, op| {
+        let source = Path::new(&source_path);
+        let _lock = operations::lock(
+            app,
+            &format!("Importing {}", operations::file_label(source)),
+            None,
+        )?;
+
+        let db_path = get_db_path(app)?;
+        let (loaded, tables) = load_archive(&db_path, source, true, op)?;
+        import_backup_data(app, loaded.path(), &tables, Some(op))
+    })
+    .await
+}
+
+/// Builds a new database file at `destination_path` holding only the archive's data,
+/// with the same schema as this app's database
+#[tauri::command]
+pub async fn convert_portable_archive(
+    app: AppHandle,
+    source_path: String,
+    destination_path: String,
+) -> Result<String, String> {
+    operations::run_blocking(app, None, OperationKind::Restore, move |app, op| {
+        let destination = Path::new(&destination_path);
+        if destination.exists() {
+            return Err(format!("File already exists: {}", destination_path));
+        }
+
+        let db_path = get_db_path(app)?;
+        let (loaded, tables) = load_archive(&db_path, Path::new(&source_path), false, op)?;
+        fs::copy(loaded.path(), destination)
+            .map_err(|e| format!("Failed to write database: {}", e))?;
+
+        Ok(format!(
+            "Created {} with {} tables from the archive",
+            destination_path,
+            tables.len()
+        ))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{describe_columns, write_table, ArchiveFormat};
+    use crate::migrations::migrate;
+    use rusqlite::Connection;
+
+    #[test]
+    fn credential_settings_are_not_exported() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('offsite_access_key', 'AKIDEXAMPLE');
+             INSERT OR REPLACE INTO settings (key, value) VALUES ('offsite_secret_key', 'wJalrXUtnFEMI');
+             INSERT OR REPLACE INTO settings (key, value) VALUES ('store_name', 'MotorMods');",
+        )
+        .unwrap();
+
+        let columns = describe_columns(&conn, "settings").unwrap();
+        let mut out = Vec::new();
+        write_table(&conn, "settings", &columns, ArchiveFormat::Csv, &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+
+        assert!(csv.contains("MotorMods"));
+        assert!(!csv.contains("offsite_access_key"));
+        assert!(!csv.contains("AKIDEXAMPLE"));
+        assert!(!csv.contains("wJalrXUtnFEMI"));
+    }
+}
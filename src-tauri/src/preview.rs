@@ -0,0 +1,226 @@
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::{get_db_path, incremental, schema, DATA_TABLES};
+
+// ============================================
+// RESTORE PREVIEW
+// ============================================
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableDiff {
+    pub table: String,
+    pub in_live: bool,
+    pub in_backup: bool,
+    pub live_rows: i64,
+    pub backup_rows: i64,
+    /// Rows that would be lost by the restore
+    pub only_in_live: i64,
+    /// Rows that would come back with the restore
+    pub only_in_backup: i64,
+    /// Rows present on both sides whose shared columns differ
+    pub changed: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RestorePreview {
+    pub backup_path: String,
+    pub tables: Vec<TableDiff>,
+    pub live_latest_invoice: Option<String>,
+    pub backup_latest_invoice: Option<String>,
+    /// Schema changes applied to an older backup before it is restored
+    pub schema_upgrades: Vec<String>,
+}
+
+fn columns(conn: &Connection, schema: &str, table: &str) -> Result<Vec<(String, bool)>, String> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA {}.table_info({})", schema, table))
+        .map_err(|e| format!("Failed to get table info: {}", e))?;
+
+    let columns = stmt
+        .query_map([], |row| {
+            let name: String = row.get(1)?;
+            let pk: i64 = row.get(5)?;
+            Ok((name, pk > 0))
+        })
+        .map_err(|e| format!("Failed to query columns: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(columns)
+}
+
+/// Column rows are matched on. Settings are re-inserted with a new id on every save,
+/// so they are matched by key instead.
+fn key_column(table: &str, columns: &[(String, bool)]) -> Option<String> {
+    if table == "settings" {
+        return Some("key".to_string());
+    }
+    columns
+        .iter()
+        .find(|(_, pk)| *pk)
+        .or_else(|| columns.iter().find(|(name, _)| name == "id"))
+        .map(|(name, _)| name.clone())
+}
+
+fn count(conn: &Connection, sql: &str) -> Result<i64, String> {
+    conn.query_row(sql, [], |row| row.get(0))
+        .map_err(|e| format!("Failed to compare tables: {}", e))
+}
+
+fn diff_table(conn: &Connection, table: &str) -> Result<TableDiff, String> {
+    let live_columns = columns(conn, "main", table)?;
+    let backup_columns = columns(conn, "backup", table)?;
+
+    let mut diff = TableDiff {
+        table: table.to_string(),
+        in_live: !live_columns.is_empty(),
+        in_backup: !backup_columns.is_empty(),
+        live_rows: 0,
+        backup_rows: 0,
+        only_in_live: 0,
+        only_in_backup: 0,
+        changed: 0,
+    };
+
+    if diff.in_live {
+        diff.live_rows = count(conn, &format!("SELECT COUNT(*) FROM main.{}", table))?;
+    }
+    if diff.in_backup {
+        diff.backup_rows = count(conn, &format!("SELECT COUNT(*) FROM backup.{}", table))?;
+    }
+
+    // A table missing on one side means every row is on the other side only
+    if !diff.in_live || !diff.in_backup {
+        diff.only_in_live = diff.live_rows;
+        diff.only_in_backup = diff.backup_rows;
+        return Ok(diff);
+    }
+
+    let key = match key_column(table, &live_columns) {
+        Some(key) if backup_columns.iter().any(|(name, _)| *name == key) => key,
+        _ => return Err(format!("Table {} has no common key to compare by", table)),
+    };
+
+    diff.only_in_live = count(
+        conn,
+        &format!(
+            "SELECT COUNT(*) FROM main.{t} l WHERE NOT EXISTS \
+             (SELECT 1 FROM backup.{t} b WHERE b.{k} = l.{k})",
+            t = table,
+            k = key
+        ),
+    )?;
+    diff.only_in_backup = count(
+        conn,
+        &format!(
+            "SELECT COUNT(*) FROM backup.{t} b WHERE NOT EXISTS \
+             (SELECT 1 FROM main.{t} l WHERE l.{k} = b.{k})",
+            t = table,
+            k = key
+        ),
+    )?;
+
+    // Only columns both versions have are compared, so a newer schema alone is not a change.
+    // Settings ids change on every save, so only their values count.
+    let compared: Vec<String> = live_columns
+        .iter()
+        .map(|(name, _)| name)
+        .filter(|name| **name != key && !(table == "settings" && *name == "id"))
+        .filter(|name| backup_columns.iter().any(|(b, _)| b == *name))
+        .map(|name| format!("l.{c} IS NOT b.{c}", c = name))
+        .collect();
+
+    if !compared.is_empty() {
+        diff.changed = count(
+            conn,
+            &format!(
+                "SELECT COUNT(*) FROM main.{t} l JOIN backup.{t} b ON b.{k} = l.{k} WHERE {cond}",
+                t = table,
+                k = key,
+                cond = compared.join(" OR ")
+            ),
+        )?;
+    }
+
+    Ok(diff)
+}
+
+fn latest_invoice(conn: &Connection, schema: &str) -> Result<Option<String>, String> {
+    if columns(conn, schema, "invoices")?.is_empty() {
+        return Ok(None);
+    }
+
+    conn.query_row(
+        &format!("SELECT MAX(created_at) FROM {}.invoices", schema),
+        [],
+        |row| row.get(0),
+    )
+    .optional()
+    .map(|v| v.flatten())
+    .map_err(|e| format!("Failed to read latest invoice: {}", e))
+}
+
+/// Opens the live database read-only with the backup attached as `backup`
+fn open_pair(live_db: &Path, backup_db: &Path) -> Result<Connection, String> {
+    let conn = Connection::open_with_flags(live_db, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open main database: {}", e))?;
+
+    // Attached databases inherit the read-only flag of the main connection
+    conn.execute(
+        "ATTACH DATABASE ?1 AS backup",
+        params![backup_db.to_string_lossy()],
+    )
+    .map_err(|e| format!("Failed to attach backup database: {}", e))?;
+
+    Ok(conn)
+}
+
+/// Shows what a restore would change, table by table, without modifying anything.
+/// Incremental backups are previewed as their replayed chain.
+/// `passphrase` is only needed for encrypted exports
+/// Runs in the background, since it may decrypt, decompress or replay a whole chain
+#[tauri::command]
+pub async fn preview_restore(
+    app: AppHandle,
+    backup_path: String,
+    passphrase: Option<String>,
+) -> Result<RestorePreview, String> {
+    let db_path = get_db_path(&app)?;
+    tauri::async_runtime::spawn_blocking(move || preview(&db_path, backup_path, passphrase))
+        .await
+        .map_err(|e| format!("Failed to preview restore: {}", e))?
+}
+
+fn preview(
+    db_path: &Path,
+    backup_path: String,
+    passphrase: Option<String>,
+) -> Result<RestorePreview, String> {
+    let backup_file = Path::new(&backup_path);
+    if !backup_file.is_file() {
+        return Err(format!("Backup file not found: {}", backup_path));
+    }
+
+    if !db_path.exists() {
+        return Err("Database not found".to_string());
+    }
+
+    let unpacked = incremental::materialize_complete(backup_file, passphrase.as_deref())?;
+    let conn = open_pair(db_path, unpacked.path())?;
+
+    let tables = DATA_TABLES
+        .iter()
+        .map(|table| diff_table(&conn, table))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(RestorePreview {
+        backup_path,
+        tables,
+        live_latest_invoice: latest_invoice(&conn, "main")?,
+        backup_latest_invoice: latest_invoice(&conn, "backup")?,
+        schema_upgrades: schema::describe_upgrades(unpacked.path())?,
+    })
+}
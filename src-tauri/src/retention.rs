@@ -0,0 +1,500 @@
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDateTime};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::incremental::INCREMENTAL_PREFIX;
+use crate::{archive, manifest, operations};
+use crate::{get_backups_dir, get_db_path, read_setting};
+
+// ============================================
+// BACKUP RETENTION (GRANDFATHER-FATHER-SON)
+// ============================================
+
+const REGULAR_PREFIX: &str = "motormods_backup_";
+const SAFETY_PREFIXES: &[&str] = &["pre_restore_safety_", "pre_import_safety_"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Newest backup of each of the last N days
+    pub keep_daily: usize,
+    /// Newest backup of each of the last N ISO weeks
+    pub keep_weekly: usize,
+    /// Newest backup of each of the last N months
+    pub keep_monthly: usize,
+    /// Safety backups always kept, regardless of age
+    pub safety_keep_count: usize,
+    /// Older safety backups are kept until they reach this age
+    pub safety_max_age_days: i64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy {
+            keep_daily: 7,
+            keep_weekly: 4,
+            keep_monthly: 12,
+            safety_keep_count: 3,
+            safety_max_age_days: 30,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Reads the policy from the settings table, using defaults for missing keys
+    pub fn load(conn: &Connection) -> Result<Self, String> {
+        let defaults = RetentionPolicy::default();
+        let number = |key: &str, default: usize| -> Result<usize, String> {
+            Ok(read_setting(conn, key)?
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or(default))
+        };
+
+        Ok(RetentionPolicy {
+            keep_daily: number("backup_keep_daily", defaults.keep_daily)?,
+            keep_weekly: number("backup_keep_weekly", defaults.keep_weekly)?,
+            keep_monthly: number("backup_keep_monthly", defaults.keep_monthly)?,
+            safety_keep_count: number("safety_backup_keep_count", defaults.safety_keep_count)?,
+            safety_max_age_days: number(
+                "safety_backup_max_age_days",
+                defaults.safety_max_age_days as usize,
+            )? as i64,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RetentionReport {
+    pub dry_run: bool,
+    /// Files kept by the policy
+    pub kept: Vec<String>,
+    /// Files deleted, or that would be deleted in a dry run
+    pub deleted: Vec<String>,
+    /// Files that could not be deleted, with the reason
+    pub failed: Vec<String>,
+    /// Newest successful backup, which is never deleted
+    pub protected_newest: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BackupKind {
+    Regular,
+    Safety,
+    /// Kept exactly as long as the full backup its chain starts from
+    Incremental,
+}
+
+#[derive(Debug)]
+struct BackupEntry {
+    filename: String,
+    path: PathBuf,
+    kind: BackupKind,
+    taken_at: NaiveDateTime,
+    /// Full backup an incremental backup's chain starts from
+    base_file: Option<String>,
+}
+
+fn classify(filename: &str) -> Option<(BackupKind, &str)> {
+    let stem = archive::strip_backup_extension(filename)?;
+
+    if let Some(rest) = stem.strip_prefix(REGULAR_PREFIX) {
+        return Some((BackupKind::Regular, rest));
+    }
+
+    if let Some(rest) = stem.strip_prefix(INCREMENTAL_PREFIX) {
+        return Some((BackupKind::Incremental, rest));
+    }
+
+    SAFETY_PREFIXES
+        .iter()
+        .find_map(|prefix| stem.strip_prefix(prefix))
+        .map(|rest| (BackupKind::Safety, rest))
+}
+
+/// Collects the backups in the directory, dated by the timestamp in the filename
+/// (or the file's modified time when the name has none). Unrecognised files are ignored.
+fn scan_backups(backups_dir: &Path) -> Result<Vec<BackupEntry>, String> {
+    let entries =
+        fs::read_dir(backups_dir).map_err(|e| format!("Failed to read backups directory: {}", e))?;
+
+    let mut backups = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(filename) = path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+            continue;
+        };
+        let Some((kind, timestamp)) = classify(&filename) else {
+            continue;
+        };
+
+        let taken_at = NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d_%H-%M-%S")
+            .ok()
+            .or_else(|| {
+                entry
+                    .metadata()
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .map(|t| DateTime::<Local>::from(t).naive_local())
+            });
+
+        // An increment whose base cannot be determined is unrestorable and gets no base
+        let base_file = if kind == BackupKind::Incremental {
+            manifest::read_manifest(&path)
+                .or_else(|| manifest::load_or_compute(&path).ok())
+                .and_then(|m| m.base_file)
+        } else {
+            None
+        };
+
+        if let Some(taken_at) = taken_at {
+            backups.push(BackupEntry {
+                filename,
+                path,
+                kind,
+                taken_at,
+                base_file,
+            });
+        }
+    }
+
+    // Newest first
+    backups.sort_by_key(|b| std::cmp::Reverse(b.taken_at));
+    Ok(backups)
+}
+
+/// Keeps the newest backup in each of the first `limit` distinct buckets
+fn keep_per_bucket<K: Eq + std::hash::Hash>(
+    regular: &[&BackupEntry],
+    limit: usize,
+    bucket: impl Fn(&NaiveDateTime) -> K,
+    keep: &mut HashSet<String>,
+) {
+    let mut seen = HashSet::new();
+    for backup in regular {
+        if seen.len() >= limit {
+            break;
+        }
+        if seen.insert(bucket(&backup.taken_at)) {
+            keep.insert(backup.filename.clone());
+        }
+    }
+}
+
+/// Decides which backups to keep. Returns the set of kept filenames and the protected newest backup.
+fn plan(
+    backups: &[BackupEntry],
+    policy: &RetentionPolicy,
+    failed_files: &HashSet<String>,
+    now: NaiveDateTime,
+) -> (HashSet<String>, Option<String>) {
+    let mut keep = HashSet::new();
+
+    // Backups logged as failed never fill a daily/weekly/monthly slot
+    let regular: Vec<&BackupEntry> = backups
+        .iter()
+        .filter(|b| b.kind == BackupKind::Regular && !failed_files.contains(&b.filename))
+        .collect();
+
+    let protected_newest = regular.first().map(|b| b.filename.clone());
+    if let Some(newest) = &protected_newest {
+        keep.insert(newest.clone());
+    }
+
+    keep_per_bucket(&regular, policy.keep_daily, |t| t.date(), &mut keep);
+    keep_per_bucket(
+        &regular,
+        policy.keep_weekly,
+        |t| {
+            let week = t.iso_week();
+            (week.year(), week.week())
+        },
+        &mut keep,
+    );
+    keep_per_bucket(&regular, policy.keep_monthly, |t| (t.year(), t.month()), &mut keep);
+
+    // Increments are useless without their base, and the base's chain needs every increment
+    for backup in backups.iter().filter(|b| b.kind == BackupKind::Incremental) {
+        if backup.base_file.as_ref().is_some_and(|base| keep.contains(base)) {
+            keep.insert(backup.filename.clone());
+        }
+    }
+
+    let safety_cutoff = now - ChronoDuration::days(policy.safety_max_age_days);
+    for (idx, backup) in backups
+        .iter()
+        .filter(|b| b.kind == BackupKind::Safety)
+        .enumerate()
+    {
+        if idx < policy.safety_keep_count || backup.taken_at >= safety_cutoff {
+            keep.insert(backup.filename.clone());
+        }
+    }
+
+    (keep, protected_newest)
+}
+
+/// Backups whose `backup_log` entry is marked failed may be incomplete and are never
+/// treated as the newest successful backup
+fn failed_backup_files(conn: &Connection) -> HashSet<String> {
+    let mut failed = HashSet::new();
+
+    if let Ok(mut stmt) = conn.prepare("SELECT backup_file FROM backup_log WHERE status = 'failed'") {
+        if let Ok(rows) = stmt.query_map([], |row| row.get::<_, String>(0)) {
+            failed.extend(rows.flatten());
+        }
+    }
+
+    failed
+}
+
+/// Applies the retention policy to the backups directory.
+/// With `dry_run` set, nothing is deleted and the report lists what would be removed.
+pub fn apply_retention(
+    conn: &Connection,
+    backups_dir: &Path,
+    dry_run: bool,
+) -> Result<RetentionReport, String> {
+    let policy = RetentionPolicy::load(conn)?;
+    let backups = scan_backups(backups_dir)?;
+    let failed_files = failed_backup_files(conn);
+
+    let (keep, protected_newest) = plan(
+        &backups,
+        &policy,
+        &failed_files,
+        Local::now().naive_local(),
+    );
+
+    let mut report = RetentionReport {
+        dry_run,
+        kept: Vec::new(),
+        deleted: Vec::new(),
+        failed: Vec::new(),
+        protected_newest,
+    };
+
+    for backup in backups {
+        if keep.contains(&backup.filename) {
+            report.kept.push(backup.filename);
+            continue;
+        }
+
+        if dry_run {
+            report.deleted.push(backup.filename);
+            continue;
+        }
+
+        match fs::remove_file(&backup.path) {
+            Ok(_) => {
+                manifest::remove_manifest(&backup.path);
+                report.deleted.push(backup.filename);
+            }
+            Err(e) => report.failed.push(format!("{}: {}", backup.filename, e)),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Applies (or previews, with `dry_run`) the backup retention policy
+#[tauri::command]
+pub fn apply_backup_retention(app: AppHandle, dry_run: bool) -> Result<RetentionReport, String> {
+    let db_path = get_db_path(&app)?;
+    let backups_dir = get_backups_dir(&app)?;
+
+    if !db_path.exists() {
+        return Err("Database file not found".to_string());
+    }
+
+    // Previews delete nothing, so they can run alongside other operations
+    let _lock = if dry_run {
+        None
+    } else {
+        Some(operations::lock(&app, "Applying backup retention", None)?)
+    };
+
+    let conn = Connection::open(&db_path)
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+
+    apply_retention(&conn, &backups_dir, dry_run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{plan, BackupEntry, BackupKind, RetentionPolicy, REGULAR_PREFIX, SAFETY_PREFIXES};
+    use crate::incremental::INCREMENTAL_PREFIX;
+    use chrono::{Duration as ChronoDuration, NaiveDate, NaiveDateTime};
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+
+    fn at(date: NaiveDate, hour: u32) -> NaiveDateTime {
+        date.and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    fn backup(kind: BackupKind, taken_at: NaiveDateTime, base: Option<&str>) -> BackupEntry {
+        let prefix = match kind {
+            BackupKind::Regular => REGULAR_PREFIX,
+            BackupKind::Safety => SAFETY_PREFIXES[0],
+            BackupKind::Incremental => INCREMENTAL_PREFIX,
+        };
+        let filename = format!("{}{}.db", prefix, taken_at.format("%Y-%m-%d_%H-%M-%S"));
+        BackupEntry {
+            path: PathBuf::from(&filename),
+            filename,
+            kind,
+            taken_at,
+            base_file: base.map(str::to_string),
+        }
+    }
+
+    fn regular_name(taken_at: NaiveDateTime) -> String {
+        backup(BackupKind::Regular, taken_at, None).filename
+    }
+
+    /// Runs `plan` on backups in any order, the way `scan_backups` hands them over
+    fn kept(
+        mut backups: Vec<BackupEntry>,
+        policy: &RetentionPolicy,
+        now: NaiveDateTime,
+    ) -> HashSet<String> {
+        backups.sort_by_key(|b| std::cmp::Reverse(b.taken_at));
+        plan(&backups, policy, &HashSet::new(), now).0
+    }
+
+    #[test]
+    fn month_long_gap_keeps_the_last_backups() {
+        // Daily backups until 20 January, then the shop was closed until 1 March
+        let first = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let backups: Vec<BackupEntry> = (0..20)
+            .map(|d| {
+                backup(
+                    BackupKind::Regular,
+                    at(first + ChronoDuration::days(d), 23),
+                    None,
+                )
+            })
+            .collect();
+        let now = at(NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(), 9);
+
+        let keep = kept(backups, &RetentionPolicy::default(), now);
+
+        let newest = at(NaiveDate::from_ymd_opt(2025, 1, 20).unwrap(), 23);
+        assert!(keep.contains(&regular_name(newest)));
+        for d in 0..7 {
+            let day = newest - ChronoDuration::days(d);
+            assert!(keep.contains(&regular_name(day)), "dropped {}", day);
+        }
+    }
+
+    #[test]
+    fn keeps_newest_backup_per_day_week_and_month() {
+        // Two backups a day through 2024; the newest is Tuesday 31 December
+        let first = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let last = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap();
+        let mut backups = Vec::new();
+        let mut day = first;
+        while day <= last {
+            backups.push(backup(BackupKind::Regular, at(day, 10), None));
+            backups.push(backup(BackupKind::Regular, at(day, 23), None));
+            day += ChronoDuration::days(1);
+        }
+
+        let keep = kept(backups, &RetentionPolicy::default(), at(last, 23));
+
+        let date = |m, d| at(NaiveDate::from_ymd_opt(2024, m, d).unwrap(), 23);
+        let mut expected: HashSet<String> = HashSet::new();
+        // Daily: 25-31 December
+        expected.extend((25..=31).map(|d| regular_name(date(12, d))));
+        // Weekly: the newest of each of the last four ISO weeks
+        expected.extend([31, 29, 22, 15].map(|d| regular_name(date(12, d))));
+        // Monthly: the last day of each month
+        expected.extend(
+            [
+                (1, 31),
+                (2, 29),
+                (3, 31),
+                (4, 30),
+                (5, 31),
+                (6, 30),
+                (7, 31),
+                (8, 31),
+                (9, 30),
+                (10, 31),
+                (11, 30),
+                (12, 31),
+            ]
+            .map(|(m, d)| regular_name(date(m, d))),
+        );
+
+        assert_eq!(keep, expected);
+    }
+
+    #[test]
+    fn increments_are_kept_only_with_their_base() {
+        let policy = RetentionPolicy {
+            keep_daily: 1,
+            keep_weekly: 0,
+            keep_monthly: 0,
+            ..RetentionPolicy::default()
+        };
+        let day1 = NaiveDate::from_ymd_opt(2025, 5, 1).unwrap();
+        let day2 = NaiveDate::from_ymd_opt(2025, 5, 2).unwrap();
+
+        let old_full = backup(BackupKind::Regular, at(day1, 8), None);
+        let new_full = backup(BackupKind::Regular, at(day2, 8), None);
+        let old_increments = [
+            backup(
+                BackupKind::Incremental,
+                at(day1, 12),
+                Some(&old_full.filename),
+            ),
+            backup(
+                BackupKind::Incremental,
+                at(day1, 18),
+                Some(&old_full.filename),
+            ),
+        ];
+        let new_increment = backup(
+            BackupKind::Incremental,
+            at(day2, 12),
+            Some(&new_full.filename),
+        );
+        let orphan = backup(BackupKind::Incremental, at(day2, 18), None);
+
+        let expected: HashSet<String> = [new_full.filename.clone(), new_increment.filename.clone()]
+            .into_iter()
+            .collect();
+
+        let mut backups = vec![old_full, new_full, new_increment, orphan];
+        backups.extend(old_increments);
+        assert_eq!(kept(backups, &policy, at(day2, 20)), expected);
+    }
+
+    #[test]
+    fn safety_backups_kept_by_count_or_age() {
+        let policy = RetentionPolicy {
+            safety_keep_count: 1,
+            safety_max_age_days: 30,
+            ..RetentionPolicy::default()
+        };
+        let now = at(NaiveDate::from_ymd_opt(2025, 6, 30).unwrap(), 12);
+        let safety = |ages: &[i64]| -> Vec<BackupEntry> {
+            ages.iter()
+                .map(|days| backup(BackupKind::Safety, now - ChronoDuration::days(*days), None))
+                .collect()
+        };
+        let name =
+            |days: i64| backup(BackupKind::Safety, now - ChronoDuration::days(days), None).filename;
+
+        // 40 days: the newest, kept by count; 45 days: too old and not among the newest
+        let keep = kept(safety(&[40, 45]), &policy, now);
+        assert_eq!(keep, HashSet::from([name(40)]));
+
+        // 10 days: within the age limit even though another one is newer
+        let keep = kept(safety(&[5, 10, 45]), &policy, now);
+        assert_eq!(keep, HashSet::from([name(5), name(10)]));
+    }
+}
@@ -0,0 +1,1160 @@
+use chrono::{DateTime, Duration as ChronoDuration, NaiveDate, NaiveDateTime, Utc};
+use rusqlite::{params, OptionalExtension, Row, Transaction, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::get_db_path;
+use crate::sales::{now_rfc3339, open_for_write, record_adjustment, StockLevel};
+
+// ============================================
+// SALES RETURNS
+// ============================================
+//
+// A return puts stock back for products sold on one invoice. Every line is checked
+// against what the invoice sold minus what earlier returns that are not cancelled
+// already took back, and the header, lines, stock and stock adjustments are written
+// in one IMMEDIATE transaction, so two counters cannot return the same units twice.
+
+/// Reasons offered by the returns screen (see motormods_spec.md, "Sales Returns")
+pub const RETURN_REASONS: &[&str] = &[
+    "damage",
+    "wrong_part",
+    "customer_request",
+    "defective",
+    "other",
+];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewSalesReturn {
+    pub invoice_id: String,
+    /// One of `RETURN_REASONS`
+    pub reason: String,
+    pub notes: Option<String>,
+    pub items: Vec<NewReturnItem>,
+    /// Recorded on the stock adjustments; defaults to `system`
+    pub created_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewReturnItem {
+    pub product_id: String,
+    pub quantity: i64,
+    pub rate: f64,
+}
+
+/// A `sales_returns` row
+#[derive(Debug, Clone, Serialize)]
+pub struct SalesReturn {
+    pub id: String,
+    pub return_no: String,
+    pub invoice_id: String,
+    pub return_date: String,
+    pub reason: String,
+    pub total_amount: f64,
+    pub notes: Option<String>,
+    pub status: String,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub cancelled_by: Option<String>,
+    pub cancelled_at: Option<String>,
+    pub amended_by: Option<String>,
+    pub amended_at: Option<String>,
+}
+
+impl SalesReturn {
+    const COLUMNS: &'static str = "id, return_no, invoice_id, return_date, reason, total_amount, notes, COALESCE(status, 'completed'), created_at, updated_at, cancelled_by, cancelled_at, amended_by, amended_at";
+
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(SalesReturn {
+            id: row.get(0)?,
+            return_no: row.get(1)?,
+            invoice_id: row.get(2)?,
+            return_date: row.get(3)?,
+            reason: row.get(4)?,
+            total_amount: row.get(5)?,
+            notes: row.get(6)?,
+            status: row.get(7)?,
+            created_at: row.get(8)?,
+            updated_at: row.get(9)?,
+            cancelled_by: row.get(10)?,
+            cancelled_at: row.get(11)?,
+            amended_by: row.get(12)?,
+            amended_at: row.get(13)?,
+        })
+    }
+}
+
+/// A return line asking for more than can still be returned
+#[derive(Debug, Clone, Serialize)]
+pub struct ReturnShortage {
+    pub product_id: String,
+    /// `None` if the product no longer exists
+    pub product_name: Option<String>,
+    pub requested: i64,
+    /// Quantity on the original invoice; 0 if the invoice did not sell this product
+    pub sold: i64,
+    /// Quantity taken back by earlier returns that are not cancelled
+    pub already_returned: i64,
+    /// What this line could still return, after earlier lines of the same product
+    pub available: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CreateReturnResult {
+    pub success: bool,
+    pub message: String,
+    /// The saved return; `None` if it was refused
+    pub sales_return: Option<SalesReturn>,
+    /// Why the return was refused; empty on success
+    pub shortages: Vec<ReturnShortage>,
+    /// New stock of every product returned; empty if the return was refused
+    pub stock: Vec<StockLevel>,
+}
+
+/// Sold and already returned quantities of a product on one invoice
+#[derive(Debug, Clone, Default)]
+struct Returnable {
+    product_name: Option<String>,
+    sold: i64,
+    returned: i64,
+}
+
+fn validate_reason(reason: &str) -> Result<(), String> {
+    if RETURN_REASONS.contains(&reason) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid return reason: {}. Expected one of: {}",
+            reason,
+            RETURN_REASONS.join(", ")
+        ))
+    }
+}
+
+fn validate_items(items: &[NewReturnItem]) -> Result<(), String> {
+    if items.is_empty() {
+        return Err("A return needs at least one item".to_string());
+    }
+
+    for item in items {
+        if item.product_id.trim().is_empty() {
+            return Err("Every return item needs a product".to_string());
+        }
+        if item.quantity <= 0 {
+            return Err(format!(
+                "Return quantity must be positive (product {})",
+                item.product_id
+            ));
+        }
+        if !item.rate.is_finite() || item.rate < 0.0 {
+            return Err(format!("Invalid rate for product {}", item.product_id));
+        }
+    }
+
+    Ok(())
+}
+
+/// Per product, what `invoice_id` sold and what its returns other than `except_return`
+/// have already taken back, leaving cancelled returns out
+fn returnable_quantities(
+    tx: &Transaction,
+    invoice_id: &str,
+    except_return: Option<&str>,
+) -> Result<HashMap<String, Returnable>, String> {
+    let mut quantities: HashMap<String, Returnable> = HashMap::new();
+
+    let mut stmt = tx
+        .prepare(
+            "SELECT ii.product_id, p.name, SUM(ii.quantity)
+             FROM invoice_items ii
+             LEFT JOIN products p ON p.id = ii.product_id
+             WHERE ii.invoice_id = ?1
+             GROUP BY ii.product_id",
+        )
+        .map_err(|e| format!("Failed to read invoice items: {}", e))?;
+    let sold = stmt
+        .query_map([invoice_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to read invoice items: {}", e))?;
+    for row in sold {
+        let (product_id, product_name, quantity) =
+            row.map_err(|e| format!("Failed to read invoice items: {}", e))?;
+        let entry = quantities.entry(product_id).or_default();
+        entry.product_name = product_name;
+        entry.sold = quantity;
+    }
+
+    let mut stmt = tx
+        .prepare(
+            "SELECT ri.product_id, SUM(ri.quantity)
+             FROM return_items ri
+             JOIN sales_returns sr ON sr.id = ri.return_id
+             WHERE sr.invoice_id = ?1
+               AND COALESCE(sr.status, 'completed') != 'cancelled'
+               AND sr.id IS NOT ?2
+             GROUP BY ri.product_id",
+        )
+        .map_err(|e| format!("Failed to read earlier returns: {}", e))?;
+    let returned = stmt
+        .query_map(params![invoice_id, except_return], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })
+        .map_err(|e| format!("Failed to read earlier returns: {}", e))?;
+    for row in returned {
+        let (product_id, quantity) =
+            row.map_err(|e| format!("Failed to read earlier returns: {}", e))?;
+        quantities.entry(product_id).or_default().returned = quantity;
+    }
+
+    Ok(quantities)
+}
+
+/// Checks every line against `quantities`, counting earlier lines of the same product
+fn find_shortages(
+    tx: &Transaction,
+    items: &[NewReturnItem],
+    quantities: &HashMap<String, Returnable>,
+) -> Result<Vec<ReturnShortage>, String> {
+    let mut claimed: HashMap<&str, i64> = HashMap::new();
+    let mut shortages = Vec::new();
+
+    for item in items {
+        let returnable = quantities
+            .get(&item.product_id)
+            .cloned()
+            .unwrap_or_default();
+        let earlier = claimed.entry(&item.product_id).or_insert(0);
+        let available = (returnable.sold - returnable.returned - *earlier).max(0);
+        *earlier += item.quantity;
+
+        if item.quantity > available {
+            let product_name = match returnable.product_name {
+                Some(name) => Some(name),
+                None => tx
+                    .query_row(
+                        "SELECT name FROM products WHERE id = ?1",
+                        [&item.product_id],
+                        |row| row.get(0),
+                    )
+                    .optional()
+                    .map_err(|e| format!("Failed to read product {}: {}", item.product_id, e))?,
+            };
+
+            shortages.push(ReturnShortage {
+                product_id: item.product_id.clone(),
+                product_name,
+                requested: item.quantity,
+                sold: returnable.sold,
+                already_returned: returnable.returned,
+                available,
+            });
+        }
+    }
+
+    Ok(shortages)
+}
+
+fn shortage_message(shortages: &[ReturnShortage]) -> String {
+    shortages
+        .iter()
+        .map(|s| {
+            let name = s.product_name.as_deref().unwrap_or(&s.product_id);
+            if s.sold == 0 {
+                format!("{} was not sold on this invoice.", name)
+            } else {
+                format!(
+                    "Cannot return {} of {}: sold {}, already returned {}, {} left to return.",
+                    s.requested, name, s.sold, s.already_returned, s.available
+                )
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Next return number for `day`, `RET-YYYYMMDD-NNN`
+fn next_return_number(tx: &Transaction, day: NaiveDate) -> Result<String, String> {
+    let prefix = format!("RET-{}", day.format("%Y%m%d"));
+
+    let last: i64 = tx
+        .query_row(
+            "SELECT COALESCE(MAX(CAST(substr(return_no, ?2) AS INTEGER)), 0)
+             FROM sales_returns WHERE return_no LIKE ?1 || '-%'",
+            params![prefix, prefix.len() as i64 + 2],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to number return: {}", e))?;
+
+    Ok(format!("{}-{:03}", prefix, last + 1))
+}
+
+/// Adds `quantity` (negative to take away) to a product's stock and returns the new level
+fn change_stock(tx: &Transaction, product_id: &str, quantity: i64) -> Result<i64, String> {
+    tx.query_row(
+        "UPDATE products SET quantity = quantity + ?1, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?2 RETURNING quantity",
+        params![quantity, product_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| format!("Failed to update stock of {}: {}", product_id, e))?
+    .ok_or_else(|| format!("Product not found: {}", product_id))
+}
+
+/// Writes `items` as the lines of `return_id`
+fn insert_return_lines(
+    tx: &Transaction,
+    return_id: &str,
+    items: &[NewReturnItem],
+) -> Result<(), String> {
+    for item in items {
+        tx.execute(
+            "INSERT INTO return_items (id, return_id, product_id, quantity, rate, line_total)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                uuid::Uuid::new_v4().to_string(),
+                return_id,
+                item.product_id,
+                item.quantity,
+                item.rate,
+                item.quantity as f64 * item.rate
+            ],
+        )
+        .map_err(|e| format!("Failed to save return item for {}: {}", item.product_id, e))?;
+    }
+    Ok(())
+}
+
+/// Which return is moving stock, who is moving it, and the note logged with it
+struct StockNote<'a> {
+    return_id: &'a str,
+    notes: &'a str,
+    by: &'a str,
+    at: &'a str,
+}
+
+/// Changes a product's stock by `quantity` and logs it: stock put back is a `return`,
+/// stock taken away again a `manual_deduction`. Records the new level in `stock`.
+fn move_stock(
+    tx: &Transaction,
+    product_id: &str,
+    quantity: i64,
+    note: &StockNote,
+    stock: &mut HashMap<String, i64>,
+) -> Result<(), String> {
+    let level = change_stock(tx, product_id, quantity)?;
+    stock.insert(product_id.to_string(), level);
+
+    let adjustment_type = if quantity > 0 {
+        "return"
+    } else {
+        "manual_deduction"
+    };
+    record_adjustment(
+        tx,
+        product_id,
+        adjustment_type,
+        quantity,
+        note.notes,
+        Some(note.return_id),
+        note.by,
+        note.at,
+    )
+}
+
+fn load_return(tx: &Transaction, id: &str) -> Result<Option<SalesReturn>, String> {
+    tx.query_row(
+        &format!(
+            "SELECT {} FROM sales_returns WHERE id = ?1",
+            SalesReturn::COLUMNS
+        ),
+        [id],
+        SalesReturn::from_row,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to read return {}: {}", id, e))
+}
+
+fn stock_levels(stock: HashMap<String, i64>) -> Vec<StockLevel> {
+    let mut levels: Vec<StockLevel> = stock
+        .into_iter()
+        .map(|(product_id, quantity)| StockLevel {
+            product_id,
+            quantity,
+        })
+        .collect();
+    levels.sort_by(|a, b| a.product_id.cmp(&b.product_id));
+    levels
+}
+
+fn create_return_in(
+    db_path: &Path,
+    request: &NewSalesReturn,
+) -> Result<CreateReturnResult, String> {
+    validate_reason(&request.reason)?;
+    validate_items(&request.items)?;
+    let created_by = request.created_by.as_deref().unwrap_or("system");
+
+    let mut conn = open_for_write(db_path)?;
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Failed to start return: {}", e))?;
+
+    let invoice_exists: bool = tx
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM invoices WHERE id = ?1",
+            [&request.invoice_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to read invoice: {}", e))?;
+    if !invoice_exists {
+        return Err(format!("Invoice not found: {}", request.invoice_id));
+    }
+
+    let quantities = returnable_quantities(&tx, &request.invoice_id, None)?;
+    let shortages = find_shortages(&tx, &request.items, &quantities)?;
+    if !shortages.is_empty() {
+        return Ok(CreateReturnResult {
+            success: false,
+            message: shortage_message(&shortages),
+            sales_return: None,
+            shortages,
+            stock: Vec::new(),
+        });
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let return_no = next_return_number(&tx, Utc::now().date_naive())?;
+    let now = now_rfc3339();
+    let total_amount: f64 = request
+        .items
+        .iter()
+        .map(|i| i.quantity as f64 * i.rate)
+        .sum();
+
+    tx.execute(
+        "INSERT INTO sales_returns (id, return_no, invoice_id, return_date, reason, total_amount, notes, status, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'completed', ?4, ?4)",
+        params![
+            id,
+            return_no,
+            request.invoice_id,
+            now,
+            request.reason,
+            total_amount,
+            request.notes
+        ],
+    )
+    .map_err(|e| format!("Failed to save return: {}", e))?;
+
+    insert_return_lines(&tx, &id, &request.items)?;
+
+    let mut stock = HashMap::new();
+    let notes = format!("Return {}: {}", return_no, request.reason);
+    let note = StockNote {
+        return_id: &id,
+        notes: &notes,
+        by: created_by,
+        at: &now,
+    };
+    for item in &request.items {
+        move_stock(&tx, &item.product_id, item.quantity, &note, &mut stock)?;
+    }
+
+    let sales_return = load_return(&tx, &id)?;
+    tx.commit()
+        .map_err(|e| format!("Failed to save return: {}", e))?;
+
+    Ok(CreateReturnResult {
+        success: true,
+        message: format!("Return {} created", return_no),
+        sales_return,
+        shortages: Vec::new(),
+        stock: stock_levels(stock),
+    })
+}
+
+/// Records a sales return against an invoice: checks each line against what the invoice
+/// sold minus earlier returns that are not cancelled, numbers the return, saves it and
+/// puts the stock back with a stock adjustment per line, all in one transaction.
+/// Lines asking for too much are reported in `shortages` with `success: false`.
+#[tauri::command]
+pub async fn create_sales_return(
+    app: AppHandle,
+    sales_return: NewSalesReturn,
+) -> Result<CreateReturnResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        create_return_in(&get_db_path(&app)?, &sales_return)
+    })
+    .await
+    .map_err(|e| format!("Failed to create return: {}", e))?
+}
+
+// ============================================
+// CANCELLING AND AMENDING RETURNS
+// ============================================
+//
+// A return can be cancelled or amended within a grace window after it was created
+// (`return_grace_hours`, 24 by default). Stock the return put back is taken away again
+// with compensating adjustments, and the change is refused if a product no longer has
+// that stock, e.g. because the returned units were sold again since.
+
+const GRACE_SETTING: &str = "return_grace_hours";
+const DEFAULT_GRACE_HOURS: i64 = 24;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SalesReturnAmendment {
+    pub return_id: String,
+    /// One of `RETURN_REASONS`
+    pub reason: String,
+    pub notes: Option<String>,
+    /// Replaces all lines of the return
+    pub items: Vec<NewReturnItem>,
+    /// Recorded on the return and the stock adjustments; defaults to `system`
+    pub amended_by: Option<String>,
+}
+
+/// A product that does not have the stock a cancellation or amendment would take away
+#[derive(Debug, Clone, Serialize)]
+pub struct StockConflict {
+    pub product_id: String,
+    pub product_name: Option<String>,
+    pub in_stock: i64,
+    pub to_remove: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReturnChangeResult {
+    pub success: bool,
+    pub message: String,
+    /// The return after the change; `None` if it was refused
+    pub sales_return: Option<SalesReturn>,
+    /// Amended lines asking for more than the invoice has left to return
+    pub shortages: Vec<ReturnShortage>,
+    /// Products that would go below zero stock
+    pub stock_conflicts: Vec<StockConflict>,
+    /// New stock of every product whose stock changed; empty if the change was refused
+    pub stock: Vec<StockLevel>,
+}
+
+impl ReturnChangeResult {
+    fn refused(
+        message: String,
+        shortages: Vec<ReturnShortage>,
+        stock_conflicts: Vec<StockConflict>,
+    ) -> Self {
+        ReturnChangeResult {
+            success: false,
+            message,
+            sales_return: None,
+            shortages,
+            stock_conflicts,
+            stock: Vec::new(),
+        }
+    }
+}
+
+fn grace_hours(tx: &Transaction) -> Result<i64, String> {
+    Ok(crate::read_setting(tx, GRACE_SETTING)?
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .filter(|h| *h >= 0)
+        .unwrap_or(DEFAULT_GRACE_HOURS))
+}
+
+/// Parses the RFC 3339 timestamps the app writes and SQLite's `CURRENT_TIMESTAMP` (UTC)
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").map(|t| t.and_utc()))
+        .ok()
+}
+
+/// Refuses changes to a cancelled return or one older than the grace window
+fn ensure_changeable(
+    tx: &Transaction,
+    sales_return: &SalesReturn,
+    action: &str,
+) -> Result<(), String> {
+    if sales_return.status == "cancelled" {
+        return Err(format!(
+            "Return {} is already cancelled",
+            sales_return.return_no
+        ));
+    }
+
+    let hours = grace_hours(tx)?;
+    let created = sales_return
+        .created_at
+        .as_deref()
+        .and_then(parse_timestamp)
+        .or_else(|| parse_timestamp(&sales_return.return_date))
+        .ok_or_else(|| {
+            format!(
+                "Return {} has no valid creation time and cannot be {}",
+                sales_return.return_no, action
+            )
+        })?;
+
+    if Utc::now() - created > ChronoDuration::hours(hours) {
+        return Err(format!(
+            "Return {} can no longer be {}: returns can only be changed within {} hours of being created",
+            sales_return.return_no, action, hours
+        ));
+    }
+
+    Ok(())
+}
+
+/// Product and quantity of each line of a return
+fn return_lines(tx: &Transaction, return_id: &str) -> Result<Vec<(String, i64)>, String> {
+    let mut stmt = tx
+        .prepare(
+            "SELECT product_id, quantity FROM return_items WHERE return_id = ?1 ORDER BY rowid",
+        )
+        .map_err(|e| format!("Failed to read return items: {}", e))?;
+    let lines = stmt
+        .query_map([return_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Failed to read return items: {}", e))?
+        .collect::<Result<Vec<(String, i64)>, _>>()
+        .map_err(|e| format!("Failed to read return items: {}", e))?;
+    Ok(lines)
+}
+
+/// Adds up stock changes per product, keeping the order products first appear in
+fn net_changes(changes: impl IntoIterator<Item = (String, i64)>) -> Vec<(String, i64)> {
+    let mut net: Vec<(String, i64)> = Vec::new();
+    for (product_id, quantity) in changes {
+        match net.iter_mut().find(|(id, _)| *id == product_id) {
+            Some((_, total)) => *total += quantity,
+            None => net.push((product_id, quantity)),
+        }
+    }
+    net
+}
+
+/// Products whose stock would go below zero with `changes` applied
+fn stock_conflicts(
+    tx: &Transaction,
+    changes: &[(String, i64)],
+) -> Result<Vec<StockConflict>, String> {
+    let mut conflicts = Vec::new();
+
+    for (product_id, quantity) in changes.iter().filter(|(_, q)| *q < 0) {
+        let product: Option<(String, i64)> = tx
+            .query_row(
+                "SELECT name, quantity FROM products WHERE id = ?1",
+                [product_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to read product {}: {}", product_id, e))?;
+
+        let in_stock = product.as_ref().map_or(0, |(_, q)| *q);
+        if in_stock + quantity < 0 {
+            conflicts.push(StockConflict {
+                product_id: product_id.clone(),
+                product_name: product.map(|(name, _)| name),
+                in_stock,
+                to_remove: -quantity,
+            });
+        }
+    }
+
+    Ok(conflicts)
+}
+
+fn conflict_message(return_no: &str, action: &str, conflicts: &[StockConflict]) -> String {
+    let products = conflicts
+        .iter()
+        .map(|c| {
+            format!(
+                "{} (in stock {}, would remove {})",
+                c.product_name.as_deref().unwrap_or(&c.product_id),
+                c.in_stock,
+                c.to_remove
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "Return {} cannot be {}: stock would go below zero for {}.",
+        return_no, action, products
+    )
+}
+
+fn cancel_return_in(
+    db_path: &Path,
+    return_id: &str,
+    cancelled_by: &str,
+) -> Result<ReturnChangeResult, String> {
+    let mut conn = open_for_write(db_path)?;
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Failed to start cancellation: {}", e))?;
+
+    let sales_return =
+        load_return(&tx, return_id)?.ok_or_else(|| format!("Return not found: {}", return_id))?;
+    ensure_changeable(&tx, &sales_return, "cancelled")?;
+
+    let lines = return_lines(&tx, return_id)?;
+    let changes = net_changes(lines.iter().map(|(p, q)| (p.clone(), -q)));
+    let conflicts = stock_conflicts(&tx, &changes)?;
+    if !conflicts.is_empty() {
+        return Ok(ReturnChangeResult::refused(
+            conflict_message(&sales_return.return_no, "cancelled", &conflicts),
+            Vec::new(),
+            conflicts,
+        ));
+    }
+
+    let now = now_rfc3339();
+    tx.execute(
+        "UPDATE sales_returns
+         SET status = 'cancelled', cancelled_by = ?1, cancelled_at = ?2, updated_at = ?2
+         WHERE id = ?3",
+        params![cancelled_by, now, return_id],
+    )
+    .map_err(|e| format!("Failed to cancel return: {}", e))?;
+
+    let mut stock = HashMap::new();
+    let notes = format!("Cancelled return {}", sales_return.return_no);
+    let note = StockNote {
+        return_id: &sales_return.id,
+        notes: &notes,
+        by: cancelled_by,
+        at: &now,
+    };
+    for (product_id, quantity) in &lines {
+        move_stock(&tx, product_id, -quantity, &note, &mut stock)?;
+    }
+
+    let updated = load_return(&tx, return_id)?;
+    tx.commit()
+        .map_err(|e| format!("Failed to cancel return: {}", e))?;
+
+    Ok(ReturnChangeResult {
+        success: true,
+        message: format!("Return {} cancelled", sales_return.return_no),
+        sales_return: updated,
+        shortages: Vec::new(),
+        stock_conflicts: Vec::new(),
+        stock: stock_levels(stock),
+    })
+}
+
+fn amend_return_in(
+    db_path: &Path,
+    amendment: &SalesReturnAmendment,
+) -> Result<ReturnChangeResult, String> {
+    validate_reason(&amendment.reason)?;
+    validate_items(&amendment.items)?;
+    let amended_by = amendment.amended_by.as_deref().unwrap_or("system");
+
+    let mut conn = open_for_write(db_path)?;
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Failed to start amendment: {}", e))?;
+
+    let sales_return = load_return(&tx, &amendment.return_id)?
+        .ok_or_else(|| format!("Return not found: {}", amendment.return_id))?;
+    ensure_changeable(&tx, &sales_return, "amended")?;
+
+    // The return's own lines are left out so its quantities can be kept or lowered
+    let quantities = returnable_quantities(&tx, &sales_return.invoice_id, Some(&sales_return.id))?;
+    let shortages = find_shortages(&tx, &amendment.items, &quantities)?;
+    if !shortages.is_empty() {
+        return Ok(ReturnChangeResult::refused(
+            shortage_message(&shortages),
+            shortages,
+            Vec::new(),
+        ));
+    }
+
+    let old_lines = return_lines(&tx, &sales_return.id)?;
+    let changes = net_changes(
+        old_lines.into_iter().map(|(p, q)| (p, -q)).chain(
+            amendment
+                .items
+                .iter()
+                .map(|i| (i.product_id.clone(), i.quantity)),
+        ),
+    );
+    let conflicts = stock_conflicts(&tx, &changes)?;
+    if !conflicts.is_empty() {
+        return Ok(ReturnChangeResult::refused(
+            conflict_message(&sales_return.return_no, "amended", &conflicts),
+            Vec::new(),
+            conflicts,
+        ));
+    }
+
+    tx.execute(
+        "DELETE FROM return_items WHERE return_id = ?1",
+        [&sales_return.id],
+    )
+    .map_err(|e| format!("Failed to replace return items: {}", e))?;
+    insert_return_lines(&tx, &sales_return.id, &amendment.items)?;
+
+    let now = now_rfc3339();
+    let mut stock = HashMap::new();
+    let notes = format!(
+        "Amended return {}: {}",
+        sales_return.return_no, amendment.reason
+    );
+    let note = StockNote {
+        return_id: &sales_return.id,
+        notes: &notes,
+        by: amended_by,
+        at: &now,
+    };
+    for (product_id, quantity) in changes.iter().filter(|(_, q)| *q != 0) {
+        move_stock(&tx, product_id, *quantity, &note, &mut stock)?;
+    }
+
+    let total_amount: f64 = amendment
+        .items
+        .iter()
+        .map(|i| i.quantity as f64 * i.rate)
+        .sum();
+    tx.execute(
+        "UPDATE sales_returns
+         SET reason = ?1, notes = ?2, total_amount = ?3, amended_by = ?4, amended_at = ?5, updated_at = ?5
+         WHERE id = ?6",
+        params![
+            amendment.reason,
+            amendment.notes,
+            total_amount,
+            amended_by,
+            now,
+            sales_return.id
+        ],
+    )
+    .map_err(|e| format!("Failed to amend return: {}", e))?;
+
+    let updated = load_return(&tx, &sales_return.id)?;
+    tx.commit()
+        .map_err(|e| format!("Failed to amend return: {}", e))?;
+
+    Ok(ReturnChangeResult {
+        success: true,
+        message: format!("Return {} amended", sales_return.return_no),
+        sales_return: updated,
+        shortages: Vec::new(),
+        stock_conflicts: Vec::new(),
+        stock: stock_levels(stock),
+    })
+}
+
+/// Cancels a return within the grace window, taking the stock it put back away again.
+/// Refused with `stock_conflicts` if a product no longer has that stock.
+#[tauri::command]
+pub async fn cancel_sales_return(
+    app: AppHandle,
+    return_id: String,
+    cancelled_by: Option<String>,
+) -> Result<ReturnChangeResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let cancelled_by = cancelled_by.as_deref().unwrap_or("system");
+        cancel_return_in(&get_db_path(&app)?, &return_id, cancelled_by)
+    })
+    .await
+    .map_err(|e| format!("Failed to cancel return: {}", e))?
+}
+
+/// Replaces the reason, notes and lines of a return within the grace window. Lines are
+/// checked against the invoice like a new return, and stock moves by the difference.
+#[tauri::command]
+pub async fn amend_sales_return(
+    app: AppHandle,
+    amendment: SalesReturnAmendment,
+) -> Result<ReturnChangeResult, String> {
+    tauri::async_runtime::spawn_blocking(move || amend_return_in(&get_db_path(&app)?, &amendment))
+        .await
+        .map_err(|e| format!("Failed to amend return: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        amend_return_in, cancel_return_in, create_return_in, next_return_number, NewReturnItem,
+        NewSalesReturn, SalesReturnAmendment,
+    };
+    use crate::migrations;
+    use crate::test_support::migrated_db;
+    use chrono::NaiveDate;
+    use rusqlite::{params, Connection};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// A live database where invoice `inv` sold 3 brake pads, with 7 left in stock
+    fn setup() -> (PathBuf, Connection) {
+        let (db_path, conn) = migrated_db("returns");
+        conn.execute_batch(
+            "INSERT INTO products (id, name, price, quantity) VALUES ('pad', 'Brake pad', 450, 7);
+             INSERT INTO products (id, name, price, quantity) VALUES ('filter', 'Oil filter', 200, 4);
+             INSERT INTO invoices (id, customer_name, total_amount) VALUES ('inv', 'Ravi', 1350);
+             INSERT INTO invoice_items (id, invoice_id, product_id, quantity, price)
+                 VALUES ('item1', 'inv', 'pad', 3, 450);",
+        )
+        .unwrap();
+
+        (db_path, conn)
+    }
+
+    fn request(reason: &str, items: &[(&str, i64)]) -> NewSalesReturn {
+        NewSalesReturn {
+            invoice_id: "inv".to_string(),
+            reason: reason.to_string(),
+            notes: None,
+            items: items
+                .iter()
+                .map(|(product_id, quantity)| NewReturnItem {
+                    product_id: product_id.to_string(),
+                    quantity: *quantity,
+                    rate: 450.0,
+                })
+                .collect(),
+            created_by: Some("tester".to_string()),
+        }
+    }
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn returns_are_limited_to_what_is_left_on_the_invoice() {
+        let (db_path, conn) = setup();
+
+        let first = create_return_in(&db_path, &request("defective", &[("pad", 2)])).unwrap();
+        assert!(first.success);
+
+        let second = create_return_in(&db_path, &request("defective", &[("pad", 2)])).unwrap();
+        assert!(!second.success);
+        assert_eq!(second.shortages.len(), 1);
+        let shortage = &second.shortages[0];
+        assert_eq!(
+            (shortage.sold, shortage.already_returned, shortage.available),
+            (3, 2, 1)
+        );
+
+        // Lines of the same product share what is left
+        let split =
+            create_return_in(&db_path, &request("defective", &[("pad", 1), ("pad", 1)])).unwrap();
+        assert!(!split.success);
+        assert_eq!(split.shortages[0].available, 0);
+
+        let not_sold = create_return_in(&db_path, &request("defective", &[("filter", 1)])).unwrap();
+        assert!(!not_sold.success);
+        assert_eq!(not_sold.shortages[0].sold, 0);
+
+        // Only the first return was written
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM sales_returns"), 1);
+        assert_eq!(
+            count(&conn, "SELECT quantity FROM products WHERE id = 'pad'"),
+            9
+        );
+
+        fs::remove_dir_all(db_path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn unknown_reasons_are_refused() {
+        let (db_path, conn) = setup();
+
+        let result = create_return_in(&db_path, &request("broken", &[("pad", 1)]));
+        assert!(result.unwrap_err().contains("Invalid return reason"));
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM sales_returns"), 0);
+
+        fs::remove_dir_all(db_path.parent().unwrap()).unwrap();
+    }
+
+    /// Id of a new return of `quantity` brake pads
+    fn returned(db_path: &Path, quantity: i64) -> String {
+        create_return_in(db_path, &request("defective", &[("pad", quantity)]))
+            .unwrap()
+            .sales_return
+            .unwrap()
+            .id
+    }
+
+    #[test]
+    fn returns_cannot_change_after_the_grace_window() {
+        let (db_path, conn) = setup();
+        let id = returned(&db_path, 1);
+        conn.execute(
+            "UPDATE sales_returns SET created_at = datetime('now', '-25 hours') WHERE id = ?1",
+            [&id],
+        )
+        .unwrap();
+
+        let error = cancel_return_in(&db_path, &id, "tester").unwrap_err();
+        assert!(error.contains("within 24 hours"), "{}", error);
+        let amendment = SalesReturnAmendment {
+            return_id: id.clone(),
+            reason: "other".to_string(),
+            notes: None,
+            items: request("other", &[("pad", 1)]).items,
+            amended_by: None,
+        };
+        assert!(amend_return_in(&db_path, &amendment).is_err());
+
+        conn.execute(
+            "UPDATE settings SET value = '48' WHERE key = 'return_grace_hours'",
+            [],
+        )
+        .unwrap();
+        assert!(cancel_return_in(&db_path, &id, "tester").unwrap().success);
+
+        fs::remove_dir_all(db_path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn cancelling_is_refused_when_the_stock_is_gone() {
+        let (db_path, conn) = setup();
+        let id = returned(&db_path, 2);
+        // The returned pads were sold again
+        conn.execute("UPDATE products SET quantity = 1 WHERE id = 'pad'", [])
+            .unwrap();
+
+        let result = cancel_return_in(&db_path, &id, "tester").unwrap();
+        assert!(!result.success);
+        assert_eq!(result.stock_conflicts.len(), 1);
+        let conflict = &result.stock_conflicts[0];
+        assert_eq!((conflict.in_stock, conflict.to_remove), (1, 2));
+
+        assert_eq!(
+            count(&conn, "SELECT quantity FROM products WHERE id = 'pad'"),
+            1
+        );
+        let status: String = conn
+            .query_row(
+                "SELECT status FROM sales_returns WHERE id = ?1",
+                [&id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(status, "completed");
+
+        fs::remove_dir_all(db_path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn amending_and_cancelling_post_compensating_adjustments() {
+        let (db_path, conn) = setup();
+        let id = returned(&db_path, 2);
+        let return_no: String = conn
+            .query_row(
+                "SELECT return_no FROM sales_returns WHERE id = ?1",
+                [&id],
+                |row| row.get(0),
+            )
+            .unwrap();
+
+        let amendment = SalesReturnAmendment {
+            return_id: id.clone(),
+            reason: "wrong_part".to_string(),
+            notes: None,
+            items: request("wrong_part", &[("pad", 1)]).items,
+            amended_by: Some("manager".to_string()),
+        };
+        assert!(amend_return_in(&db_path, &amendment).unwrap().success);
+        assert!(cancel_return_in(&db_path, &id, "manager").unwrap().success);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT adjustment_type, quantity, notes, created_by FROM stock_adjustments
+                 WHERE reference_id = ?1 ORDER BY rowid",
+            )
+            .unwrap();
+        let adjustments: Vec<(String, i64, String, String)> = stmt
+            .query_map([&id], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            adjustments,
+            vec![
+                (
+                    "return".to_string(),
+                    2,
+                    format!("Return {}: defective", return_no),
+                    "tester".to_string()
+                ),
+                (
+                    "manual_deduction".to_string(),
+                    -1,
+                    format!("Amended return {}: wrong_part", return_no),
+                    "manager".to_string()
+                ),
+                (
+                    "manual_deduction".to_string(),
+                    -1,
+                    format!("Cancelled return {}", return_no),
+                    "manager".to_string()
+                ),
+            ]
+        );
+
+        // Stock is back where it was before the return
+        assert_eq!(
+            count(&conn, "SELECT quantity FROM products WHERE id = 'pad'"),
+            7
+        );
+
+        fs::remove_dir_all(db_path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn return_numbers_restart_each_day_and_grow_past_999() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrations::migrate(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO invoices (id, customer_name, total_amount) VALUES ('inv', 'Ravi', 0)",
+            [],
+        )
+        .unwrap();
+        let tx = conn.transaction().unwrap();
+        let add = |return_no: &str| {
+            tx.execute(
+                "INSERT INTO sales_returns (id, return_no, invoice_id, return_date, reason, total_amount)
+                 VALUES (?1, ?1, 'inv', '2026-10-17', 'other', 0)",
+                params![return_no],
+            )
+            .unwrap();
+        };
+        let day = |d: u32| NaiveDate::from_ymd_opt(2026, 10, d).unwrap();
+
+        assert_eq!(
+            next_return_number(&tx, day(17)).unwrap(),
+            "RET-20261017-001"
+        );
+
+        add("RET-20261016-007");
+        add("RET-20261017-999");
+        assert_eq!(
+            next_return_number(&tx, day(17)).unwrap(),
+            "RET-20261017-1000"
+        );
+        add("RET-20261017-1000");
+        assert_eq!(
+            next_return_number(&tx, day(17)).unwrap(),
+            "RET-20261017-1001"
+        );
+
+        // A new day starts again at 001, whatever the day before reached
+        assert_eq!(
+            next_return_number(&tx, day(18)).unwrap(),
+            "RET-20261018-001"
+        );
+    }
+}
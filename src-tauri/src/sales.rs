@@ -0,0 +1,565 @@
+use chrono::{SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Transaction, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::get_db_path;
+
+// ============================================
+// SALES
+// ============================================
+//
+// A sale touches invoices, invoice_items, products and stock_adjustments. It runs as
+// one IMMEDIATE transaction on its own connection: the write lock is taken before the
+// stock is read, so two counters billing the same product cannot both pass the stock
+// check, and a crash mid-bill leaves nothing behind.
+
+/// How long a sale waits for another writer (a backup, the other counter) to finish
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewInvoice {
+    pub id: String,
+    pub customer_name: Option<String>,
+    pub customer_phone: Option<String>,
+    #[serde(default)]
+    pub discount_amount: f64,
+    pub total_amount: f64,
+    pub payment_mode: Option<String>,
+    /// Defaults to now
+    pub created_at: Option<String>,
+}
+
+/// An invoice line. The cost price is taken from the product's current purchase price.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewInvoiceItem {
+    pub id: String,
+    pub product_id: String,
+    pub quantity: i64,
+    pub price: f64,
+}
+
+/// A line that asks for more than the stock left for it
+#[derive(Debug, Clone, Serialize)]
+pub struct StockShortage {
+    pub item_id: String,
+    pub product_id: String,
+    /// `None` if the product does not exist
+    pub product_name: Option<String>,
+    pub requested: i64,
+    /// Stock left for this line after the earlier lines of the same product
+    pub available: i64,
+}
+
+/// Stock of a product after the sale, for syncing elsewhere
+#[derive(Debug, Clone, Serialize)]
+pub struct StockLevel {
+    pub product_id: String,
+    pub quantity: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CreateInvoiceResult {
+    pub success: bool,
+    pub message: String,
+    pub invoice_id: String,
+    /// Why the sale was refused; empty on success
+    pub shortages: Vec<StockShortage>,
+    /// New stock of every product sold; empty if the sale was refused
+    pub stock: Vec<StockLevel>,
+}
+
+/// Product fields a sale reads
+struct ProductStock {
+    name: String,
+    quantity: i64,
+    purchase_price: f64,
+}
+
+pub(crate) fn now_rfc3339() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Opens the live database for a sale or return
+pub(crate) fn open_for_write(db_path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open(db_path).map_err(|e| format!("Failed to open database: {}", e))?;
+    conn.busy_timeout(BUSY_TIMEOUT)
+        .map_err(|e| format!("Failed to set busy timeout: {}", e))?;
+    Ok(conn)
+}
+
+/// Short reference used in stock adjustment notes and receipts, e.g. `1A2B3C4D`
+pub(crate) fn short_ref(id: &str) -> String {
+    id.chars().take(8).collect::<String>().to_uppercase()
+}
+
+fn validate_invoice(invoice: &NewInvoice, items: &[NewInvoiceItem]) -> Result<(), String> {
+    if invoice.id.trim().is_empty() {
+        return Err("Invoice id is required".to_string());
+    }
+    if items.is_empty() {
+        return Err("An invoice needs at least one item".to_string());
+    }
+    if !invoice.total_amount.is_finite() || !invoice.discount_amount.is_finite() {
+        return Err("Invoice amounts must be numbers".to_string());
+    }
+
+    for item in items {
+        if item.id.trim().is_empty() || item.product_id.trim().is_empty() {
+            return Err("Every invoice item needs an id and a product".to_string());
+        }
+        if item.quantity <= 0 {
+            return Err(format!(
+                "Quantity must be positive (item {}, product {})",
+                item.id, item.product_id
+            ));
+        }
+        if !item.price.is_finite() || item.price < 0.0 {
+            return Err(format!("Invalid price for product {}", item.product_id));
+        }
+    }
+
+    Ok(())
+}
+
+fn load_product(tx: &Transaction, product_id: &str) -> Result<Option<ProductStock>, String> {
+    tx.query_row(
+        "SELECT name, quantity, COALESCE(purchase_price, 0) FROM products WHERE id = ?1",
+        [product_id],
+        |row| {
+            Ok(ProductStock {
+                name: row.get(0)?,
+                quantity: row.get(1)?,
+                purchase_price: row.get(2)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| format!("Failed to read product {}: {}", product_id, e))
+}
+
+/// Records the sale. Returns the shortages instead if any line lacks stock, without writing.
+fn record_sale(
+    tx: &Transaction,
+    invoice: &NewInvoice,
+    items: &[NewInvoiceItem],
+) -> Result<Result<Vec<StockLevel>, Vec<StockShortage>>, String> {
+    // Stock is checked for every line first, counting earlier lines of the same product
+    let mut products: HashMap<&str, Option<ProductStock>> = HashMap::new();
+    let mut remaining: HashMap<&str, i64> = HashMap::new();
+    let mut shortages = Vec::new();
+
+    for item in items {
+        let product_id = item.product_id.as_str();
+        if !products.contains_key(product_id) {
+            let product = load_product(tx, product_id)?;
+            remaining.insert(product_id, product.as_ref().map_or(0, |p| p.quantity));
+            products.insert(product_id, product);
+        }
+
+        let available = remaining[product_id];
+        if item.quantity > available {
+            shortages.push(StockShortage {
+                item_id: item.id.clone(),
+                product_id: item.product_id.clone(),
+                product_name: products[product_id].as_ref().map(|p| p.name.clone()),
+                requested: item.quantity,
+                available: available.max(0),
+            });
+        }
+        remaining.insert(product_id, available - item.quantity);
+    }
+
+    if !shortages.is_empty() {
+        return Ok(Err(shortages));
+    }
+
+    let now = now_rfc3339();
+    let created_at = invoice
+        .created_at
+        .as_deref()
+        .filter(|c| !c.is_empty())
+        .unwrap_or(&now);
+
+    tx.execute(
+        "INSERT INTO invoices (id, customer_name, customer_phone, discount_amount, total_amount, payment_mode, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, COALESCE(?6, 'cash'), ?7)",
+        params![
+            invoice.id,
+            invoice.customer_name.as_deref().unwrap_or(""),
+            invoice.customer_phone,
+            invoice.discount_amount,
+            invoice.total_amount,
+            invoice.payment_mode,
+            created_at,
+        ],
+    )
+    .map_err(|e| format!("Failed to save invoice: {}", e))?;
+
+    let notes = format!("Invoice {}", short_ref(&invoice.id));
+
+    for item in items {
+        let cost_price = products[item.product_id.as_str()]
+            .as_ref()
+            .map_or(0.0, |p| p.purchase_price);
+
+        tx.execute(
+            "INSERT INTO invoice_items (id, invoice_id, product_id, quantity, price, cost_price)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                item.id,
+                invoice.id,
+                item.product_id,
+                item.quantity,
+                item.price,
+                cost_price
+            ],
+        )
+        .map_err(|e| format!("Failed to save invoice item {}: {}", item.id, e))?;
+
+        tx.execute(
+            "UPDATE products SET quantity = quantity - ?1, last_sale_date = ?2, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?3",
+            params![item.quantity, now, item.product_id],
+        )
+        .map_err(|e| format!("Failed to update stock of {}: {}", item.product_id, e))?;
+
+        record_adjustment(
+            tx,
+            &item.product_id,
+            "sale",
+            -item.quantity,
+            &notes,
+            Some(&invoice.id),
+            "system",
+            &now,
+        )?;
+    }
+
+    let mut stock: Vec<StockLevel> = remaining
+        .into_iter()
+        .map(|(product_id, quantity)| StockLevel {
+            product_id: product_id.to_string(),
+            quantity,
+        })
+        .collect();
+    stock.sort_by(|a, b| a.product_id.cmp(&b.product_id));
+
+    Ok(Ok(stock))
+}
+
+/// Logs a stock movement in `stock_adjustments`.
+/// `reference_id` is the invoice or sales return that caused it, if any.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn record_adjustment(
+    tx: &Transaction,
+    product_id: &str,
+    adjustment_type: &str,
+    quantity: i64,
+    notes: &str,
+    reference_id: Option<&str>,
+    created_by: &str,
+    created_at: &str,
+) -> Result<(), String> {
+    tx.execute(
+        "INSERT INTO stock_adjustments (id, product_id, adjustment_type, quantity, notes, reference_id, created_by, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            product_id,
+            adjustment_type,
+            quantity,
+            notes,
+            reference_id,
+            created_by,
+            created_at
+        ],
+    )
+    .map(|_| ())
+    .map_err(|e| format!("Failed to log stock adjustment for {}: {}", product_id, e))
+}
+
+/// Creates an invoice, its items and the stock movements in one transaction
+fn create_invoice_in(
+    db_path: &Path,
+    invoice: &NewInvoice,
+    items: &[NewInvoiceItem],
+) -> Result<CreateInvoiceResult, String> {
+    validate_invoice(invoice, items)?;
+
+    let mut conn = open_for_write(db_path)?;
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Failed to start sale: {}", e))?;
+
+    match record_sale(&tx, invoice, items)? {
+        Ok(stock) => {
+            tx.commit()
+                .map_err(|e| format!("Failed to save sale: {}", e))?;
+            Ok(CreateInvoiceResult {
+                success: true,
+                message: format!("Invoice {} created", short_ref(&invoice.id)),
+                invoice_id: invoice.id.clone(),
+                shortages: Vec::new(),
+                stock,
+            })
+        }
+        Err(shortages) => {
+            // Dropping the transaction rolls it back; nothing was written
+            let message = shortages
+                .iter()
+                .map(|s| {
+                    format!(
+                        "Insufficient stock for {}. Available {}, requested {}.",
+                        s.product_name.as_deref().unwrap_or(&s.product_id),
+                        s.available,
+                        s.requested
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            Ok(CreateInvoiceResult {
+                success: false,
+                message,
+                invoice_id: invoice.id.clone(),
+                shortages,
+                stock: Vec::new(),
+            })
+        }
+    }
+}
+
+/// Records a sale: checks stock for every line, saves the invoice and its items with the
+/// current purchase price as cost, deducts stock, updates `last_sale_date` and logs the
+/// stock adjustments. All of it or none of it is written; lines short of stock are
+/// reported in `shortages` with `success: false`.
+#[tauri::command]
+pub async fn create_invoice(
+    app: AppHandle,
+    invoice: NewInvoice,
+    items: Vec<NewInvoiceItem>,
+) -> Result<CreateInvoiceResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        create_invoice_in(&get_db_path(&app)?, &invoice, &items)
+    })
+    .await
+    .map_err(|e| format!("Failed to create invoice: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{record_sale, validate_invoice, NewInvoice, NewInvoiceItem};
+    use crate::migrations::migrate;
+    use rusqlite::Connection;
+
+    /// A migrated database with a brake pad (5 in stock, bought at 300) and an oil filter (2 in stock)
+    fn shop() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO products (id, name, price, quantity, purchase_price)
+                 VALUES ('pad', 'Brake pad', 450, 5, 300);
+             INSERT INTO products (id, name, price, quantity, purchase_price)
+                 VALUES ('filter', 'Oil filter', 200, 2, 120);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn invoice(id: &str) -> NewInvoice {
+        NewInvoice {
+            id: id.to_string(),
+            customer_name: Some("Ravi".to_string()),
+            customer_phone: None,
+            discount_amount: 0.0,
+            total_amount: 0.0,
+            payment_mode: None,
+            created_at: None,
+        }
+    }
+
+    fn item(id: &str, product_id: &str, quantity: i64) -> NewInvoiceItem {
+        NewInvoiceItem {
+            id: id.to_string(),
+            product_id: product_id.to_string(),
+            quantity,
+            price: 100.0,
+        }
+    }
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    fn quantity(conn: &Connection, product_id: &str) -> i64 {
+        conn.query_row(
+            "SELECT quantity FROM products WHERE id = ?1",
+            [product_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn invalid_invoices_are_refused() {
+        assert!(validate_invoice(&invoice("inv"), &[]).is_err());
+        assert!(validate_invoice(&invoice(" "), &[item("i1", "pad", 1)]).is_err());
+        assert!(validate_invoice(&invoice("inv"), &[item("i1", "pad", 0)]).is_err());
+        assert!(validate_invoice(&invoice("inv"), &[item("i1", "", 1)]).is_err());
+
+        let mut negative = item("i1", "pad", 1);
+        negative.price = -1.0;
+        assert!(validate_invoice(&invoice("inv"), &[negative]).is_err());
+
+        let mut nan = invoice("inv");
+        nan.total_amount = f64::NAN;
+        assert!(validate_invoice(&nan, &[item("i1", "pad", 1)]).is_err());
+
+        assert!(validate_invoice(&invoice("inv"), &[item("i1", "pad", 1)]).is_ok());
+    }
+
+    #[test]
+    fn lines_of_the_same_product_share_its_stock() {
+        let mut conn = shop();
+        let tx = conn.transaction().unwrap();
+        let shortages = record_sale(
+            &tx,
+            &invoice("inv"),
+            &[item("i1", "pad", 3), item("i2", "pad", 3)],
+        )
+        .unwrap()
+        .unwrap_err();
+
+        assert_eq!(shortages.len(), 1);
+        assert_eq!(shortages[0].item_id, "i2");
+        assert_eq!(shortages[0].requested, 3);
+        assert_eq!(shortages[0].available, 2);
+    }
+
+    #[test]
+    fn unknown_products_are_short() {
+        let mut conn = shop();
+        let tx = conn.transaction().unwrap();
+        let shortages = record_sale(&tx, &invoice("inv"), &[item("i1", "missing", 1)])
+            .unwrap()
+            .unwrap_err();
+
+        assert_eq!(shortages.len(), 1);
+        assert_eq!(shortages[0].product_name, None);
+        assert_eq!(shortages[0].available, 0);
+    }
+
+    #[test]
+    fn a_short_line_writes_nothing() {
+        let mut conn = shop();
+        let tx = conn.transaction().unwrap();
+        let result = record_sale(
+            &tx,
+            &invoice("inv"),
+            &[item("i1", "pad", 1), item("i2", "filter", 3)],
+        )
+        .unwrap();
+        assert!(result.is_err());
+        drop(tx);
+
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM invoices"), 0);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM invoice_items"), 0);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM stock_adjustments"), 0);
+        assert_eq!(quantity(&conn, "pad"), 5);
+        assert_eq!(quantity(&conn, "filter"), 2);
+    }
+
+    #[test]
+    fn a_failed_write_leaves_nothing_behind() {
+        let mut conn = shop();
+        let tx = conn.transaction().unwrap();
+        // The second line reuses the first line's id, so its insert fails after the first was written
+        let result = record_sale(
+            &tx,
+            &invoice("inv"),
+            &[item("i1", "pad", 1), item("i1", "filter", 1)],
+        );
+        assert!(result.is_err());
+        drop(tx);
+
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM invoices"), 0);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM stock_adjustments"), 0);
+        assert_eq!(quantity(&conn, "pad"), 5);
+    }
+
+    #[test]
+    fn a_sale_deducts_stock_and_logs_it() {
+        let mut conn = shop();
+        let tx = conn.transaction().unwrap();
+        let stock = record_sale(
+            &tx,
+            &invoice("a1b2c3d4-sale"),
+            &[
+                item("i1", "pad", 2),
+                item("i2", "filter", 1),
+                item("i3", "pad", 1),
+            ],
+        )
+        .unwrap()
+        .unwrap();
+        tx.commit().unwrap();
+
+        let levels: Vec<(&str, i64)> = stock
+            .iter()
+            .map(|s| (s.product_id.as_str(), s.quantity))
+            .collect();
+        assert_eq!(levels, vec![("filter", 1), ("pad", 2)]);
+        assert_eq!(quantity(&conn, "pad"), 2);
+        assert_eq!(quantity(&conn, "filter"), 1);
+
+        // Cost comes from the product's purchase price at the time of sale
+        let cost: f64 = conn
+            .query_row(
+                "SELECT cost_price FROM invoice_items WHERE id = 'i1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(cost, 300.0);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT product_id, adjustment_type, quantity, notes, reference_id
+                 FROM stock_adjustments ORDER BY product_id, quantity",
+            )
+            .unwrap();
+        let adjustments: Vec<(String, String, i64, String, String)> = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let expected = |product: &str, quantity: i64| {
+            (
+                product.to_string(),
+                "sale".to_string(),
+                quantity,
+                "Invoice A1B2C3D4".to_string(),
+                "a1b2c3d4-sale".to_string(),
+            )
+        };
+        assert_eq!(
+            adjustments,
+            vec![
+                expected("filter", -1),
+                expected("pad", -2),
+                expected("pad", -1)
+            ]
+        );
+    }
+}
@@ -0,0 +1,302 @@
+use chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDateTime, NaiveTime, SecondsFormat,
+    Utc, Weekday,
+};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+use crate::manifest::BackupType;
+use crate::{mirror, offsite, operations, retention};
+use crate::{app_version, create_backup_file, get_backups_dir, get_db_path, read_setting};
+
+// ============================================
+// AUTO-BACKUP SCHEDULER
+// ============================================
+
+/// How often the scheduler wakes up to check whether a backup is due
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long to wait before retrying after a failed auto-backup
+const RETRY_AFTER_FAILURE: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Frequency {
+    Daily,
+    Weekly,
+}
+
+#[derive(Debug)]
+struct ScheduleSettings {
+    enabled: bool,
+    time: NaiveTime,
+    frequency: Frequency,
+    weekday: Weekday,
+    retention_days: i64,
+}
+
+impl ScheduleSettings {
+    /// Reads the auto-backup settings, falling back to the same defaults as the frontend
+    fn load(conn: &Connection) -> Result<Self, String> {
+        let enabled = read_setting(conn, "auto_backup_enabled")?
+            .map(|v| v == "1" || v == "true")
+            .unwrap_or(true);
+
+        let time = read_setting(conn, "auto_backup_time")?
+            .and_then(|v| NaiveTime::parse_from_str(&v, "%H:%M").ok())
+            .unwrap_or_else(|| NaiveTime::from_hms_opt(23, 0, 0).unwrap());
+
+        let frequency = match read_setting(conn, "auto_backup_frequency")?.as_deref() {
+            Some("weekly") => Frequency::Weekly,
+            _ => Frequency::Daily,
+        };
+
+        // Stored as 0 = Sunday .. 6 = Saturday to match JavaScript's Date.getDay()
+        let weekday = read_setting(conn, "auto_backup_day")?
+            .and_then(|v| v.parse::<u32>().ok())
+            .and_then(|d| match d {
+                0 => Some(Weekday::Sun),
+                1 => Some(Weekday::Mon),
+                2 => Some(Weekday::Tue),
+                3 => Some(Weekday::Wed),
+                4 => Some(Weekday::Thu),
+                5 => Some(Weekday::Fri),
+                6 => Some(Weekday::Sat),
+                _ => None,
+            })
+            .unwrap_or(Weekday::Sun);
+
+        let retention_days = read_setting(conn, "backup_retention_days")?
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(30);
+
+        Ok(ScheduleSettings {
+            enabled,
+            time,
+            frequency,
+            weekday,
+            retention_days,
+        })
+    }
+
+    /// Returns the most recent scheduled run at or before `now`
+    fn latest_slot(&self, now: NaiveDateTime) -> NaiveDateTime {
+        let mut slot = now.date().and_time(self.time);
+        if slot > now {
+            slot -= ChronoDuration::days(1);
+        }
+
+        if self.frequency == Frequency::Weekly {
+            while slot.weekday() != self.weekday {
+                slot -= ChronoDuration::days(1);
+            }
+        }
+
+        slot
+    }
+}
+
+/// Starts the background auto-backup thread.
+/// The first check runs immediately so a run missed while the app was closed is caught up.
+pub fn start(app: AppHandle) {
+    let spawned = thread::Builder::new()
+        .name("auto-backup".to_string())
+        .spawn(move || {
+            let mut last_failure: Option<Instant> = None;
+
+            loop {
+                let backing_off = last_failure.is_some_and(|t| t.elapsed() < RETRY_AFTER_FAILURE);
+
+                if !backing_off {
+                    match run_if_due(&app) {
+                        Ok(Some(filename)) => {
+                            println!("[AutoBackup] Created {}", filename);
+                            last_failure = None;
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            eprintln!("[AutoBackup] {}", e);
+                            last_failure = Some(Instant::now());
+                        }
+                    }
+                }
+
+                retry_offsite_uploads(&app);
+
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+
+    if let Err(e) = spawned {
+        eprintln!("[AutoBackup] Failed to start scheduler: {}", e);
+    }
+}
+
+/// Retries offsite uploads that failed earlier, e.g. while the PC was offline
+fn retry_offsite_uploads(app: &AppHandle) {
+    let (Ok(db_path), Ok(backups_dir)) = (get_db_path(app), get_backups_dir(app)) else {
+        return;
+    };
+    if !db_path.exists() {
+        return;
+    }
+
+    match offsite::process_queue(&db_path, &backups_dir, false) {
+        Ok(0) => {}
+        Ok(count) => println!("[AutoBackup] Uploaded {} queued backup(s) offsite", count),
+        Err(e) => eprintln!("[AutoBackup] {}", e),
+    }
+}
+
+/// Checks the schedule and performs an auto-backup if one is due.
+/// Returns the backup filename when a backup was created.
+fn run_if_due(app: &AppHandle) -> Result<Option<String>, String> {
+    let db_path = get_db_path(app)?;
+
+    // The frontend creates the database on first launch; nothing to do until then
+    if !db_path.exists() {
+        return Ok(None);
+    }
+
+    let conn = Connection::open(&db_path).map_err(|e| format!("Failed to open database: {}", e))?;
+    conn.busy_timeout(Duration::from_secs(5))
+        .map_err(|e| format!("Failed to set busy timeout: {}", e))?;
+
+    if !table_exists(&conn, "settings")? || !table_exists(&conn, "backup_log")? {
+        return Ok(None);
+    }
+
+    let settings = ScheduleSettings::load(&conn)?;
+    if !settings.enabled {
+        return Ok(None);
+    }
+
+    let now = Local::now().naive_local();
+    let slot = settings.latest_slot(now);
+
+    if let Some(last) = last_auto_backup(&conn, "success")? {
+        if last >= slot {
+            return Ok(None);
+        }
+    }
+
+    // A restore or another backup is running; the slot is still due at the next check
+    let _lock = match operations::lock(app, "Creating an auto-backup", None) {
+        Ok(lock) => lock,
+        Err(e) => {
+            println!("[AutoBackup] Postponed: {}", e);
+            return Ok(None);
+        }
+    };
+
+    let backups_dir = get_backups_dir(app)?;
+    let backup_date = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+
+    match create_backup_file(
+        &db_path,
+        &backups_dir,
+        BackupType::Auto,
+        None,
+        &app_version(app),
+        false,
+        None,
+    ) {
+        Ok(result) => {
+            log_backup(
+                &conn,
+                &result.filename,
+                &backup_date,
+                Some(result.file_size),
+                "success",
+                None,
+            )?;
+
+            prune_old_backups(&conn, &backups_dir, settings.retention_days);
+            mirror::mirror_backup(&db_path, &backups_dir, Path::new(&result.path), None);
+            offsite::queue_backup(&db_path, &backups_dir, Path::new(&result.path));
+
+            Ok(Some(result.filename))
+        }
+        Err(e) => {
+            // Retries of the same slot are not logged again, and no file was written
+            let already_logged = last_auto_backup(&conn, "failed")?.is_some_and(|t| t >= slot);
+            if !already_logged {
+                log_backup(&conn, "", &backup_date, None, "failed", Some(&e))?;
+            }
+            Err(format!("Auto-backup failed: {}", e))
+        }
+    }
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type='table' AND name=?1",
+        params![table],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to check table existence: {}", e))
+}
+
+/// Returns the local time of the newest auto-backup with `status` recorded in `backup_log`
+fn last_auto_backup(conn: &Connection, status: &str) -> Result<Option<NaiveDateTime>, String> {
+    let latest: Option<String> = conn
+        .query_row(
+            "SELECT MAX(backup_date) FROM backup_log WHERE backup_type = 'auto' AND status = ?1",
+            params![status],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read backup log: {}", e))?
+        .flatten();
+
+    Ok(latest.and_then(|d| {
+        DateTime::parse_from_rfc3339(&d)
+            .ok()
+            .map(|dt| dt.with_timezone(&Local).naive_local())
+    }))
+}
+
+fn log_backup(
+    conn: &Connection,
+    backup_file: &str,
+    backup_date: &str,
+    file_size: Option<u64>,
+    status: &str,
+    notes: Option<&str>,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO backup_log (backup_file, backup_date, backup_type, file_size, status, notes)
+         VALUES (?1, ?2, 'auto', ?3, ?4, ?5)",
+        params![backup_file, backup_date, file_size.map(|s| s as i64), status, notes],
+    )
+    .map_err(|e| format!("Failed to write backup log: {}", e))?;
+
+    Ok(())
+}
+
+/// Applies the backup retention policy and prunes `backup_log` entries older than the retention period
+fn prune_old_backups(conn: &Connection, backups_dir: &Path, retention_days: i64) {
+    let cutoff = Utc::now() - ChronoDuration::days(retention_days);
+    let cutoff_iso = cutoff.to_rfc3339_opts(SecondsFormat::Millis, true);
+
+    if let Err(e) = conn.execute(
+        "DELETE FROM backup_log WHERE backup_date < ?1",
+        params![cutoff_iso],
+    ) {
+        eprintln!("[AutoBackup] Failed to prune backup log: {}", e);
+    }
+
+    match retention::apply_retention(conn, backups_dir, false) {
+        Ok(report) => {
+            for filename in &report.deleted {
+                println!("[AutoBackup] Deleted old backup: {}", filename);
+            }
+            for failure in &report.failed {
+                eprintln!("[AutoBackup] Failed to delete {}", failure);
+            }
+        }
+        Err(e) => eprintln!("[AutoBackup] Retention failed: {}", e),
+    }
+}
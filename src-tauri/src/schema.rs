@@ -0,0 +1,54 @@
+use rusqlite::Connection;
+use std::path::Path;
+
+use crate::archive::MaterializedBackup;
+use crate::{incremental, migrations, DATA_TABLES};
+
+// ============================================
+// SCHEMA UPGRADES FOR OLDER BACKUPS
+// ============================================
+//
+// Runs the migrations in migrations.rs on a copy of a backup taken with an older app
+// version, so it has the current schema before its rows are imported. Backups from a
+// newer app version are refused.
+// Data tables the backup does not have are dropped again after migrating: the restore
+// keeps the live rows of those tables instead of replacing them with nothing.
+
+/// Describes what restoring `database_path` would need to upgrade, without changing it
+pub fn describe_upgrades(database_path: &Path) -> Result<Vec<String>, String> {
+    let version = migrations::ensure_file_supported(database_path)?;
+    Ok(migrations::pending_descriptions(version))
+}
+
+/// Returns an upgraded temporary copy of an older backup database, or `None` if it
+/// already has the current schema. The backup itself is never modified.
+pub fn upgraded_copy(database_path: &Path) -> Result<Option<MaterializedBackup>, String> {
+    let version = migrations::ensure_file_supported(database_path)?;
+    if version == migrations::latest_version() {
+        return Ok(None);
+    }
+
+    let upgraded = MaterializedBackup::temporary("upgraded")?;
+    upgraded.copy_from(database_path)?;
+
+    let conn = Connection::open(upgraded.path())
+        .map_err(|e| format!("Failed to open backup copy: {}", e))?;
+
+    let mut missing = Vec::new();
+    for table in DATA_TABLES {
+        if incremental::table_columns(&conn, "main", table)?.is_empty() {
+            missing.push(*table);
+        }
+    }
+
+    migrations::migrate(&conn)?;
+
+    for table in missing {
+        conn.execute(&format!("DROP TABLE {}", table), [])
+            .map_err(|e| format!("Failed to drop {} from backup copy: {}", table, e))?;
+    }
+
+    drop(conn);
+
+    Ok(Some(upgraded))
+}
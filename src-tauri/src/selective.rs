@@ -0,0 +1,390 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::operations::{self, Operation, OperationKind};
+use crate::{
+    get_db_path, import_backup_data, incremental, schema, verify, write_safety_backup,
+    RestoreResult, TableRestoreResult, DATA_TABLES,
+};
+
+// ============================================
+// SELECTIVE RESTORE AND RECORD RECOVERY
+// ============================================
+
+/// Child rows that belong to a recovered parent: (parent table, child table, column referencing the parent)
+const CHILD_TABLES: &[(&str, &str, &str)] = &[
+    ("invoices", "invoice_items", "invoice_id"),
+    ("sales_returns", "return_items", "return_id"),
+];
+
+/// Tables whose rows are identified by a stable `id` and can be recovered individually
+const RECOVERABLE_TABLES: &[&str] = &[
+    "products",
+    "invoices",
+    "invoice_items",
+    "stock_adjustments",
+    "sales_returns",
+    "return_items",
+    "users",
+];
+
+fn row_exists(conn: &Connection, schema: &str, table: &str, id: &str) -> Result<bool, String> {
+    conn.query_row(
+        &format!("SELECT 1 FROM {}.{} WHERE id = ?1", schema, table),
+        params![id],
+        |_| Ok(()),
+    )
+    .optional()
+    .map(|r| r.is_some())
+    .map_err(|e| format!("Failed to look up {} {}: {}", table, id, e))
+}
+
+/// Foreign keys of a backup table as (column, parent table), limited to parents keyed by `id`
+fn parent_keys(conn: &Connection, table: &str) -> Result<Vec<(String, String)>, String> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA backup.foreign_key_list({})", table))
+        .map_err(|e| format!("Failed to read foreign keys of {}: {}", table, e))?;
+
+    let keys = stmt
+        .query_map([], |row| {
+            let parent: String = row.get(2)?;
+            let from: String = row.get(3)?;
+            let to: Option<String> = row.get(4)?;
+            Ok((from, parent, to))
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to read foreign keys of {}: {}", table, e))?
+        .into_iter()
+        .filter(|(_, parent, to)| {
+            RECOVERABLE_TABLES.contains(&parent.as_str()) && to.as_deref().unwrap_or("id") == "id"
+        })
+        .map(|(from, parent, _)| (from, parent))
+        .collect();
+
+    Ok(keys)
+}
+
+/// Adds parents that the selected rows reference but the live database no longer has,
+/// so a recovered invoice item does not point at a deleted product
+fn add_missing_parents(
+    conn: &Connection,
+    selected: &mut BTreeMap<String, BTreeSet<String>>,
+) -> Result<(), String> {
+    loop {
+        let mut missing: Vec<(String, String)> = Vec::new();
+
+        for (table, ids) in selected.iter() {
+            for (column, parent) in parent_keys(conn, table)? {
+                for id in ids {
+                    let parent_id: Option<String> = conn
+                        .query_row(
+                            &format!("SELECT {} FROM backup.{} WHERE id = ?1", column, table),
+                            params![id],
+                            |row| row.get(0),
+                        )
+                        .optional()
+                        .map_err(|e| format!("Failed to read {} {}: {}", table, id, e))?
+                        .flatten();
+
+                    let Some(parent_id) = parent_id else { continue };
+                    let already = selected
+                        .get(&parent)
+                        .is_some_and(|s| s.contains(&parent_id));
+
+                    if !already
+                        && !row_exists(conn, "main", &parent, &parent_id)?
+                        && row_exists(conn, "backup", &parent, &parent_id)?
+                    {
+                        missing.push((parent.clone(), parent_id));
+                    }
+                }
+            }
+        }
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        for (table, id) in missing {
+            selected.entry(table).or_default().insert(id);
+        }
+    }
+}
+
+/// Copies the selected rows from the attached backup, replacing any live version.
+/// Children of recovered parents are replaced as a set so none are left over from later edits.
+/// Stops with the cancellation error between tables; the caller's transaction is then rolled back.
+fn copy_rows(
+    conn: &Connection,
+    selected: &BTreeMap<String, BTreeSet<String>>,
+    requested_table: &str,
+    operation: &Operation,
+) -> Result<Vec<TableRestoreResult>, String> {
+    for (parent, child, column) in CHILD_TABLES {
+        if *parent != requested_table {
+            continue;
+        }
+        for id in selected.get(*parent).into_iter().flatten() {
+            conn.execute(
+                &format!("DELETE FROM main.{} WHERE {} = ?1", child, column),
+                params![id],
+            )
+            .map_err(|e| format!("Failed to clear {} of {} {}: {}", child, parent, id, e))?;
+        }
+    }
+
+    let mut restored = Vec::new();
+    let total = selected.len() as u64;
+
+    // Parents before children
+    for table in DATA_TABLES {
+        let Some(ids) = selected.get(*table) else {
+            continue;
+        };
+
+        operation.check_cancelled()?;
+        operation.report("importing", Some(table), restored.len() as u64, total);
+
+        let live_columns = incremental::table_columns(conn, "main", table)?;
+        let columns: Vec<String> = incremental::table_columns(conn, "backup", table)?
+            .into_iter()
+            .filter(|c| live_columns.contains(c))
+            .collect();
+        if columns.is_empty() {
+            return Err(format!("Table {} is missing from the live database", table));
+        }
+
+        let columns = columns.join(", ");
+        let sql = format!(
+            "INSERT OR REPLACE INTO main.{t} ({c}) SELECT {c} FROM backup.{t} WHERE id = ?1",
+            t = table,
+            c = columns
+        );
+
+        let mut count = 0;
+        for id in ids {
+            count += conn
+                .execute(&sql, params![id])
+                .map_err(|e| format!("Failed to recover {} {}: {}", table, id, e))?;
+        }
+        restored.push(TableRestoreResult {
+            table: table.to_string(),
+            in_backup: true,
+            imported: count,
+            failed: 0,
+            skipped: 0,
+        });
+        operation.report("importing", Some(table), restored.len() as u64, total);
+    }
+
+    Ok(restored)
+}
+
+/// Restores only the given tables from a backup, leaving the rest of the live data untouched.
+/// Refuses backups that fail verification unless `force` is set
+/// Encrypted exports need their `passphrase`
+/// Runs in the background, emitting `operation-progress` events for `operation_id`
+#[tauri::command]
+pub async fn restore_tables_from_backup(
+    app: AppHandle,
+    backup_path: String,
+    tables: Vec<String>,
+    force: Option<bool>,
+    passphrase: Option<String>,
+    operation_id: Option<String>,
+) -> Result<RestoreResult, String> {
+    operations::run_blocking(app, operation_id, OperationKind::Restore, move |app, op| {
+        restore_tables(app, &backup_path, &tables, force, passphrase.as_deref(), op)
+    })
+    .await
+}
+
+fn restore_tables(
+    app: &AppHandle,
+    backup_path: &str,
+    tables: &[String],
+    force: Option<bool>,
+    passphrase: Option<&str>,
+    operation: &Operation,
+) -> Result<RestoreResult, String> {
+    if tables.is_empty() {
+        return Err("Select at least one table to restore".to_string());
+    }
+    if let Some(unknown) = tables.iter().find(|t| !DATA_TABLES.contains(&t.as_str())) {
+        return Err(format!("Unknown table: {}", unknown));
+    }
+
+    let backup_file = Path::new(backup_path);
+    if !backup_file.is_file() {
+        return Err(format!("Backup file not found: {}", backup_path));
+    }
+
+    let name = operations::file_label(backup_file);
+    let _lock = operations::lock(app, &format!("Restoring tables from {}", name), Some(&name))?;
+
+    operation.report("preparing", None, 0, 1);
+    let unpacked = incremental::materialize_complete(backup_file, passphrase)?;
+    verify::ensure_verified(backup_file, unpacked.path(), force)?;
+
+    let tables: Vec<&str> = tables.iter().map(|t| t.as_str()).collect();
+    import_backup_data(app, unpacked.path(), &tables, Some(operation))
+}
+
+/// Recovers individual rows by id from a backup, e.g. an accidentally deleted invoice.
+/// Invoices and returns come back with their line items, and referenced rows missing
+/// from the live database (such as a deleted product) are recovered with them.
+/// Stock levels are not adjusted.
+/// Runs in the background, emitting `operation-progress` events for `operation_id`
+#[tauri::command]
+pub async fn recover_records(
+    app: AppHandle,
+    backup_path: String,
+    table: String,
+    ids: Vec<String>,
+    force: Option<bool>,
+    passphrase: Option<String>,
+    operation_id: Option<String>,
+) -> Result<RestoreResult, String> {
+    operations::run_blocking(app, operation_id, OperationKind::Restore, move |app, op| {
+        recover(
+            app,
+            &backup_path,
+            &table,
+            &ids,
+            force,
+            passphrase.as_deref(),
+            op,
+        )
+    })
+    .await
+}
+
+fn recover(
+    app: &AppHandle,
+    backup_path: &str,
+    table: &str,
+    ids: &[String],
+    force: Option<bool>,
+    passphrase: Option<&str>,
+    operation: &Operation,
+) -> Result<RestoreResult, String> {
+    if !RECOVERABLE_TABLES.contains(&table) {
+        return Err(format!("Records cannot be recovered from table {}", table));
+    }
+    if ids.is_empty() {
+        return Err("Select at least one record to recover".to_string());
+    }
+
+    let backup_file = Path::new(backup_path);
+    if !backup_file.is_file() {
+        return Err(format!("Backup file not found: {}", backup_path));
+    }
+
+    let name = operations::file_label(backup_file);
+    let _lock = operations::lock(
+        app,
+        &format!("Recovering records from {}", name),
+        Some(&name),
+    )?;
+
+    operation.report("preparing", None, 0, 1);
+    let unpacked = incremental::materialize_complete(backup_file, passphrase)?;
+    verify::ensure_verified(backup_file, unpacked.path(), force)?;
+
+    let upgraded = schema::upgraded_copy(unpacked.path())?;
+    let backup_db = upgraded.as_ref().map_or(unpacked.path(), |u| u.path());
+
+    let db_path = get_db_path(app)?;
+    let conn =
+        Connection::open(&db_path).map_err(|e| format!("Failed to open main database: {}", e))?;
+
+    conn.execute(
+        "ATTACH DATABASE ?1 AS backup",
+        params![backup_db.to_string_lossy()],
+    )
+    .map_err(|e| format!("Failed to attach backup database: {}", e))?;
+
+    let mut not_found = Vec::new();
+    let mut requested = BTreeSet::new();
+    for id in ids {
+        if row_exists(&conn, "backup", table, id)? {
+            requested.insert(id.clone());
+        } else {
+            not_found.push(id.clone());
+        }
+    }
+
+    if requested.is_empty() {
+        return Err(format!(
+            "None of the selected {} exist in this backup",
+            table
+        ));
+    }
+
+    let mut selected: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+
+    for (parent, child, column) in CHILD_TABLES {
+        if *parent != table {
+            continue;
+        }
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT id FROM backup.{} WHERE {} = ?1",
+                child, column
+            ))
+            .map_err(|e| format!("Failed to read {}: {}", child, e))?;
+        for id in &requested {
+            let children = stmt
+                .query_map(params![id], |row| row.get::<_, String>(0))
+                .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+                .map_err(|e| format!("Failed to read {} of {}: {}", child, id, e))?;
+            selected
+                .entry(child.to_string())
+                .or_default()
+                .extend(children);
+        }
+    }
+
+    selected.insert(table.to_string(), requested);
+    add_missing_parents(&conn, &mut selected)?;
+
+    operation.check_cancelled()?;
+    let safety_filename = write_safety_backup(app, "pre_restore_safety_")?;
+
+    // Rows arrive parents first, but replaced rows are briefly detached from their children
+    conn.execute("PRAGMA foreign_keys = OFF", [])
+        .map_err(|e| format!("Failed to disable foreign keys: {}", e))?;
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let restored = copy_rows(&tx, &selected, table, operation)?;
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    let _ = conn.execute("PRAGMA foreign_keys = ON", []);
+
+    let total: usize = restored.iter().map(|t| t.imported).sum();
+    let mut message = format!(
+        "Recovered {} from backup",
+        restored
+            .iter()
+            .map(|t| format!("{} {}", t.imported, t.table.replace('_', " ")))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    if !not_found.is_empty() {
+        message.push_str(&format!(". Not in backup: {}", not_found.join(", ")));
+    }
+
+    Ok(RestoreResult {
+        success: true,
+        message,
+        records_imported: total,
+        safety_backup: safety_filename,
+        tables: restored,
+        errors: Vec::new(),
+    })
+}
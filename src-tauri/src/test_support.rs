@@ -0,0 +1,36 @@
+use rusqlite::Connection;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::migrations;
+
+// ============================================
+// TEST HELPERS
+// ============================================
+//
+// Shared by the unit tests of every module. Each call gets a folder of its own, so
+// tests can run in parallel; a test removes it with `fs::remove_dir_all` when done.
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// Creates an empty folder `motormods_<label>_<pid>_<n>` in the system temp folder
+pub fn temp_dir(label: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "motormods_{}_{}_{}",
+        label,
+        std::process::id(),
+        NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Creates `motormods.db` at the latest schema version in a new `temp_dir(label)` and
+/// opens it. The folder is the database's parent.
+pub fn migrated_db(label: &str) -> (PathBuf, Connection) {
+    let db_path = temp_dir(label).join("motormods.db");
+    migrations::migrate_file(&db_path).unwrap();
+    let conn = Connection::open(&db_path).unwrap();
+    (db_path, conn)
+}
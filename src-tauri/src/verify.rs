@@ -0,0 +1,329 @@
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::{archive, manifest};
+
+// ============================================
+// BACKUP VERIFICATION
+// ============================================
+
+/// Tables every MotorMods database has had since the first release
+const REQUIRED_TABLES: &[&str] = &["products", "invoices", "invoice_items"];
+
+/// Core columns checked for each table when it is present.
+/// Columns added by later migrations are deliberately left out so older backups still pass.
+const EXPECTED_COLUMNS: &[(&str, &[&str])] = &[
+    ("products", &["id", "name", "price", "quantity"]),
+    ("invoices", &["id", "customer_name", "total_amount", "created_at"]),
+    ("invoice_items", &["id", "invoice_id", "product_id", "quantity", "price"]),
+    ("settings", &["key", "value"]),
+    ("stock_adjustments", &["id", "product_id", "adjustment_type", "quantity"]),
+    ("sales_returns", &["id", "return_no", "invoice_id", "total_amount"]),
+    ("return_items", &["id", "return_id", "product_id", "quantity"]),
+    ("backup_log", &["backup_file", "backup_date"]),
+    ("users", &["id", "username", "password_hash", "role"]),
+];
+
+/// Limit on how many individual problems are reported per check
+const MAX_REPORTED: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecksumStatus {
+    Match,
+    /// The file's checksum or size differs from its manifest
+    Mismatch,
+    /// No manifest is stored next to the file, so there is nothing to compare against
+    NoManifest,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupVerification {
+    pub path: String,
+    /// True when the backup is safe to restore
+    pub ok: bool,
+    pub integrity_ok: bool,
+    pub integrity_errors: Vec<String>,
+    pub foreign_key_violations: usize,
+    pub missing_tables: Vec<String>,
+    /// Entries formatted as `table.column`
+    pub missing_columns: Vec<String>,
+    pub checksum: ChecksumStatus,
+    /// Problems that do not block a restore, e.g. foreign key violations
+    pub warnings: Vec<String>,
+    /// Problems that prevented the checks from running at all
+    pub errors: Vec<String>,
+}
+
+impl BackupVerification {
+    fn new(path: &Path) -> Self {
+        BackupVerification {
+            path: path.to_string_lossy().to_string(),
+            ok: false,
+            integrity_ok: false,
+            integrity_errors: Vec::new(),
+            foreign_key_violations: 0,
+            missing_tables: Vec::new(),
+            missing_columns: Vec::new(),
+            checksum: ChecksumStatus::NoManifest,
+            warnings: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// One-line description of why verification failed
+    pub fn summary(&self) -> String {
+        let mut problems = self.errors.clone();
+        if !self.integrity_ok && !self.integrity_errors.is_empty() {
+            problems.push(format!(
+                "integrity check failed ({})",
+                self.integrity_errors.join("; ")
+            ));
+        }
+        if !self.missing_tables.is_empty() {
+            problems.push(format!("missing tables: {}", self.missing_tables.join(", ")));
+        }
+        if !self.missing_columns.is_empty() {
+            problems.push(format!("missing columns: {}", self.missing_columns.join(", ")));
+        }
+        if self.checksum == ChecksumStatus::Mismatch {
+            problems.push("checksum or size does not match manifest".to_string());
+        }
+
+        if problems.is_empty() {
+            "backup verified".to_string()
+        } else {
+            problems.join("; ")
+        }
+    }
+}
+
+fn table_columns(conn: &Connection, table: &str) -> Result<Option<HashSet<String>>, String> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA table_info({})", table))
+        .map_err(|e| format!("Failed to get table info: {}", e))?;
+
+    let columns: HashSet<String> = stmt
+        .query_map([], |row| row.get::<_, String>(1))
+        .map_err(|e| format!("Failed to query columns: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    // PRAGMA table_info returns no rows for a table that does not exist
+    Ok(if columns.is_empty() { None } else { Some(columns) })
+}
+
+fn run_checks(conn: &Connection, result: &mut BackupVerification) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA integrity_check({})", MAX_REPORTED))
+        .map_err(|e| format!("Failed to run integrity check: {}", e))?;
+    let messages: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to run integrity check: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    result.integrity_ok = messages.len() == 1 && messages[0] == "ok";
+    if !result.integrity_ok {
+        result.integrity_errors = messages;
+    }
+
+    let mut stmt = conn
+        .prepare("PRAGMA foreign_key_check")
+        .map_err(|e| format!("Failed to run foreign key check: {}", e))?;
+    let violations: Vec<String> = stmt
+        .query_map([], |row| {
+            let table: String = row.get(0)?;
+            let rowid: Option<i64> = row.get(1)?;
+            let parent: String = row.get(2)?;
+            Ok(format!(
+                "{} row {} references missing {}",
+                table,
+                rowid.map_or("?".to_string(), |r| r.to_string()),
+                parent
+            ))
+        })
+        .map_err(|e| format!("Failed to run foreign key check: {}", e))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    result.foreign_key_violations = violations.len();
+    result
+        .warnings
+        .extend(violations.into_iter().take(MAX_REPORTED));
+
+    for (table, columns) in EXPECTED_COLUMNS {
+        match table_columns(conn, table)? {
+            Some(present) => {
+                for column in columns.iter() {
+                    if !present.contains(*column) {
+                        result.missing_columns.push(format!("{}.{}", table, column));
+                    }
+                }
+            }
+            None if REQUIRED_TABLES.contains(table) => {
+                result.missing_tables.push(table.to_string());
+            }
+            None => {
+                result
+                    .warnings
+                    .push(format!("Table {} not present (older backup)", table));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Verifies that a file is an intact MotorMods database without modifying it.
+/// Compressed and encrypted backups are unpacked to a temporary file first.
+pub fn verify_backup_file(path: &Path, passphrase: Option<&str>) -> BackupVerification {
+    if !path.is_file() {
+        let mut result = BackupVerification::new(path);
+        result.errors.push("Backup file not found".to_string());
+        return result;
+    }
+
+    match archive::materialize(path, passphrase) {
+        Ok(unpacked) => verify_unpacked(path, unpacked.path()),
+        Err(e) => {
+            let mut result = BackupVerification::new(path);
+            result.errors.push(e);
+            result
+        }
+    }
+}
+
+/// Verifies a backup whose uncompressed database is already available at `database_path`.
+/// The checksum is compared against the file as stored, `backup_path`.
+pub fn verify_unpacked(backup_path: &Path, database_path: &Path) -> BackupVerification {
+    let mut result = BackupVerification::new(backup_path);
+
+    if let Some(stored) = manifest::read_manifest(backup_path) {
+        // A changed size fails on its own, so a truncated or extended file cannot pass
+        // against a sidecar whose checksum was rewritten after the change
+        let size_matches = fs::metadata(backup_path).is_ok_and(|m| m.len() == stored.file_size);
+        result.checksum = match manifest::sha256_file(backup_path) {
+            Ok(actual) if size_matches && actual == stored.sha256 => ChecksumStatus::Match,
+            Ok(_) => ChecksumStatus::Mismatch,
+            Err(e) => {
+                result.errors.push(e);
+                ChecksumStatus::Mismatch
+            }
+        };
+    }
+
+    match Connection::open_with_flags(database_path, OpenFlags::SQLITE_OPEN_READ_ONLY) {
+        Ok(conn) => {
+            if let Err(e) = run_checks(&conn, &mut result) {
+                result.errors.push(e);
+            }
+        }
+        Err(e) => result
+            .errors
+            .push(format!("Failed to open backup database: {}", e)),
+    }
+
+    result.ok = result.errors.is_empty()
+        && result.integrity_ok
+        && result.missing_tables.is_empty()
+        && result.missing_columns.is_empty()
+        && result.checksum != ChecksumStatus::Mismatch;
+
+    result
+}
+
+/// Refuses to continue unless the backup verifies, or the caller explicitly overrides.
+/// `database_path` is the uncompressed copy of `backup_path` (the same path if it was not compressed).
+pub fn ensure_verified(
+    backup_path: &Path,
+    database_path: &Path,
+    force: Option<bool>,
+) -> Result<(), String> {
+    let verification = verify_unpacked(backup_path, database_path);
+
+    if verification.ok {
+        return Ok(());
+    }
+
+    if force.unwrap_or(false) {
+        eprintln!(
+            "Warning: Restoring unverified backup {}: {}",
+            backup_path.display(),
+            verification.summary()
+        );
+        return Ok(());
+    }
+
+    Err(format!(
+        "Backup failed verification: {}",
+        verification.summary()
+    ))
+}
+
+/// Checks a backup file's integrity, schema and checksum before it is restored
+/// `passphrase` is only needed for encrypted exports
+/// Runs in the background, since the file is unpacked and hashed
+#[tauri::command]
+pub async fn verify_backup(
+    backup_path: String,
+    passphrase: Option<String>,
+) -> Result<BackupVerification, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        verify_backup_file(Path::new(&backup_path), passphrase.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Failed to verify backup: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{verify_backup_file, ChecksumStatus};
+    use crate::list_backup_files;
+    use crate::manifest::{self, BackupType};
+    use crate::test_support::migrated_db;
+    use std::fs::{self, OpenOptions};
+
+    #[test]
+    fn truncated_backup_fails_after_being_listed() {
+        let (db_path, conn) = migrated_db("verify");
+        drop(conn);
+        let root = db_path.parent().unwrap();
+        let backups_dir = root.join("backups");
+        fs::create_dir_all(&backups_dir).unwrap();
+        let backup = backups_dir.join("motormods_backup_2026-10-17_10-00-00.db");
+        fs::copy(&db_path, &backup).unwrap();
+        manifest::create_manifest(&backup, BackupType::Manual, None, "test").unwrap();
+        assert!(verify_backup_file(&backup, None).ok);
+
+        let length = fs::metadata(&backup).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&backup)
+            .unwrap()
+            .set_len(length - 4096)
+            .unwrap();
+
+        // Listing reports the change and leaves the stored checksum alone
+        let listed = list_backup_files(&backups_dir);
+        assert!(listed[0].manifest.as_ref().unwrap().stale);
+        let verification = verify_backup_file(&backup, None);
+        assert!(!verification.ok);
+        assert_eq!(verification.checksum, ChecksumStatus::Mismatch);
+
+        // Even a sidecar whose checksum was rewritten still records the old size
+        let mut stored = manifest::read_manifest(&backup).unwrap();
+        stored.sha256 = manifest::sha256_file(&backup).unwrap();
+        manifest::write_manifest(&backup, &stored).unwrap();
+        assert!(manifest::load_or_compute(&backup).unwrap().stale);
+        assert_eq!(
+            verify_backup_file(&backup, None).checksum,
+            ChecksumStatus::Mismatch
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
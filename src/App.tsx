@@ -49,7 +49,7 @@ function AppContent() {
       case "billing":
         return <Billing />;
       case "stock":
-        return <StockManagement canEdit={true} canDelete={session?.role === "admin"} />;
+        return <StockManagement canEdit={true} canDelete={session?.role === "admin"} userName={session?.name} />;
       case "returns":
         return <SalesReturns userRole={session?.role} userName={session?.name} />;
       case "invoices":
@@ -57,7 +57,7 @@ function AppContent() {
       case "reports":
         return <Reports />;
       case "backups":
-        return <BackupRestore />;
+        return <BackupRestore userName={session?.name} />;
       case "settings":
         return <Settings />;
       default:
@@ -0,0 +1,282 @@
+import { ChevronLeft, ChevronRight, FileText, Search } from "lucide-react";
+import React, { useCallback, useEffect, useState } from "react";
+import { backupService } from "../db/backupService";
+import { BackupBrowsePage, BackupFileInfo, InvoiceWithItems, Product, SalesReturnWithItems } from "../types";
+import { Badge, Button, Input, Modal, useToast } from "./ui";
+
+type BrowseTab = "invoices" | "products" | "returns";
+
+type BrowseRow = InvoiceWithItems | Product | SalesReturnWithItems;
+
+interface BackupBrowserProps {
+    backup: BackupFileInfo | null;
+    onClose: () => void;
+}
+
+const PAGE_SIZE = 25;
+
+const formatCurrency = (amount: number | null | undefined) => `₹${(amount ?? 0).toLocaleString()}`;
+
+const formatDate = (value: string | null | undefined) => (value ? new Date(value).toLocaleString() : "-");
+
+/**
+ * Read-only view of the invoices, products and returns inside a backup.
+ * Nothing is restored; invoices can be saved as PDF again for reprinting.
+ */
+export const BackupBrowser: React.FC<BackupBrowserProps> = ({ backup, onClose }) => {
+    const toast = useToast();
+    const [tab, setTab] = useState<BrowseTab>("invoices");
+    const [search, setSearch] = useState("");
+    const [dateFrom, setDateFrom] = useState("");
+    const [dateTo, setDateTo] = useState("");
+    const [page, setPage] = useState(1);
+    const [result, setResult] = useState<BackupBrowsePage<BrowseRow> | null>(null);
+    const [loading, setLoading] = useState(false);
+    const [expanded, setExpanded] = useState<string | null>(null);
+
+    const load = useCallback(async () => {
+        if (!backup) return;
+        setLoading(true);
+        try {
+            const filter = {
+                search: search || undefined,
+                date_from: dateFrom || undefined,
+                date_to: dateTo || undefined,
+                page,
+                page_size: PAGE_SIZE,
+            };
+            const data: BackupBrowsePage<BrowseRow> =
+                tab === "invoices"
+                    ? await backupService.browseBackupInvoices(backup.path, filter)
+                    : tab === "products"
+                        ? await backupService.browseBackupProducts(backup.path, filter)
+                        : await backupService.browseBackupReturns(backup.path, filter);
+            setResult(data);
+        } catch (error) {
+            console.error(error);
+            toast.error("Could Not Open Backup", String(error));
+            setResult(null);
+        } finally {
+            setLoading(false);
+        }
+        // eslint-disable-next-line react-hooks/exhaustive-deps
+    }, [backup, tab, page, search, dateFrom, dateTo]);
+
+    useEffect(() => {
+        load();
+    }, [load]);
+
+    useEffect(() => {
+        setPage(1);
+        setExpanded(null);
+    }, [backup, tab, search, dateFrom, dateTo]);
+
+    const handleClose = () => {
+        backupService.closeBackupBrowser();
+        setResult(null);
+        setSearch("");
+        setDateFrom("");
+        setDateTo("");
+        setTab("invoices");
+        onClose();
+    };
+
+    const handleSaveInvoicePdf = async (invoice: InvoiceWithItems) => {
+        try {
+            const { generateInvoicePdfBytes } = await import("../utils/invoiceGenerator");
+            const { savePdfWithDialog } = await import("../utils/printService");
+
+            const { items, ...header } = invoice;
+            const { bytes, filename } = await generateInvoicePdfBytes({
+                invoice: { ...header, customer_name: header.customer_name || "Walking Customer" },
+                items,
+            });
+            const saveResult = await savePdfWithDialog(bytes, filename);
+
+            if (saveResult.success && saveResult.savedPath) {
+                toast.success("Invoice Saved", `Saved to ${saveResult.savedPath}`);
+            } else if (saveResult.error && saveResult.error !== "Save cancelled by user") {
+                toast.warning("Save Failed", saveResult.error);
+            }
+        } catch (error) {
+            console.error(error);
+            toast.error("Error", "Failed to generate invoice PDF");
+        }
+    };
+
+    const totalPages = result ? Math.max(1, Math.ceil(result.total / result.page_size)) : 1;
+
+    const renderInvoice = (invoice: InvoiceWithItems) => (
+        <div key={invoice.id} className="border border-slate-200 rounded-xl">
+            <button
+                className="w-full flex items-center justify-between p-3 text-left hover:bg-slate-50"
+                onClick={() => setExpanded(expanded === invoice.id ? null : invoice.id)}
+            >
+                <div>
+                    <p className="text-sm font-semibold text-slate-800">
+                        #{invoice.id.slice(-8).toUpperCase()} · {invoice.customer_name || "Walking Customer"}
+                    </p>
+                    <p className="text-xs text-slate-500">
+                        {formatDate(invoice.created_at)}
+                        {invoice.customer_phone && ` · ${invoice.customer_phone}`}
+                    </p>
+                </div>
+                <span className="font-semibold text-slate-800">{formatCurrency(invoice.total_amount)}</span>
+            </button>
+            {expanded === invoice.id && (
+                <div className="border-t border-slate-100 p-3 space-y-2">
+                    {(invoice.items ?? []).map((item) => (
+                        <div key={item.id} className="flex justify-between text-sm text-slate-600">
+                            <span>{item.product_name ?? item.product_id} × {item.quantity}</span>
+                            <span>{formatCurrency(item.price * item.quantity)}</span>
+                        </div>
+                    ))}
+                    {invoice.discount_amount > 0 && (
+                        <div className="flex justify-between text-sm text-slate-500">
+                            <span>Discount</span>
+                            <span>-{formatCurrency(invoice.discount_amount)}</span>
+                        </div>
+                    )}
+                    <Button
+                        variant="secondary"
+                        size="sm"
+                        leftIcon={<FileText size={14} />}
+                        onClick={() => handleSaveInvoicePdf(invoice)}
+                    >
+                        Save as PDF
+                    </Button>
+                </div>
+            )}
+        </div>
+    );
+
+    const renderProduct = (product: Product) => (
+        <div key={product.id} className="flex items-center justify-between p-3 border border-slate-200 rounded-xl">
+            <div>
+                <p className="text-sm font-semibold text-slate-800">{product.name}</p>
+                <p className="text-xs text-slate-500">
+                    {[product.sku, product.category, product.barcode].filter(Boolean).join(" · ") || "-"}
+                </p>
+            </div>
+            <div className="text-right">
+                <p className="text-sm font-semibold text-slate-800">{formatCurrency(product.price)}</p>
+                <p className="text-xs text-slate-500">{product.quantity} in stock</p>
+            </div>
+        </div>
+    );
+
+    const renderReturn = (salesReturn: SalesReturnWithItems) => (
+        <div key={salesReturn.id} className="border border-slate-200 rounded-xl">
+            <button
+                className="w-full flex items-center justify-between p-3 text-left hover:bg-slate-50"
+                onClick={() => setExpanded(expanded === salesReturn.id ? null : salesReturn.id)}
+            >
+                <div>
+                    <p className="text-sm font-semibold text-slate-800">
+                        {salesReturn.return_no} · {salesReturn.customer_name || "Walking Customer"}
+                    </p>
+                    <p className="text-xs text-slate-500">
+                        {formatDate(salesReturn.return_date)} · Invoice #{salesReturn.invoice_id.slice(-8).toUpperCase()}
+                    </p>
+                </div>
+                <div className="flex items-center gap-2">
+                    {salesReturn.status === "cancelled" && <Badge variant="warning">cancelled</Badge>}
+                    <span className="font-semibold text-slate-800">{formatCurrency(salesReturn.total_amount)}</span>
+                </div>
+            </button>
+            {expanded === salesReturn.id && (
+                <div className="border-t border-slate-100 p-3 space-y-1">
+                    {(salesReturn.items ?? []).map((item) => (
+                        <div key={item.id} className="flex justify-between text-sm text-slate-600">
+                            <span>{item.product_name ?? item.product_id} × {item.quantity}</span>
+                            <span>{formatCurrency(item.line_total)}</span>
+                        </div>
+                    ))}
+                    {salesReturn.notes && <p className="text-xs text-slate-500">{salesReturn.notes}</p>}
+                </div>
+            )}
+        </div>
+    );
+
+    return (
+        <Modal
+            isOpen={backup !== null}
+            onClose={handleClose}
+            title={`Browse Backup${backup ? ` · ${backup.filename}` : ""}`}
+            size="xl"
+        >
+            <div className="space-y-4">
+                <p className="text-sm text-slate-500">
+                    Read-only view of this backup. Nothing here changes your current data.
+                </p>
+
+                <div className="flex gap-2">
+                    {(["invoices", "products", "returns"] as BrowseTab[]).map((t) => (
+                        <Button
+                            key={t}
+                            size="sm"
+                            variant={tab === t ? "primary" : "ghost"}
+                            onClick={() => setTab(t)}
+                        >
+                            {t === "invoices" ? "Invoices" : t === "products" ? "Products" : "Returns"}
+                        </Button>
+                    ))}
+                </div>
+
+                <div className="grid grid-cols-3 gap-2">
+                    <Input
+                        placeholder={tab === "products" ? "Name, SKU or barcode" : "Number, customer or phone"}
+                        value={search}
+                        onChange={(e) => setSearch(e.target.value)}
+                        leftIcon={<Search size={16} />}
+                    />
+                    {tab !== "products" && (
+                        <>
+                            <Input type="date" value={dateFrom} onChange={(e) => setDateFrom(e.target.value)} />
+                            <Input type="date" value={dateTo} onChange={(e) => setDateTo(e.target.value)} />
+                        </>
+                    )}
+                </div>
+
+                <div className="space-y-2 max-h-[50vh] overflow-y-auto">
+                    {loading && !result ? (
+                        <p className="text-sm text-slate-500">Opening backup...</p>
+                    ) : result && result.rows.length === 0 ? (
+                        <p className="text-sm text-slate-500">No matching records in this backup.</p>
+                    ) : (
+                        result?.rows.map((row) =>
+                            tab === "invoices"
+                                ? renderInvoice(row as InvoiceWithItems)
+                                : tab === "products"
+                                    ? renderProduct(row as Product)
+                                    : renderReturn(row as SalesReturnWithItems)
+                        )
+                    )}
+                </div>
+
+                {result && result.total > 0 && (
+                    <div className="flex items-center justify-between text-sm text-slate-500">
+                        <span>{result.total} record{result.total === 1 ? "" : "s"}</span>
+                        <div className="flex items-center gap-2">
+                            <Button
+                                variant="ghost"
+                                size="sm"
+                                disabled={page <= 1 || loading}
+                                onClick={() => setPage(page - 1)}
+                                leftIcon={<ChevronLeft size={14} />}
+                            />
+                            <span>Page {result.page} of {totalPages}</span>
+                            <Button
+                                variant="ghost"
+                                size="sm"
+                                disabled={page >= totalPages || loading}
+                                onClick={() => setPage(page + 1)}
+                                leftIcon={<ChevronRight size={14} />}
+                            />
+                        </div>
+                    </div>
+                )}
+            </div>
+        </Modal>
+    );
+};
@@ -2,35 +2,132 @@ import {
     AlertTriangle,
     Archive,
     Clock,
+    Cloud,
     Database,
     Download,
 
     FileCheck,
+    FileSpreadsheet,
     FolderOpen,
+    GitMerge,
     HardDrive,
     History,
+    ListChecks,
+    Lock,
+    Plus,
     RefreshCcw,
     RotateCcw,
     Save,
+    Search,
+    Share,
     Trash2,
     Upload
 } from "lucide-react";
-import React, { useCallback, useEffect, useState } from "react";
+import React, { useCallback, useEffect, useRef, useState } from "react";
+import { v4 as uuidv4 } from "uuid";
 import { backupService } from "../db/backupService";
 import { isTauriRuntime } from "../db/runtime";
 import { settingsService } from "../db/settingsService";
-import { AppSettings, BackupFileInfo, BackupLog } from "../types";
-import { Badge, Button, Card, ConfirmModal, Input, useToast } from "./ui";
+import { AppSettings, BackupCompression, BackupFileInfo, BackupFrequency, BackupLog, BackupMode, MergeReport, PortableArchiveFormat, MirrorStatus, OffsiteStatus, OperationProgress, RemoteBackup, RestorePreview } from "../types";
+import { BackupBrowser } from "./BackupBrowser";
+import { Badge, Button, Card, ConfirmModal, Input, Modal, useToast } from "./ui";
 
-export const BackupRestore: React.FC = () => {
+const WEEKDAYS = ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+
+interface BackupRestoreProps {
+    userName?: string;
+}
+
+const RESTORABLE_TABLES = [
+    { table: "products", label: "Products" },
+    { table: "invoices", label: "Invoices" },
+    { table: "invoice_items", label: "Invoice Items" },
+    { table: "stock_adjustments", label: "Stock Adjustments" },
+    { table: "sales_returns", label: "Sales Returns" },
+    { table: "return_items", label: "Return Items" },
+    { table: "settings", label: "Settings" },
+    { table: "users", label: "Users" },
+];
+
+// Tables whose records can be recovered one by one
+const RECOVERABLE_TABLES = ["products", "invoices", "sales_returns", "stock_adjustments", "users"];
+
+const describeProgress = (progress: OperationProgress | null): string => {
+    if (!progress || progress.phase === "preparing") return "Preparing...";
+    const percent = progress.total > 0 ? Math.round((progress.done / progress.total) * 100) : 0;
+    if (progress.phase === "copying") return `Copying database... ${percent}%`;
+    if (progress.phase === "mirroring") return `Copying to mirror destinations... ${percent}%`;
+    if (progress.phase === "exporting") return `Exporting ${progress.table?.replace(/_/g, " ") ?? "data"}... ${percent}%`;
+    if (progress.phase === "importing") return `Restoring ${progress.table?.replace(/_/g, " ") ?? "data"}... ${percent}%`;
+    return "Finishing...";
+};
+
+const parseMirrorDestinations = (raw: string | undefined): string[] => {
+    try {
+        const parsed = JSON.parse(raw || "[]");
+        return Array.isArray(parsed) ? parsed.filter((d): d is string => typeof d === "string") : [];
+    } catch {
+        return [];
+    }
+};
+
+const isPassphraseError = (error: unknown): boolean =>
+    error instanceof Error
+        ? error.message.includes("passphrase")
+        : typeof error === "string" && error.includes("passphrase");
+
+const describeBackupContents = (backup: BackupFileInfo | null): string => {
+    const counts = backup?.manifest?.table_counts;
+    if (!counts) return "";
+    return `\n\nThis backup contains ${counts.products ?? 0} products, ${counts.invoices ?? 0} invoices and ${counts.sales_returns ?? 0} returns.`;
+};
+
+const describeRestorePreview = (preview: RestorePreview | null): string => {
+    if (!preview) return "";
+
+    const changes = preview.tables
+        .filter((t) => t.table !== "backup_log" && (t.only_in_live || t.only_in_backup || t.changed))
+        .map((t) => {
+            const parts = [];
+            if (t.only_in_live) parts.push(`${t.only_in_live} lost`);
+            if (t.only_in_backup) parts.push(`${t.only_in_backup} restored`);
+            if (t.changed) parts.push(`${t.changed} changed`);
+            return `${t.table.replace(/_/g, " ")}: ${parts.join(", ")}`;
+        });
+
+    if (changes.length === 0) return "\n\nThe backup matches the current data.";
+
+    const latest = (value: string | null) => value ? new Date(value).toLocaleString() : "none";
+    const upgrades = preview.schema_upgrades.length > 0
+        ? `\n\nThis backup is from an older version and will be upgraded (${preview.schema_upgrades.join(", ")}).`
+        : "";
+    return `\n\nCompared with the current data:\n${changes.join("\n")}\n\nLatest invoice now: ${latest(preview.live_latest_invoice)}\nLatest invoice in backup: ${latest(preview.backup_latest_invoice)}${upgrades}`;
+};
+
+export const BackupRestore: React.FC<BackupRestoreProps> = ({ userName }) => {
     const toast = useToast();
     const [loading, setLoading] = useState(true);
     const [loadError, setLoadError] = useState(false);
     const [backups, setBackups] = useState<BackupLog[]>([]);
     const [backupFiles, setBackupFiles] = useState<BackupFileInfo[]>([]);
     const [settings, setSettings] = useState<AppSettings | null>(null);
+    const [mirrorStatus, setMirrorStatus] = useState<MirrorStatus[]>([]);
+
+    // Offsite (S3-compatible) storage
+    const [offsiteStatus, setOffsiteStatus] = useState<OffsiteStatus | null>(null);
+    const [offsiteSecretKey, setOffsiteSecretKey] = useState("");
+    const [offsitePassphrase, setOffsitePassphrase] = useState("");
+    const [remoteBackups, setRemoteBackups] = useState<RemoteBackup[] | null>(null);
+    const [isLoadingRemote, setIsLoadingRemote] = useState(false);
+    const [isUploadingOffsite, setIsUploadingOffsite] = useState(false);
+    const [downloadingRemote, setDownloadingRemote] = useState<string | null>(null);
     const [isBackingUp, setIsBackingUp] = useState(false);
     const [isRestoring, setIsRestoring] = useState(false);
+
+    // Backup or restore running in the background, with its latest progress event
+    const [operationId, setOperationId] = useState<string | null>(null);
+    const [operationProgress, setOperationProgress] = useState<OperationProgress | null>(null);
+    const operationIdRef = useRef<string | null>(null);
     const [isImporting, setIsImporting] = useState(false);
     const [savingSettings, setSavingSettings] = useState(false);
     const [deletingBackup, setDeletingBackup] = useState<string | null>(null);
@@ -41,6 +138,17 @@ export const BackupRestore: React.FC = () => {
         open: false,
         backup: null,
     });
+    const [restorePreview, setRestorePreview] = useState<RestorePreview | null>(null);
+
+    // Backup opened in the read-only browser
+    const [browseTarget, setBrowseTarget] = useState<BackupFileInfo | null>(null);
+
+    // Selective restore state
+    const [selectiveTarget, setSelectiveTarget] = useState<BackupFileInfo | null>(null);
+    const [selectedTables, setSelectedTables] = useState<string[]>([]);
+    const [recoverTable, setRecoverTable] = useState("invoices");
+    const [recoverIds, setRecoverIds] = useState("");
+    const [isSelectiveRestoring, setIsSelectiveRestoring] = useState(false);
 
     // Delete confirmation state
     const [deleteConfirm, setDeleteConfirm] = useState<{ open: boolean; backup: BackupFileInfo | null }>({
@@ -48,18 +156,44 @@ export const BackupRestore: React.FC = () => {
         backup: null,
     });
 
+    // Export state (an empty passphrase exports the backup unencrypted)
+    const [exportTarget, setExportTarget] = useState<BackupFileInfo | null>(null);
+    const [exportPassphrase, setExportPassphrase] = useState("");
+    const [exportPassphraseConfirm, setExportPassphraseConfirm] = useState("");
+    const [isExporting, setIsExporting] = useState(false);
+
+    // Encrypted import waiting for its passphrase
+    const [encryptedImportPath, setEncryptedImportPath] = useState<string | null>(null);
+    const [importPassphrase, setImportPassphrase] = useState("");
+
+    // Spreadsheet export/import of all tables
+    const [isExportingArchive, setIsExportingArchive] = useState(false);
+    const [isImportingArchive, setIsImportingArchive] = useState(false);
+    const [archiveImportConfirm, setArchiveImportConfirm] = useState<string | null>(null);
+
+    // Merge from another terminal: the chosen database and its dry-run or final report
+    const [mergeSource, setMergeSource] = useState<string | null>(null);
+    const [mergePassphrase, setMergePassphrase] = useState("");
+    const [mergeNeedsPassphrase, setMergeNeedsPassphrase] = useState(false);
+    const [mergeReport, setMergeReport] = useState<MergeReport | null>(null);
+    const [isMerging, setIsMerging] = useState(false);
+
     const loadData = useCallback(async () => {
         setLoading(true);
         setLoadError(false);
         try {
-            const [logs, files, appSettings] = await Promise.all([
+            const [logs, files, appSettings, mirrors, offsite] = await Promise.all([
                 backupService.getBackupLog(),
                 backupService.listBackupFiles(),
-                settingsService.getAll()
+                settingsService.getAll(),
+                backupService.getMirrorStatus(),
+                backupService.getOffsiteStatus()
             ]);
             setBackups(logs);
             setBackupFiles(files);
             setSettings(appSettings);
+            setMirrorStatus(mirrors);
+            setOffsiteStatus(offsite);
         } catch (error) {
             console.error(error);
             setLoadError(true);
@@ -74,10 +208,42 @@ export const BackupRestore: React.FC = () => {
         loadData();
     }, [loadData]);
 
+    useEffect(() => {
+        const unlisten = backupService.onOperationProgress((progress) => {
+            if (progress.operation_id === operationIdRef.current) {
+                setOperationProgress(progress);
+            }
+        });
+        return () => {
+            unlisten.then((fn) => fn());
+        };
+    }, []);
+
+    const beginOperation = (): string => {
+        const id = uuidv4();
+        operationIdRef.current = id;
+        setOperationId(id);
+        setOperationProgress(null);
+        return id;
+    };
+
+    const endOperation = () => {
+        operationIdRef.current = null;
+        setOperationId(null);
+        setOperationProgress(null);
+    };
+
+    const handleCancelOperation = async () => {
+        if (operationId) {
+            await backupService.cancelOperation(operationId);
+        }
+    };
+
     const handleManualBackup = async () => {
         setIsBackingUp(true);
+        const id = beginOperation();
         try {
-            await backupService.triggerBackup('manual');
+            await backupService.triggerBackup('manual', userName, false, id);
             toast.success("Backup Complete", "Database has been backed up successfully");
             loadData();
         } catch (error) {
@@ -85,6 +251,7 @@ export const BackupRestore: React.FC = () => {
             toast.error("Backup Failed", error instanceof Error ? error.message : "Could not create backup");
         } finally {
             setIsBackingUp(false);
+            endOperation();
         }
     };
 
@@ -93,22 +260,95 @@ export const BackupRestore: React.FC = () => {
         setSavingSettings(true);
         try {
             await settingsService.setMultiple(settings);
+            if (offsiteSecretKey || offsitePassphrase) {
+                await backupService.setOffsiteCredentials(offsiteSecretKey || undefined, offsitePassphrase || undefined);
+                setOffsiteSecretKey("");
+                setOffsitePassphrase("");
+            }
+            setMirrorStatus(await backupService.getMirrorStatus());
+            setOffsiteStatus(await backupService.getOffsiteStatus());
+            setRemoteBackups(null);
             toast.success("Settings Saved", "Backup preferences updated");
         } catch (error) {
             console.error(error);
-            toast.error("Error", "Failed to save settings");
+            toast.error("Error", error instanceof Error ? error.message : String(error));
         } finally {
             setSavingSettings(false);
         }
     };
 
+    const openRestoreConfirm = async (backup: BackupFileInfo) => {
+        setRestorePreview(null);
+        setRestoreConfirm({ open: true, backup });
+        try {
+            setRestorePreview(await backupService.previewRestore(backup.path));
+        } catch (error) {
+            // The manifest counts are shown instead
+            console.error("Restore preview failed:", error);
+        }
+    };
+
+    const closeSelectiveModal = () => {
+        setSelectiveTarget(null);
+        setSelectedTables([]);
+        setRecoverIds("");
+    };
+
+    const toggleSelectedTable = (table: string) => {
+        setSelectedTables((current) =>
+            current.includes(table) ? current.filter((t) => t !== table) : [...current, table]
+        );
+    };
+
+    const handleRestoreTables = async () => {
+        if (!selectiveTarget || selectedTables.length === 0) return;
+        setIsSelectiveRestoring(true);
+        const id = beginOperation();
+        try {
+            const result = await backupService.restoreTables(selectiveTarget.path, selectedTables, false, undefined, id);
+            toast.success("Restore Complete", result.message);
+            closeSelectiveModal();
+            await loadData();
+        } catch (error) {
+            console.error("Selective restore failed:", error);
+            toast.error("Restore Failed", error instanceof Error ? error.message : String(error));
+        } finally {
+            setIsSelectiveRestoring(false);
+            endOperation();
+        }
+    };
+
+    const handleRecoverRecords = async () => {
+        const ids = recoverIds.split(/[\s,]+/).filter(Boolean);
+        if (!selectiveTarget || ids.length === 0) return;
+        setIsSelectiveRestoring(true);
+        const id = beginOperation();
+        try {
+            const result = await backupService.recoverRecords(selectiveTarget.path, recoverTable, ids, false, undefined, id);
+            toast.success("Records Recovered", result.message);
+            closeSelectiveModal();
+            await loadData();
+        } catch (error) {
+            console.error("Record recovery failed:", error);
+            toast.error("Recovery Failed", error instanceof Error ? error.message : String(error));
+        } finally {
+            setIsSelectiveRestoring(false);
+            endOperation();
+        }
+    };
+
     const handleRestore = async () => {
         if (!restoreConfirm.backup) return;
         setRestoreConfirm({ open: false, backup: null });
+        setRestorePreview(null);
         setIsRestoring(true);
 
         try {
-            const result = await backupService.restoreFromBackup(restoreConfirm.backup.filename);
+            const backup = restoreConfirm.backup;
+            const id = beginOperation();
+            const result = backup.manifest?.kind === 'incremental'
+                ? (await backupService.restoreBackupChain(backup.filename, false, id)).message
+                : await backupService.restoreFromBackup(backup.filename, false, id);
             toast.success("Restore Complete", result);
             
             // Give user a moment to see success message, then reload to refresh all data
@@ -120,6 +360,38 @@ export const BackupRestore: React.FC = () => {
             console.error(error);
             toast.error("Restore Failed", error instanceof Error ? error.message : "Could not restore backup");
             setIsRestoring(false);
+            endOperation();
+        }
+    };
+
+    const runImport = async (filePath: string, passphrase?: string) => {
+        setIsImporting(true);
+        const id = beginOperation();
+        try {
+            const result = await backupService.importBackup(filePath, false, passphrase, id);
+            setEncryptedImportPath(null);
+            setImportPassphrase("");
+            toast.success("Import Complete", result);
+            
+            // Give user a moment to see success message, then reload to refresh all data
+            toast.info("Refreshing", "Reloading application to apply imported data...");
+            setTimeout(() => {
+                window.location.reload();
+            }, 1500);
+        } catch (error) {
+            console.error(error);
+            if (isPassphraseError(error)) {
+                // Encrypted export: ask for the passphrase, or ask again if it was wrong
+                if (passphrase) {
+                    toast.error("Import Failed", error instanceof Error ? error.message : String(error));
+                }
+                setImportPassphrase("");
+                setEncryptedImportPath(filePath);
+            } else {
+                toast.error("Import Failed", error instanceof Error ? error.message : "Could not import backup");
+            }
+            setIsImporting(false);
+            endOperation();
         }
     };
 
@@ -132,18 +404,141 @@ export const BackupRestore: React.FC = () => {
                 return; // User cancelled
             }
 
-            const result = await backupService.importBackup(filePath);
-            toast.success("Import Complete", result);
-            
-            // Give user a moment to see success message, then reload to refresh all data
+            await runImport(filePath);
+        } catch (error) {
+            console.error(error);
+            toast.error("Import Failed", error instanceof Error ? error.message : "Could not import backup");
+            setIsImporting(false);
+        }
+    };
+
+    const handleExportArchive = async (format: PortableArchiveFormat) => {
+        setIsExportingArchive(true);
+        const id = beginOperation();
+        try {
+            const manifest = await backupService.exportPortableArchive(format, id);
+            if (manifest) {
+                const rows = manifest.tables.reduce((sum, t) => sum + t.rows, 0);
+                toast.success("Export Complete", `Exported ${rows} records from ${manifest.tables.length} tables`);
+            }
+        } catch (error) {
+            console.error("Archive export failed:", error);
+            toast.error("Export Failed", error instanceof Error ? error.message : String(error));
+        } finally {
+            setIsExportingArchive(false);
+            endOperation();
+        }
+    };
+
+    const handleSelectArchive = async () => {
+        try {
+            const filePath = await backupService.selectPortableArchive();
+            if (filePath) {
+                setArchiveImportConfirm(filePath);
+            }
+        } catch (error) {
+            console.error(error);
+            toast.error("Import Failed", error instanceof Error ? error.message : String(error));
+        }
+    };
+
+    const handleImportArchive = async () => {
+        if (!archiveImportConfirm) return;
+        const filePath = archiveImportConfirm;
+        setArchiveImportConfirm(null);
+        setIsImportingArchive(true);
+        const id = beginOperation();
+        try {
+            const result = await backupService.importPortableArchive(filePath, id);
+            toast.success("Import Complete", result.message);
+
             toast.info("Refreshing", "Reloading application to apply imported data...");
             setTimeout(() => {
                 window.location.reload();
             }, 1500);
+        } catch (error) {
+            console.error("Archive import failed:", error);
+            toast.error("Import Failed", error instanceof Error ? error.message : String(error));
+            setIsImportingArchive(false);
+            endOperation();
+        }
+    };
+
+    const runMerge = async (sourcePath: string, dryRun: boolean, passphrase?: string) => {
+        setIsMerging(true);
+        const id = beginOperation();
+        try {
+            const report = await backupService.mergeImport(sourcePath, dryRun, false, passphrase, id);
+            setMergeNeedsPassphrase(false);
+            setMergeReport(report);
+            if (!dryRun) {
+                toast.success("Merge Complete", report.message);
+                await loadData();
+            }
+        } catch (error) {
+            console.error("Merge failed:", error);
+            if (isPassphraseError(error)) {
+                if (passphrase) {
+                    toast.error("Merge Failed", error instanceof Error ? error.message : String(error));
+                }
+                setMergePassphrase("");
+                setMergeNeedsPassphrase(true);
+            } else {
+                toast.error("Merge Failed", error instanceof Error ? error.message : String(error));
+                closeMergeModal();
+            }
+        } finally {
+            setIsMerging(false);
+            endOperation();
+        }
+    };
+
+    const handleSelectMergeSource = async () => {
+        try {
+            const filePath = await backupService.selectExternalBackup();
+            if (!filePath) return; // User cancelled
+
+            setMergeSource(filePath);
+            setMergeReport(null);
+            await runMerge(filePath, true);
         } catch (error) {
             console.error(error);
-            toast.error("Import Failed", error instanceof Error ? error.message : "Could not import backup");
-            setIsImporting(false);
+            toast.error("Merge Failed", error instanceof Error ? error.message : "Could not open database");
+        }
+    };
+
+    const closeMergeModal = () => {
+        setMergeSource(null);
+        setMergePassphrase("");
+        setMergeNeedsPassphrase(false);
+        setMergeReport(null);
+    };
+
+    const closeExportModal = () => {
+        setExportTarget(null);
+        setExportPassphrase("");
+        setExportPassphraseConfirm("");
+    };
+
+    const handleExportBackup = async () => {
+        if (!exportTarget) return;
+        if (exportPassphrase !== exportPassphraseConfirm) {
+            toast.error("Export Failed", "Passphrases do not match");
+            return;
+        }
+
+        setIsExporting(true);
+        try {
+            const result = await backupService.exportBackup(exportTarget.filename, exportPassphrase || undefined);
+            if (result) {
+                toast.success("Export Complete", result);
+                closeExportModal();
+            }
+        } catch (error) {
+            console.error(error);
+            toast.error("Export Failed", error instanceof Error ? error.message : String(error));
+        } finally {
+            setIsExporting(false);
         }
     };
 
@@ -187,6 +582,67 @@ export const BackupRestore: React.FC = () => {
         }
     };
 
+    const handleLoadRemoteBackups = async () => {
+        setIsLoadingRemote(true);
+        try {
+            setRemoteBackups(await backupService.listOffsiteBackups());
+        } catch (error) {
+            console.error(error);
+            toast.error("Offsite Storage", String(error));
+        } finally {
+            setIsLoadingRemote(false);
+        }
+    };
+
+    const handleRetryOffsiteUploads = async () => {
+        setIsUploadingOffsite(true);
+        try {
+            const uploaded = await backupService.retryOffsiteUploads();
+            toast.success("Offsite Upload", `${uploaded} backup${uploaded === 1 ? "" : "s"} uploaded`);
+        } catch (error) {
+            console.error(error);
+            toast.error("Offsite Upload Failed", String(error));
+        } finally {
+            setOffsiteStatus(await backupService.getOffsiteStatus());
+            setIsUploadingOffsite(false);
+        }
+    };
+
+    const handleDownloadRemote = async (remote: RemoteBackup) => {
+        setDownloadingRemote(remote.filename);
+        try {
+            await backupService.downloadOffsiteBackup(remote.filename);
+            toast.success("Downloaded", `${remote.filename} is now in Available Backups and can be restored`);
+            await loadData();
+            setRemoteBackups(await backupService.listOffsiteBackups());
+        } catch (error) {
+            console.error(error);
+            toast.error("Download Failed", String(error));
+        } finally {
+            setDownloadingRemote(null);
+        }
+    };
+
+    const handleAddMirror = async () => {
+        if (!settings) return;
+        try {
+            const folder = await backupService.selectMirrorFolder();
+            if (!folder) return;
+            const destinations = parseMirrorDestinations(settings.backup_mirror_destinations);
+            if (destinations.includes(folder)) return;
+            updateSetting("backup_mirror_destinations", JSON.stringify([...destinations, folder]));
+        } catch (error) {
+            console.error(error);
+            toast.error("Error", String(error));
+        }
+    };
+
+    const handleRemoveMirror = (folder: string) => {
+        if (!settings) return;
+        const destinations = parseMirrorDestinations(settings.backup_mirror_destinations);
+        updateSetting("backup_mirror_destinations", JSON.stringify(destinations.filter((d) => d !== folder)));
+    };
+
     const formatFileSize = (bytes: number | null) => {
         if (!bytes) return '-';
         if (bytes < 1024) return `${bytes} B`;
@@ -250,6 +706,23 @@ export const BackupRestore: React.FC = () => {
                         </div>
                     </div>
 
+                    {operationId && (
+                        <div className="mb-4 flex items-center gap-3 rounded-xl border border-teal-100 bg-teal-50 p-3">
+                            <div className="flex-1">
+                                <p className="text-sm font-medium text-teal-800">{describeProgress(operationProgress)}</p>
+                                <div className="mt-2 h-1.5 rounded-full bg-teal-100 overflow-hidden">
+                                    <div
+                                        className="h-full bg-teal-500 transition-all"
+                                        style={{ width: `${operationProgress && operationProgress.total > 0 ? (operationProgress.done / operationProgress.total) * 100 : 0}%` }}
+                                    />
+                                </div>
+                            </div>
+                            <Button variant="ghost" size="sm" onClick={handleCancelOperation}>
+                                Cancel
+                            </Button>
+                        </div>
+                    )}
+
                     <div className="grid grid-cols-1 md:grid-cols-3 gap-4">
                         <div className="bg-teal-50 rounded-xl p-4 border border-teal-100">
                             <div className="flex items-center gap-3 mb-2">
@@ -334,30 +807,68 @@ export const BackupRestore: React.FC = () => {
                                                         {new Date(backup.modified_at).toLocaleString()}
                                                     </td>
                                                     <td className="p-3">
-                                                        <Badge variant={logEntry?.backup_type === 'auto' ? 'info' : 'neutral'}>
-                                                            {logEntry?.backup_type || 'manual'}
-                                                        </Badge>
+                                                        <div className="flex items-center gap-1">
+                                                            <Badge variant={logEntry?.backup_type === 'auto' ? 'info' : 'neutral'}>
+                                                                {logEntry?.backup_type || 'manual'}
+                                                            </Badge>
+                                                            {backup.manifest?.kind === 'incremental' && (
+                                                                <span title={`Changes since ${backup.manifest.parent_file ?? 'unknown'}\nChain starts at ${backup.manifest.base_file ?? 'unknown'}`}>
+                                                                    <Badge variant="warning">incremental</Badge>
+                                                                </span>
+                                                            )}
+                                                        </div>
                                                     </td>
                                                     <td className="p-3 text-slate-600 font-mono text-xs">
                                                         {formatFileSize(backup.file_size)}
                                                     </td>
                                                     <td className="p-3">
-                                                        <Badge variant="success">
-                                                            Available
-                                                        </Badge>
+                                                        {backup.manifest?.stale ? (
+                                                            <span title="The file's size differs from when it was backed up; it may be damaged">
+                                                                <Badge variant="danger">Changed</Badge>
+                                                            </span>
+                                                        ) : (
+                                                            <Badge variant="success">
+                                                                Available
+                                                            </Badge>
+                                                        )}
                                                     </td>
                                                     <td className="p-3 text-right">
                                                         <div className="flex items-center justify-end gap-1">
                                                             <Button
                                                                 variant="ghost"
                                                                 size="sm"
-                                                                onClick={() => setRestoreConfirm({ open: true, backup })}
+                                                                onClick={() => openRestoreConfirm(backup)}
                                                                 disabled={isRestoring}
                                                                 className="hover:bg-teal-50 hover:text-teal-600"
                                                                 leftIcon={<RotateCcw size={14} />}
                                                             >
                                                                 Restore
                                                             </Button>
+                                                            <Button
+                                                                variant="ghost"
+                                                                size="sm"
+                                                                onClick={() => setBrowseTarget(backup)}
+                                                                className="hover:bg-teal-50 hover:text-teal-600"
+                                                                leftIcon={<Search size={14} />}
+                                                                title="Browse records without restoring"
+                                                            />
+                                                            <Button
+                                                                variant="ghost"
+                                                                size="sm"
+                                                                onClick={() => setSelectiveTarget(backup)}
+                                                                disabled={isRestoring}
+                                                                className="hover:bg-teal-50 hover:text-teal-600"
+                                                                leftIcon={<ListChecks size={14} />}
+                                                                title="Restore selected tables or records"
+                                                            />
+                                                            <Button
+                                                                variant="ghost"
+                                                                size="sm"
+                                                                onClick={() => setExportTarget(backup)}
+                                                                className="hover:bg-sky-50 hover:text-sky-600"
+                                                                leftIcon={<Share size={14} />}
+                                                                title="Export"
+                                                            />
                                                             <Button
                                                                 variant="ghost"
                                                                 size="sm"
@@ -393,7 +904,7 @@ export const BackupRestore: React.FC = () => {
                             <div className="flex items-center justify-between">
                                 <div>
                                     <label className="font-semibold text-slate-700 block">Enable Auto-Backup</label>
-                                    <p className="text-xs text-slate-500">Backup daily automatically</p>
+                                    <p className="text-xs text-slate-500">Runs in the background, even if this screen is never opened</p>
                                 </div>
                                 <label className="relative inline-flex items-center cursor-pointer">
                                     <input
@@ -408,6 +919,35 @@ export const BackupRestore: React.FC = () => {
 
                             {settings.auto_backup_enabled && (
                                 <div className="space-y-4 animate-in fade-in slide-in-from-top-2">
+                                    <div>
+                                        <label className="block text-sm font-semibold text-slate-700 mb-2">
+                                            Frequency
+                                        </label>
+                                        <select
+                                            value={settings.auto_backup_frequency}
+                                            onChange={(e) => updateSetting("auto_backup_frequency", e.target.value as BackupFrequency)}
+                                            className="w-full h-11 px-3 rounded-xl border border-slate-200 bg-white text-slate-700 focus:outline-none focus:ring-2 focus:ring-teal-500/30"
+                                        >
+                                            <option value="daily">Daily</option>
+                                            <option value="weekly">Weekly</option>
+                                        </select>
+                                    </div>
+                                    {settings.auto_backup_frequency === 'weekly' && (
+                                        <div>
+                                            <label className="block text-sm font-semibold text-slate-700 mb-2">
+                                                Backup Day
+                                            </label>
+                                            <select
+                                                value={settings.auto_backup_day}
+                                                onChange={(e) => updateSetting("auto_backup_day", parseInt(e.target.value))}
+                                                className="w-full h-11 px-3 rounded-xl border border-slate-200 bg-white text-slate-700 focus:outline-none focus:ring-2 focus:ring-teal-500/30"
+                                            >
+                                                {WEEKDAYS.map((day, idx) => (
+                                                    <option key={day} value={idx}>{day}</option>
+                                                ))}
+                                            </select>
+                                        </div>
+                                    )}
                                     <div>
                                         <label className="block text-sm font-semibold text-slate-700 mb-2">
                                             Backup Time
@@ -420,7 +960,52 @@ export const BackupRestore: React.FC = () => {
                                     </div>
                                     <div>
                                         <label className="block text-sm font-semibold text-slate-700 mb-2">
-                                            Retention Period
+                                            Keep Backups
+                                        </label>
+                                        <div className="grid grid-cols-3 gap-2 mb-4">
+                                            <Input
+                                                type="number"
+                                                min={1}
+                                                label="Daily"
+                                                value={settings.backup_keep_daily}
+                                                onChange={(e) => updateSetting("backup_keep_daily", parseInt(e.target.value) || 1)}
+                                            />
+                                            <Input
+                                                type="number"
+                                                min={0}
+                                                label="Weekly"
+                                                value={settings.backup_keep_weekly}
+                                                onChange={(e) => updateSetting("backup_keep_weekly", parseInt(e.target.value) || 0)}
+                                            />
+                                            <Input
+                                                type="number"
+                                                min={0}
+                                                label="Monthly"
+                                                value={settings.backup_keep_monthly}
+                                                onChange={(e) => updateSetting("backup_keep_monthly", parseInt(e.target.value) || 0)}
+                                            />
+                                        </div>
+                                        <label className="block text-sm font-semibold text-slate-700 mb-2">
+                                            Safety Backups
+                                        </label>
+                                        <div className="grid grid-cols-2 gap-2 mb-4">
+                                            <Input
+                                                type="number"
+                                                min={0}
+                                                label="Always keep newest"
+                                                value={settings.safety_backup_keep_count}
+                                                onChange={(e) => updateSetting("safety_backup_keep_count", parseInt(e.target.value) || 0)}
+                                            />
+                                            <Input
+                                                type="number"
+                                                min={1}
+                                                label="Keep others for (days)"
+                                                value={settings.safety_backup_max_age_days}
+                                                onChange={(e) => updateSetting("safety_backup_max_age_days", parseInt(e.target.value) || 1)}
+                                            />
+                                        </div>
+                                        <label className="block text-sm font-semibold text-slate-700 mb-2">
+                                            Backup Log Retention
                                         </label>
                                         <select
                                             value={settings.backup_retention_days}
@@ -437,6 +1022,107 @@ export const BackupRestore: React.FC = () => {
                                 </div>
                             )}
 
+                            <div>
+                                <label className="block text-sm font-semibold text-slate-700 mb-2">
+                                    Backup Mode
+                                </label>
+                                <select
+                                    value={settings.backup_mode}
+                                    onChange={(e) => updateSetting("backup_mode", e.target.value as BackupMode)}
+                                    className="w-full h-11 px-3 rounded-xl border border-slate-200 bg-white text-slate-700 focus:outline-none focus:ring-2 focus:ring-teal-500/30"
+                                >
+                                    <option value="full">Full copy every time</option>
+                                    <option value="incremental">Incremental (changes only)</option>
+                                </select>
+                                {settings.backup_mode === 'incremental' && (
+                                    <div className="mt-2">
+                                        <Input
+                                            type="number"
+                                            min={1}
+                                            label="Full backup every (days)"
+                                            value={settings.backup_full_interval_days}
+                                            onChange={(e) => updateSetting("backup_full_interval_days", parseInt(e.target.value) || 1)}
+                                        />
+                                    </div>
+                                )}
+                            </div>
+
+                            <div>
+                                <label className="block text-sm font-semibold text-slate-700 mb-2">
+                                    Compression
+                                </label>
+                                <div className="grid grid-cols-2 gap-2">
+                                    <select
+                                        value={settings.backup_compression}
+                                        onChange={(e) => updateSetting("backup_compression", e.target.value as BackupCompression)}
+                                        className="w-full h-11 px-3 rounded-xl border border-slate-200 bg-white text-slate-700 focus:outline-none focus:ring-2 focus:ring-teal-500/30"
+                                    >
+                                        <option value="none">None (.db)</option>
+                                        <option value="zstd">Zstandard (.db.zst)</option>
+                                        <option value="gzip">Gzip (.db.gz)</option>
+                                    </select>
+                                    {settings.backup_compression !== 'none' && (
+                                        <Input
+                                            type="number"
+                                            min={settings.backup_compression === 'zstd' ? 1 : 0}
+                                            max={settings.backup_compression === 'zstd' ? 19 : 9}
+                                            value={settings.backup_compression_level}
+                                            onChange={(e) => updateSetting("backup_compression_level", parseInt(e.target.value) || 1)}
+                                        />
+                                    )}
+                                </div>
+                            </div>
+
+                            <div>
+                                <label className="block text-sm font-semibold text-slate-700 mb-2">
+                                    Mirror Destinations
+                                </label>
+                                <p className="text-xs text-slate-500 mb-2">
+                                    Every backup is also copied to these folders. Drives that are not connected are skipped and catch up next time.
+                                </p>
+                                <div className="space-y-2">
+                                    {parseMirrorDestinations(settings.backup_mirror_destinations).map((folder) => {
+                                        const status = mirrorStatus.find((m) => m.destination === folder);
+                                        return (
+                                            <div key={folder} className="flex items-start gap-2 p-2 rounded-xl border border-slate-200 bg-white">
+                                                <HardDrive size={16} className="mt-0.5 text-slate-400 shrink-0" />
+                                                <div className="flex-1 min-w-0">
+                                                    <p className="text-sm text-slate-700 truncate" title={folder}>{folder}</p>
+                                                    {status ? (
+                                                        <p className="text-xs text-slate-500">
+                                                            {status.available
+                                                                ? `Connected${status.free_bytes !== null ? ` · ${formatFileSize(status.free_bytes)} free` : ""}`
+                                                                : "Not connected"}
+                                                            {status.last_success_at && ` · Last copy ${new Date(status.last_success_at).toLocaleString()}`}
+                                                        </p>
+                                                    ) : (
+                                                        <p className="text-xs text-slate-500">Save to check this destination</p>
+                                                    )}
+                                                    {status?.last_error && (
+                                                        <p className="text-xs text-red-600">{status.last_error}</p>
+                                                    )}
+                                                </div>
+                                                <button
+                                                    onClick={() => handleRemoveMirror(folder)}
+                                                    className="p-1 text-slate-400 hover:text-red-600"
+                                                    title="Remove destination"
+                                                >
+                                                    <Trash2 size={14} />
+                                                </button>
+                                            </div>
+                                        );
+                                    })}
+                                    <Button
+                                        variant="secondary"
+                                        onClick={handleAddMirror}
+                                        className="w-full"
+                                        leftIcon={<Plus size={16} />}
+                                    >
+                                        Add Folder
+                                    </Button>
+                                </div>
+                            </div>
+
                             <Button
                                 onClick={handleSaveSettings}
                                 isLoading={savingSettings}
@@ -448,6 +1134,139 @@ export const BackupRestore: React.FC = () => {
                         </div>
                     </Card>
 
+                    <Card>
+                        <div className="flex items-center justify-between mb-4">
+                            <h3 className="font-bold text-slate-800 flex items-center gap-2">
+                                <Cloud className="text-teal-600" size={18} />
+                                Offsite Storage
+                            </h3>
+                            <label className="relative inline-flex items-center cursor-pointer">
+                                <input
+                                    type="checkbox"
+                                    checked={settings.offsite_enabled}
+                                    onChange={(e) => updateSetting("offsite_enabled", e.target.checked)}
+                                    className="sr-only peer"
+                                />
+                                <div className="w-11 h-6 bg-slate-200 peer-focus:outline-none peer-focus:ring-4 peer-focus:ring-teal-300 rounded-full peer peer-checked:after:translate-x-full peer-checked:after:border-white after:content-[''] after:absolute after:top-0.5 after:left-0.5 after:bg-white after:border-slate-300 after:border after:rounded-full after:h-5 after:w-5 after:transition-all peer-checked:bg-teal-600"></div>
+                            </label>
+                        </div>
+                        <p className="text-sm text-slate-500 mb-4">
+                            Upload every backup to an S3-compatible bucket (AWS S3, MinIO, Backblaze B2, ...). Uploads made while offline are retried automatically.
+                        </p>
+
+                        {settings.offsite_enabled && (
+                            <div className="space-y-3">
+                                <Input
+                                    label="Endpoint"
+                                    placeholder="https://s3.eu-west-1.amazonaws.com"
+                                    value={settings.offsite_endpoint}
+                                    onChange={(e) => updateSetting("offsite_endpoint", e.target.value)}
+                                />
+                                <div className="grid grid-cols-2 gap-2">
+                                    <Input
+                                        label="Bucket"
+                                        value={settings.offsite_bucket}
+                                        onChange={(e) => updateSetting("offsite_bucket", e.target.value)}
+                                    />
+                                    <Input
+                                        label="Region"
+                                        value={settings.offsite_region}
+                                        onChange={(e) => updateSetting("offsite_region", e.target.value)}
+                                    />
+                                </div>
+                                <Input
+                                    label="Folder in Bucket"
+                                    value={settings.offsite_prefix}
+                                    onChange={(e) => updateSetting("offsite_prefix", e.target.value)}
+                                />
+                                <Input
+                                    label="Access Key"
+                                    value={settings.offsite_access_key}
+                                    onChange={(e) => updateSetting("offsite_access_key", e.target.value)}
+                                />
+                                <Input
+                                    type="password"
+                                    label="Secret Key"
+                                    placeholder={offsiteStatus?.has_secret_key ? "Saved. Type to replace" : undefined}
+                                    value={offsiteSecretKey}
+                                    onChange={(e) => setOffsiteSecretKey(e.target.value)}
+                                />
+                                <Input
+                                    type="password"
+                                    label="Encryption Passphrase"
+                                    placeholder={offsiteStatus?.has_passphrase ? "Saved. Type to replace" : "At least 8 characters"}
+                                    value={offsitePassphrase}
+                                    onChange={(e) => setOffsitePassphrase(e.target.value)}
+                                />
+                                <p className="text-xs text-slate-500">
+                                    Backups are encrypted with this passphrase before upload. Keep a copy somewhere safe: remote backups cannot be restored without it.
+                                </p>
+
+                                {offsiteStatus && offsiteStatus.pending.length > 0 && (
+                                    <div className="p-3 rounded-xl bg-amber-50 border border-amber-100 text-sm text-amber-800">
+                                        {offsiteStatus.pending.length} backup{offsiteStatus.pending.length === 1 ? "" : "s"} waiting to upload
+                                        {offsiteStatus.pending[0].last_error && (
+                                            <p className="text-xs mt-1">{offsiteStatus.pending[0].last_error}</p>
+                                        )}
+                                    </div>
+                                )}
+
+                                <div className="grid grid-cols-2 gap-2">
+                                    <Button
+                                        variant="secondary"
+                                        onClick={handleLoadRemoteBackups}
+                                        isLoading={isLoadingRemote}
+                                        disabled={!offsiteStatus?.configured}
+                                        title={offsiteStatus?.configured ? undefined : "Save the storage settings first"}
+                                    >
+                                        Show Remote Backups
+                                    </Button>
+                                    <Button
+                                        variant="secondary"
+                                        onClick={handleRetryOffsiteUploads}
+                                        isLoading={isUploadingOffsite}
+                                        disabled={!offsiteStatus?.configured || offsiteStatus.pending.length === 0}
+                                    >
+                                        Upload Now
+                                    </Button>
+                                </div>
+
+                                {remoteBackups && (
+                                    <div className="space-y-2 max-h-64 overflow-y-auto">
+                                        {remoteBackups.length === 0 ? (
+                                            <p className="text-sm text-slate-500">No backups in the bucket yet.</p>
+                                        ) : (
+                                            remoteBackups.map((remote) => (
+                                                <div key={remote.key} className="flex items-center gap-2 p-2 rounded-xl border border-slate-200">
+                                                    <div className="flex-1 min-w-0">
+                                                        <p className="text-sm text-slate-700 truncate" title={remote.filename}>{remote.filename}</p>
+                                                        <p className="text-xs text-slate-500">
+                                                            {formatFileSize(remote.size)} · {new Date(remote.last_modified).toLocaleString()}
+                                                        </p>
+                                                    </div>
+                                                    {remote.downloaded ? (
+                                                        <Badge variant="success">local</Badge>
+                                                    ) : (
+                                                        <Button
+                                                            variant="ghost"
+                                                            size="sm"
+                                                            onClick={() => handleDownloadRemote(remote)}
+                                                            isLoading={downloadingRemote === remote.filename}
+                                                            leftIcon={<Download size={14} />}
+                                                            title="Download to this PC"
+                                                        />
+                                                    )}
+                                                </div>
+                                            ))
+                                        )}
+                                    </div>
+                                )}
+                            </div>
+                        )}
+
+                        <p className="text-xs text-slate-400 mt-3">Storage settings are saved with Save Preferences.</p>
+                    </Card>
+
                     <Card className="bg-amber-50 border-amber-100">
                         <div className="flex gap-3">
                             <AlertTriangle className="text-amber-600 shrink-0" size={24} />
@@ -468,7 +1287,7 @@ export const BackupRestore: React.FC = () => {
                             Restore from File
                         </h3>
                         <p className="text-sm text-slate-500 mb-4">
-                            Have a backup file (.db) saved externally? Select it to restore your data.
+                            Have a backup file (.db, .db.zst or .db.gz) saved externally? Select it to restore your data.
                         </p>
                         <Button
                             variant="secondary"
@@ -480,20 +1299,89 @@ export const BackupRestore: React.FC = () => {
                             Select Backup File
                         </Button>
                     </Card>
+
+                    <Card>
+                        <h3 className="font-bold text-slate-800 mb-4 flex items-center gap-2">
+                            <FileSpreadsheet className="text-teal-600" size={18} />
+                            Spreadsheet Export
+                        </h3>
+                        <p className="text-sm text-slate-500 mb-4">
+                            Save all data as a zip of CSV or JSON files that spreadsheets and scripts can open.
+                            Edited files can be imported again.
+                        </p>
+                        <div className="grid grid-cols-2 gap-2 mb-2">
+                            <Button
+                                variant="secondary"
+                                leftIcon={<FileSpreadsheet size={18} />}
+                                onClick={() => handleExportArchive("csv")}
+                                isLoading={isExportingArchive}
+                            >
+                                Export CSV
+                            </Button>
+                            <Button
+                                variant="secondary"
+                                leftIcon={<FileSpreadsheet size={18} />}
+                                onClick={() => handleExportArchive("json")}
+                                isLoading={isExportingArchive}
+                            >
+                                Export JSON
+                            </Button>
+                        </div>
+                        <Button
+                            variant="ghost"
+                            className="w-full"
+                            leftIcon={<Upload size={18} />}
+                            onClick={handleSelectArchive}
+                            isLoading={isImportingArchive}
+                        >
+                            Import Archive
+                        </Button>
+                    </Card>
+
+                    <Card>
+                        <h3 className="font-bold text-slate-800 mb-4 flex items-center gap-2">
+                            <GitMerge className="text-teal-600" size={18} />
+                            Merge from Another Terminal
+                        </h3>
+                        <p className="text-sm text-slate-500 mb-4">
+                            Add the invoices and returns recorded on another computer to this one. Nothing here is
+                            replaced, and you can review what will be merged first.
+                        </p>
+                        <Button
+                            variant="secondary"
+                            className="w-full"
+                            leftIcon={<GitMerge size={18} />}
+                            onClick={handleSelectMergeSource}
+                            isLoading={isMerging && mergeReport === null && !mergeNeedsPassphrase}
+                        >
+                            Select Database to Merge
+                        </Button>
+                    </Card>
                 </div>
             </div>
 
             {/* Restore Confirmation Modal */}
             <ConfirmModal
                 isOpen={restoreConfirm.open}
-                onClose={() => setRestoreConfirm({ open: false, backup: null })}
+                onClose={() => { setRestoreConfirm({ open: false, backup: null }); setRestorePreview(null); }}
                 onConfirm={handleRestore}
                 title="Restore Database?"
-                message={`Are you sure you want to restore the database from "${restoreConfirm.backup?.modified_at ? new Date(restoreConfirm.backup.modified_at).toLocaleString() : 'selected backup'}"?\n\nA safety backup will be created automatically. The application will restart after restore.`}
+                message={`Are you sure you want to restore the database from "${restoreConfirm.backup?.modified_at ? new Date(restoreConfirm.backup.modified_at).toLocaleString() : 'selected backup'}"?${restorePreview ? describeRestorePreview(restorePreview) : describeBackupContents(restoreConfirm.backup)}\n\nA safety backup will be created automatically. The application will restart after restore.`}
                 confirmText="Yes, Restore Database"
                 variant="danger"
             />
 
+            {/* Archive Import Confirmation Modal */}
+            <ConfirmModal
+                isOpen={archiveImportConfirm !== null}
+                onClose={() => setArchiveImportConfirm(null)}
+                onConfirm={handleImportArchive}
+                title="Import Data Archive?"
+                message={`Every table in "${archiveImportConfirm?.split(/[\\/]/).pop() ?? ""}" will replace the current data in that table. Tables not in the archive are left as they are.\n\nThe archive is checked first and nothing changes if any value is invalid. A safety backup will be created automatically.`}
+                confirmText="Yes, Import Data"
+                variant="danger"
+            />
+
             {/* Delete Confirmation Modal */}
             <ConfirmModal
                 isOpen={deleteConfirm.open}
@@ -504,6 +1392,257 @@ export const BackupRestore: React.FC = () => {
                 confirmText="Yes, Delete Backup"
                 variant="danger"
             />
+
+            {/* Selective Restore Modal */}
+            <BackupBrowser backup={browseTarget} onClose={() => setBrowseTarget(null)} />
+
+            <Modal
+                isOpen={selectiveTarget !== null}
+                onClose={closeSelectiveModal}
+                title="Partial Restore"
+                size="sm"
+            >
+                <div className="space-y-4">
+                    <p className="text-sm text-slate-600">
+                        Replace only the selected tables with their contents in this backup.
+                        Other data is left as it is. A safety backup is created first.
+                    </p>
+                    <div className="grid grid-cols-2 gap-2">
+                        {RESTORABLE_TABLES.map(({ table, label }) => (
+                            <label key={table} className="flex items-center gap-2 text-sm text-slate-700">
+                                <input
+                                    type="checkbox"
+                                    checked={selectedTables.includes(table)}
+                                    onChange={() => toggleSelectedTable(table)}
+                                    className="rounded border-slate-300 text-teal-600 focus:ring-teal-500"
+                                />
+                                {label}
+                            </label>
+                        ))}
+                    </div>
+                    <Button
+                        onClick={handleRestoreTables}
+                        isLoading={isSelectiveRestoring}
+                        disabled={selectedTables.length === 0}
+                        className="w-full"
+                        leftIcon={<RotateCcw size={18} />}
+                    >
+                        Restore Selected Tables
+                    </Button>
+
+                    <div className="border-t border-slate-100 pt-4 space-y-3">
+                        <p className="text-sm text-slate-600">
+                            Or recover individual records by ID. Invoices and returns include their items.
+                        </p>
+                        <select
+                            value={recoverTable}
+                            onChange={(e) => setRecoverTable(e.target.value)}
+                            className="w-full h-11 px-3 rounded-xl border border-slate-200 bg-white text-slate-700 focus:outline-none focus:ring-2 focus:ring-teal-500/30"
+                        >
+                            {RECOVERABLE_TABLES.map((table) => (
+                                <option key={table} value={table}>
+                                    {RESTORABLE_TABLES.find((t) => t.table === table)?.label ?? table}
+                                </option>
+                            ))}
+                        </select>
+                        <Input
+                            label="Record IDs (comma separated)"
+                            value={recoverIds}
+                            onChange={(e) => setRecoverIds(e.target.value)}
+                        />
+                        <Button
+                            variant="secondary"
+                            onClick={handleRecoverRecords}
+                            isLoading={isSelectiveRestoring}
+                            disabled={!recoverIds.trim()}
+                            className="w-full"
+                            leftIcon={<History size={18} />}
+                        >
+                            Recover Records
+                        </Button>
+                    </div>
+                </div>
+            </Modal>
+
+            {/* Export Modal */}
+            <Modal
+                isOpen={exportTarget !== null}
+                onClose={closeExportModal}
+                title="Export Backup"
+                size="sm"
+            >
+                <div className="space-y-4">
+                    <p className="text-sm text-slate-600">
+                        Backups contain customer details and password hashes. Set a passphrase to encrypt
+                        the exported file, or leave it empty to export it as-is.
+                    </p>
+                    <Input
+                        type="password"
+                        label="Passphrase (optional)"
+                        value={exportPassphrase}
+                        onChange={(e) => setExportPassphrase(e.target.value)}
+                        autoComplete="new-password"
+                    />
+                    {exportPassphrase && (
+                        <Input
+                            type="password"
+                            label="Confirm Passphrase"
+                            value={exportPassphraseConfirm}
+                            onChange={(e) => setExportPassphraseConfirm(e.target.value)}
+                            autoComplete="new-password"
+                        />
+                    )}
+                    {exportPassphrase && (
+                        <p className="text-xs text-amber-700">
+                            The passphrase cannot be recovered. Without it the exported backup cannot be restored.
+                        </p>
+                    )}
+                    <Button
+                        onClick={handleExportBackup}
+                        isLoading={isExporting}
+                        className="w-full"
+                        leftIcon={exportPassphrase ? <Lock size={18} /> : <Share size={18} />}
+                    >
+                        {exportPassphrase ? "Encrypt & Export" : "Export"}
+                    </Button>
+                </div>
+            </Modal>
+
+            {/* Merge Preview Modal */}
+            <Modal
+                isOpen={mergeSource !== null && (mergeReport !== null || mergeNeedsPassphrase)}
+                onClose={closeMergeModal}
+                title={mergeReport?.dry_run === false ? "Merge Complete" : "Merge from Another Terminal"}
+                size="md"
+            >
+                {mergeNeedsPassphrase ? (
+                    <div className="space-y-4">
+                        <p className="text-sm text-slate-600">
+                            This file is encrypted. Enter the passphrase used when it was exported.
+                        </p>
+                        <Input
+                            type="password"
+                            label="Passphrase"
+                            value={mergePassphrase}
+                            onChange={(e) => setMergePassphrase(e.target.value)}
+                            autoFocus
+                        />
+                        <Button
+                            onClick={() => mergeSource && runMerge(mergeSource, true, mergePassphrase)}
+                            isLoading={isMerging}
+                            disabled={!mergePassphrase}
+                            className="w-full"
+                            leftIcon={<Lock size={18} />}
+                        >
+                            Decrypt &amp; Preview
+                        </Button>
+                    </div>
+                ) : mergeReport && (
+                    <div className="space-y-4">
+                        <p className="text-sm text-slate-600">
+                            {mergeReport.dry_run
+                                ? "Nothing has been changed yet. This is what merging the selected database would do:"
+                                : mergeReport.message}
+                        </p>
+
+                        <div className="border border-slate-200 rounded-xl divide-y divide-slate-100">
+                            {mergeReport.tables.map((t) => (
+                                <div key={t.table} className="flex items-center justify-between px-3 py-2 text-sm">
+                                    <span className="capitalize text-slate-700">{t.table.replace(/_/g, " ")}</span>
+                                    <span className="text-slate-500">
+                                        {t.imported} new · {t.already_present} already here
+                                        {t.conflicts > 0 && <span className="text-amber-600"> · {t.conflicts} conflicts</span>}
+                                    </span>
+                                </div>
+                            ))}
+                        </div>
+
+                        {(mergeReport.stock_changes.length > 0 || mergeReport.products_added.length > 0) && (
+                            <div className="text-sm text-slate-600 space-y-1">
+                                <p className="font-semibold text-slate-700">Stock</p>
+                                {mergeReport.stock_changes.map((c) => (
+                                    <div key={c.product_id} className="flex justify-between">
+                                        <span>{c.product_name ?? c.product_id}</span>
+                                        <span className={c.delta < 0 ? "text-red-600" : "text-green-600"}>
+                                            {c.delta > 0 ? `+${c.delta}` : c.delta}
+                                        </span>
+                                    </div>
+                                ))}
+                                {mergeReport.products_added.length > 0 && (
+                                    <p className="text-xs text-slate-500">
+                                        {mergeReport.products_added.length} product{mergeReport.products_added.length === 1 ? "" : "s"} only
+                                        the other terminal has will be added with its stock level.
+                                    </p>
+                                )}
+                            </div>
+                        )}
+
+                        {mergeReport.conflicts.length > 0 && (
+                            <div className="p-3 bg-amber-50 border border-amber-200 rounded-xl text-sm text-amber-800 space-y-1 max-h-48 overflow-y-auto">
+                                <p className="font-semibold">Left unchanged</p>
+                                {mergeReport.conflicts.map((c) => (
+                                    <p key={`${c.table}-${c.id}`} className="text-xs">
+                                        {c.table.replace(/_/g, " ")} {c.id.slice(-8).toUpperCase()}: {c.reason}
+                                        {c.columns.length > 0 && ` (${c.columns.join(", ")})`}
+                                    </p>
+                                ))}
+                            </div>
+                        )}
+
+                        {mergeReport.dry_run ? (
+                            <Button
+                                onClick={() => mergeSource && runMerge(mergeSource, false, mergePassphrase || undefined)}
+                                isLoading={isMerging}
+                                disabled={mergeReport.tables.every((t) => t.imported === 0)}
+                                className="w-full"
+                                leftIcon={<GitMerge size={18} />}
+                            >
+                                Merge Now
+                            </Button>
+                        ) : (
+                            <Button variant="secondary" onClick={closeMergeModal} className="w-full">
+                                Done
+                            </Button>
+                        )}
+                        {mergeReport.dry_run && (
+                            <p className="text-xs text-slate-500 text-center">A safety backup is created before merging.</p>
+                        )}
+                    </div>
+                )}
+            </Modal>
+
+            {/* Encrypted Import Passphrase Modal */}
+            <Modal
+                isOpen={encryptedImportPath !== null}
+                onClose={() => {
+                    setEncryptedImportPath(null);
+                    setImportPassphrase("");
+                }}
+                title="Encrypted Backup"
+                size="sm"
+            >
+                <div className="space-y-4">
+                    <p className="text-sm text-slate-600">
+                        This backup is encrypted. Enter the passphrase used when it was exported.
+                    </p>
+                    <Input
+                        type="password"
+                        label="Passphrase"
+                        value={importPassphrase}
+                        onChange={(e) => setImportPassphrase(e.target.value)}
+                        autoFocus
+                    />
+                    <Button
+                        onClick={() => encryptedImportPath && runImport(encryptedImportPath, importPassphrase)}
+                        isLoading={isImporting}
+                        disabled={!importPassphrase}
+                        className="w-full"
+                        leftIcon={<Lock size={18} />}
+                    >
+                        Decrypt &amp; Import
+                    </Button>
+                </div>
+            </Modal>
         </div>
     );
 };
@@ -3,6 +3,7 @@ import {
     Calendar,
     ChevronLeft,
     ChevronRight,
+    Edit2,
     Eye,
     FileText,
     Package,
@@ -15,6 +16,8 @@ import {
 import React, { useCallback, useEffect, useMemo, useState } from "react";
 import { invoiceService } from "../db/invoiceService";
 import { returnsService } from "../db/returnsService";
+import { isTauriRuntime } from "../db/runtime";
+import { settingsService } from "../db/settingsService";
 import { useDebounce } from "../hooks";
 import {
     Invoice,
@@ -71,6 +74,14 @@ export const SalesReturns: React.FC<SalesReturnsProps> = ({ userRole = "staff",
     });
     const [isCancelling, setIsCancelling] = useState(false);
 
+    // Amend modal
+    const [graceHours, setGraceHours] = useState(24);
+    const [editingReturn, setEditingReturn] = useState<SalesReturnWithItems | null>(null);
+    const [editQuantities, setEditQuantities] = useState<Map<string, number>>(new Map());
+    const [editReason, setEditReason] = useState<ReturnReason>("customer_request");
+    const [editNotes, setEditNotes] = useState("");
+    const [isAmending, setIsAmending] = useState(false);
+
     // Stats
     const [stats, setStats] = useState({ totalReturns: 0, totalAmount: 0, todayReturns: 0, todayAmount: 0 });
 
@@ -109,6 +120,12 @@ export const SalesReturns: React.FC<SalesReturnsProps> = ({ userRole = "staff",
         setPage(1);
     }, [fromDate, toDate]);
 
+    useEffect(() => {
+        settingsService.get("return_grace_hours")
+            .then(setGraceHours)
+            .catch(console.error);
+    }, []);
+
     const totalPages = Math.max(1, Math.ceil(totalCount / PAGE_SIZE));
 
     const filteredReturns = useMemo(() => {
@@ -263,12 +280,79 @@ export const SalesReturns: React.FC<SalesReturnsProps> = ({ userRole = "staff",
             loadReturns();
         } catch (error) {
             console.error(error);
-            toast.error("Error", "Could not cancel return");
+            const msg = error instanceof Error ? error.message : String(error);
+            toast.error("Error", msg || "Could not cancel return");
         } finally {
             setIsCancelling(false);
         }
     };
 
+    // Returns can be amended in the desktop app until the grace window closes
+    const isWithinGrace = (returnItem: SalesReturn) => {
+        if (!isTauriRuntime() || returnItem.status === 'cancelled') return false;
+        // SQLite timestamps ("YYYY-MM-DD HH:MM:SS") are UTC
+        const raw = returnItem.created_at || returnItem.return_date;
+        const created = new Date(/^\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}$/.test(raw) ? `${raw.replace(" ", "T")}Z` : raw);
+        return !Number.isNaN(created.getTime())
+            && Date.now() - created.getTime() < graceHours * 60 * 60 * 1000;
+    };
+
+    const openEditModal = async (returnItem: SalesReturn) => {
+        try {
+            const detail = await returnsService.getById(returnItem.id);
+            if (!detail) {
+                toast.error("Error", "Could not load return details");
+                return;
+            }
+            setEditingReturn(detail);
+            setEditQuantities(new Map(detail.items.map(item => [item.id, item.quantity])));
+            setEditReason(detail.reason);
+            setEditNotes(detail.notes || "");
+        } catch (error) {
+            console.error(error);
+            toast.error("Error", "Could not load return details");
+        }
+    };
+
+    const calculateAmendedAmount = () => {
+        if (!editingReturn) return 0;
+        return editingReturn.items.reduce((total, item) => total + item.rate * (editQuantities.get(item.id) || 0), 0);
+    };
+
+    const handleAmendReturn = async () => {
+        if (!editingReturn) return;
+
+        const items = editingReturn.items
+            .filter(item => (editQuantities.get(item.id) || 0) > 0)
+            .map(item => ({
+                productId: item.product_id,
+                quantity: editQuantities.get(item.id) || 0,
+                rate: item.rate,
+            }));
+        if (items.length === 0) {
+            toast.warning("No Items", "Keep at least one item, or cancel the return instead");
+            return;
+        }
+
+        setIsAmending(true);
+        try {
+            const result = await returnsService.amend(editingReturn.id, {
+                reason: editReason,
+                notes: editNotes.trim() || null,
+                items,
+            }, userName);
+            toast.success("Return Updated", `Return ${result.return_no} has been updated. Stock adjusted.`);
+            setEditingReturn(null);
+            loadReturns();
+        } catch (error) {
+            console.error(error);
+            const msg = error instanceof Error ? error.message : String(error);
+            toast.error("Error", msg || "Could not update return");
+        } finally {
+            setIsAmending(false);
+        }
+    };
+
     const printReturn = (returnItem: SalesReturnWithItems) => {
         const printWindow = window.open('', '_blank');
         if (!printWindow) return;
@@ -486,6 +570,16 @@ export const SalesReturns: React.FC<SalesReturnsProps> = ({ userRole = "staff",
                                                 >
                                                     View
                                                 </Button>
+                                                {userRole === 'admin' && isWithinGrace(ret) && (
+                                                    <Button
+                                                        variant="ghost"
+                                                        size="sm"
+                                                        onClick={() => openEditModal(ret)}
+                                                        leftIcon={<Edit2 size={14} />}
+                                                    >
+                                                        Edit
+                                                    </Button>
+                                                )}
                                                 {ret.status !== 'cancelled' && userRole === 'admin' && (
                                                     <Button
                                                         variant="ghost"
@@ -798,6 +892,88 @@ export const SalesReturns: React.FC<SalesReturnsProps> = ({ userRole = "staff",
                 )}
             </Modal>
 
+            {/* Amend Return Modal */}
+            <Modal
+                isOpen={!!editingReturn}
+                onClose={() => setEditingReturn(null)}
+                title={`Edit Return ${editingReturn?.return_no}`}
+                size="md"
+            >
+                {editingReturn && (
+                    <div className="space-y-6">
+                        <div>
+                            <p className="text-sm font-semibold text-slate-700 mb-2">Returned quantities (0 removes the item):</p>
+                            <div className="border border-slate-200 rounded-xl divide-y divide-slate-100">
+                                {editingReturn.items.map(item => (
+                                    <div key={item.id} className="p-4 flex items-center gap-4">
+                                        <div className="flex-1">
+                                            <div className="flex items-center gap-2">
+                                                <Package size={16} className="text-slate-400" />
+                                                <span className="font-medium text-slate-800">{item.product_name || 'Unknown Product'}</span>
+                                            </div>
+                                            <p className="text-sm text-slate-500">₹{item.rate.toLocaleString()} each</p>
+                                        </div>
+                                        <input
+                                            type="number"
+                                            min={0}
+                                            value={editQuantities.get(item.id) ?? 0}
+                                            onChange={(e) => {
+                                                const next = new Map(editQuantities);
+                                                next.set(item.id, Math.max(0, parseInt(e.target.value) || 0));
+                                                setEditQuantities(next);
+                                            }}
+                                            className="w-20 px-2 py-1 border border-slate-200 rounded-lg text-center"
+                                        />
+                                    </div>
+                                ))}
+                            </div>
+                        </div>
+
+                        <div className="grid grid-cols-2 gap-4">
+                            <div>
+                                <label className="block text-sm font-semibold text-slate-700 mb-2">Return Reason</label>
+                                <select
+                                    value={editReason}
+                                    onChange={(e) => setEditReason(e.target.value as ReturnReason)}
+                                    className="w-full h-11 px-3 rounded-xl border border-slate-200 bg-white text-slate-700 focus:outline-none focus:ring-2 focus:ring-teal-500/30"
+                                >
+                                    {Object.entries(RETURN_REASON_LABELS).map(([value, label]) => (
+                                        <option key={value} value={value}>{label}</option>
+                                    ))}
+                                </select>
+                            </div>
+                            <div>
+                                <label className="block text-sm font-semibold text-slate-700 mb-2">Notes (Optional)</label>
+                                <Input
+                                    value={editNotes}
+                                    onChange={(e) => setEditNotes(e.target.value)}
+                                    placeholder="Additional notes..."
+                                />
+                            </div>
+                        </div>
+
+                        <div className="bg-slate-50 p-4 rounded-xl flex justify-between items-center">
+                            <span className="text-slate-600">Refund Amount:</span>
+                            <span className="text-2xl font-bold text-teal-600">₹{calculateAmendedAmount().toLocaleString()}</span>
+                        </div>
+
+                        <div className="flex gap-3 pt-4 border-t border-slate-100">
+                            <Button variant="secondary" onClick={() => setEditingReturn(null)} className="flex-1">
+                                Cancel
+                            </Button>
+                            <Button
+                                onClick={handleAmendReturn}
+                                isLoading={isAmending}
+                                className="flex-1"
+                                leftIcon={<Edit2 size={18} />}
+                            >
+                                Save Changes
+                            </Button>
+                        </div>
+                    </div>
+                )}
+            </Modal>
+
             {/* Cancel Confirmation */}
             <ConfirmModal
                 isOpen={cancelConfirm.open}
@@ -553,6 +553,29 @@ export const Settings: React.FC = () => {
                                     </p>
                                 </div>
                             )}
+
+                            <div className="bg-slate-50 p-6 rounded-2xl border border-slate-100">
+                                <label className="block text-sm font-bold text-slate-700 mb-2">
+                                    Return Grace Period
+                                </label>
+                                <div className="flex items-center gap-4">
+                                    <Input
+                                        type="number"
+                                        min={0}
+                                        max={720}
+                                        value={settings.return_grace_hours}
+                                        onChange={(e) => {
+                                            const hours = parseInt(e.target.value);
+                                            updateSetting("return_grace_hours", Number.isNaN(hours) ? 24 : Math.max(0, hours));
+                                        }}
+                                        className="w-32"
+                                    />
+                                    <span className="text-slate-600 font-medium">hours</span>
+                                </div>
+                                <p className="text-sm text-slate-500 mt-2">
+                                    Sales returns can be cancelled or amended for this long after they are created.
+                                </p>
+                            </div>
                         </div>
                     </div>
                 )}
@@ -4,6 +4,7 @@ import {
   BarChart3,
   ChevronDown,
   ChevronUp,
+  ClipboardCheck,
   Edit2,
   Package,
   Plus,
@@ -20,15 +21,16 @@ import { v4 as uuidv4 } from "uuid";
 import { productService } from "../db/productService";
 import { stockAdjustmentService } from "../db/stockAdjustmentService";
 import { useDebounce, useProducts } from "../hooks";
-import { ADJUSTMENT_TYPE_LABELS, AdjustmentType, Product, ProductFormData, emptyProductForm } from "../types";
+import { ADJUSTMENT_TYPE_LABELS, AdjustmentType, Product, ProductFormData, StockReconciliation, emptyProductForm } from "../types";
 import { Badge, Button, Card, ConfirmModal, EmptyState, Input, Modal, useToast } from "./ui";
 
 interface StockManagementProps {
   canEdit?: boolean;
   canDelete?: boolean;
+  userName?: string;
 }
 
-export const StockManagement: React.FC<StockManagementProps> = ({ canEdit = false, canDelete = false }) => {
+export const StockManagement: React.FC<StockManagementProps> = ({ canEdit = false, canDelete = false, userName = "Staff" }) => {
   const { products, loading, refetch } = useProducts();
   const toast = useToast();
 
@@ -46,6 +48,11 @@ export const StockManagement: React.FC<StockManagementProps> = ({ canEdit = fals
   const [isAdjustModalOpen, setIsAdjustModalOpen] = useState(false);
   const [isAdjusting, setIsAdjusting] = useState(false);
 
+  // Stock ledger reconciliation
+  const [reconciliation, setReconciliation] = useState<StockReconciliation | null>(null);
+  const [isReconcileModalOpen, setIsReconcileModalOpen] = useState(false);
+  const [isReconciling, setIsReconciling] = useState(false);
+
   // Delete confirmation
   const [deleteConfirm, setDeleteConfirm] = useState<{ open: boolean; product: Product | null }>({
     open: false,
@@ -271,7 +278,7 @@ export const StockManagement: React.FC<StockManagementProps> = ({ canEdit = fals
         adjustType,
         delta,
         adjustReason.trim() || null,
-        'admin' // TODO: Get actual username from session
+        userName
       );
 
       toast.success(
@@ -289,6 +296,28 @@ export const StockManagement: React.FC<StockManagementProps> = ({ canEdit = fals
     }
   };
 
+  const runReconciliation = async (apply: boolean) => {
+    if (!ensureCanEdit()) return;
+    setIsReconciling(true);
+    try {
+      const result = await stockAdjustmentService.reconcile(apply, userName);
+      if (apply) {
+        toast.success("Stock Reconciled", result.message);
+        setIsReconcileModalOpen(false);
+        setReconciliation(null);
+      } else {
+        setReconciliation(result);
+        setIsReconcileModalOpen(true);
+      }
+    } catch (error) {
+      console.error(error);
+      const message = error instanceof Error ? error.message : String(error);
+      toast.error("Reconciliation Failed", message || "Could not check the stock ledger. Please try again.");
+    } finally {
+      setIsReconciling(false);
+    }
+  };
+
   const handleSubmit = async (e: React.FormEvent) => {
     e.preventDefault();
     if (!ensureCanEdit()) return;
@@ -463,6 +492,16 @@ export const StockManagement: React.FC<StockManagementProps> = ({ canEdit = fals
             >
               Filters
             </Button>
+            <Button
+              variant="secondary"
+              onClick={() => runReconciliation(false)}
+              leftIcon={<ClipboardCheck size={18} />}
+              isLoading={isReconciling && !isReconcileModalOpen}
+              disabled={!canEdit}
+              title={canEdit ? "Compare stock with the adjustment ledger" : "Admin only"}
+            >
+              Reconcile
+            </Button>
             <Button
               onClick={openAddModal}
               leftIcon={<Plus size={18} />}
@@ -858,6 +897,79 @@ export const StockManagement: React.FC<StockManagementProps> = ({ canEdit = fals
         </form>
       </Modal>
 
+      {/* Stock Ledger Reconciliation Modal */}
+      <Modal
+        isOpen={isReconcileModalOpen}
+        onClose={() => setIsReconcileModalOpen(false)}
+        title="Reconcile Stock Ledger"
+        size="xl"
+      >
+        <div className="space-y-4">
+          <div className="text-sm text-slate-600 bg-slate-50 p-3 rounded-lg border border-slate-100">
+            {reconciliation?.message}. Expected stock is opening stock plus every stock adjustment.
+          </div>
+
+          {reconciliation && reconciliation.discrepancies.length > 0 && (
+            <>
+              <div className="max-h-96 overflow-auto custom-scrollbar border border-slate-100 rounded-lg">
+                <table className="w-full text-left border-collapse text-sm">
+                  <thead className="bg-slate-50 sticky top-0">
+                    <tr>
+                      <th className="p-3 text-xs font-bold text-slate-500 uppercase">Product</th>
+                      <th className="p-3 text-xs font-bold text-slate-500 uppercase text-right">Opening</th>
+                      <th className="p-3 text-xs font-bold text-slate-500 uppercase text-right">Movements</th>
+                      <th className="p-3 text-xs font-bold text-slate-500 uppercase text-right">Expected</th>
+                      <th className="p-3 text-xs font-bold text-slate-500 uppercase text-right">In Stock</th>
+                      <th className="p-3 text-xs font-bold text-slate-500 uppercase text-right">Difference</th>
+                    </tr>
+                  </thead>
+                  <tbody className="divide-y divide-slate-100">
+                    {reconciliation.discrepancies.map((d) => (
+                      <tr key={d.product_id}>
+                        <td className="p-3">
+                          <div className="font-medium text-slate-800">{d.product_name}</div>
+                          {d.sku && <div className="text-xs text-slate-400">{d.sku}</div>}
+                          {!d.has_opening_stock && (
+                            <Badge variant="warning">No opening stock</Badge>
+                          )}
+                        </td>
+                        <td className="p-3 text-right text-slate-600">{d.opening_stock}</td>
+                        <td className="p-3 text-right text-slate-600">{d.movements}</td>
+                        <td className="p-3 text-right text-slate-600">{d.expected}</td>
+                        <td className="p-3 text-right font-semibold text-slate-800">{d.recorded}</td>
+                        <td className={`p-3 text-right font-semibold ${d.difference > 0 ? "text-emerald-600" : "text-red-600"}`}>
+                          {d.difference > 0 ? "+" : ""}{d.difference}
+                        </td>
+                      </tr>
+                    ))}
+                  </tbody>
+                </table>
+              </div>
+              <p className="text-xs text-slate-500">
+                Posting corrections logs an "Other" adjustment for each difference with an audit note.
+                Stock quantities are not changed.
+              </p>
+            </>
+          )}
+
+          <div className="flex gap-3 pt-4 border-t border-slate-100">
+            <Button type="button" variant="secondary" onClick={() => setIsReconcileModalOpen(false)} className="flex-1">
+              <X size={18} className="mr-2" /> Close
+            </Button>
+            {reconciliation && reconciliation.discrepancies.length > 0 && (
+              <Button
+                type="button"
+                onClick={() => runReconciliation(true)}
+                isLoading={isReconciling}
+                className="flex-1 bg-indigo-600 hover:bg-indigo-700"
+              >
+                <Save size={18} className="mr-2" /> Post Corrections
+              </Button>
+            )}
+          </div>
+        </div>
+      </Modal>
+
       {/* Delete Confirmation */}
       <ConfirmModal
         isOpen={deleteConfirm.open}
@@ -1,6 +1,7 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
 import { open, save } from "@tauri-apps/plugin-dialog";
-import { BackupFileInfo, BackupLog, BackupResult } from "../types";
+import { BackupBrowseFilter, BackupBrowsePage, BackupFileInfo, BackupLog, BackupResult, BackupVerification, InvoiceWithItems, MergeReport, MirrorStatus, OffsiteStatus, OperationProgress, PortableArchiveFormat, PortableArchiveManifest, Product, RemoteBackup, RestorePreview, RestoreResult, RetentionReport, RunningOperation, SalesReturnWithItems } from "../types";
 import { getDb } from "./index";
 import { isTauriRuntime } from "./runtime";
 import { settingsService } from "./settingsService";
@@ -8,6 +9,15 @@ import { settingsService } from "./settingsService";
 const LAST_BACKUP_KEY = "motormods_last_backup_date";
 const BACKUP_LOG_KEY = "motormods_backup_log_v1";
 
+// Data restores roll back on any failure and report it in the result rather than as an error
+const ensureRestored = (result: RestoreResult): RestoreResult => {
+  if (!result.success) {
+    const details = result.errors.length > 1 ? `\n${result.errors.slice(1).join("\n")}` : "";
+    throw new Error(`${result.message}${details}`);
+  }
+  return result;
+};
+
 // ============================================
 // LOCAL STORAGE HELPERS (for web dev mode)
 // ============================================
@@ -32,9 +42,10 @@ const saveBackupLog = (logs: BackupLog[]) => {
 
 export const backupService = {
   /**
-   * Triggers a database backup and logs the result
+   * Triggers a database backup and logs the result.
+   * Progress is reported through onOperationProgress under `operationId`.
    */
-  async triggerBackup(type: 'auto' | 'manual' = 'manual'): Promise<string> {
+  async triggerBackup(type: 'auto' | 'manual' = 'manual', createdBy?: string, full: boolean = false, operationId?: string): Promise<string> {
     const backupDate = new Date().toISOString();
     let backupFile = `motormods_backup_${backupDate.replace(/[:.]/g, '-')}.db`;
     let fileSize: number | null = null;
@@ -43,7 +54,12 @@ export const backupService = {
     try {
       if (isTauriRuntime()) {
         // The Rust command returns a BackupResult object with file info
-        const result = await invoke<BackupResult>("backup_database");
+        const result = await invoke<BackupResult>("backup_database", {
+          backupType: type,
+          createdBy: createdBy ?? null,
+          full,
+          operationId,
+        });
         backupFile = result.filename;
         fileSize = result.file_size;
       }
@@ -79,7 +95,7 @@ export const backupService = {
    * Restores the database from a backup in the backups directory.
    * Closes DB connection, replaces file, reopens connection.
    */
-  async restoreFromBackup(backupFilename: string): Promise<string> {
+  async restoreFromBackup(backupFilename: string, force: boolean = false, operationId?: string): Promise<string> {
     if (!isTauriRuntime()) {
       throw new Error("Restore is only available in the desktop application");
     }
@@ -91,20 +107,92 @@ export const backupService = {
     await closeDatabase();
 
     // Step 2: Have Rust replace the database file
-    const result = await invoke<string>("restore_database", { backupFilename });
-
-    // Step 3: Reopen the database connection by calling getDb
+    // Step 3: Reopen the database connection by calling getDb, also after a failed or cancelled restore
     // This will create a fresh connection to the restored database
-    await getDb();
+    try {
+      return await invoke<string>("restore_database", { backupFilename, force, operationId });
+    } finally {
+      await getDb();
+    }
+  },
 
-    return result;
+  /**
+   * Compares a backup with the live database without changing anything.
+   * Shows how many rows each table would lose, regain or change on restore.
+   */
+  async previewRestore(backupPath: string, passphrase?: string): Promise<RestorePreview> {
+    if (!isTauriRuntime()) {
+      throw new Error("Restore preview is only available in the desktop application");
+    }
+
+    return await invoke<RestorePreview>("preview_restore", { backupPath, passphrase });
+  },
+
+  /**
+   * Restores only the given tables from a backup, leaving the other tables as they are.
+   */
+  async restoreTables(backupPath: string, tables: string[], force: boolean = false, passphrase?: string, operationId?: string): Promise<RestoreResult> {
+    if (!isTauriRuntime()) {
+      throw new Error("Restore is only available in the desktop application");
+    }
+
+    return ensureRestored(await invoke<RestoreResult>("restore_tables_from_backup", { backupPath, tables, force, passphrase, operationId }));
+  },
+
+  /**
+   * Recovers individual records by id from a backup. Invoices and returns come back with
+   * their items, and referenced products that were deleted are recovered too.
+   */
+  async recoverRecords(backupPath: string, table: string, ids: string[], force: boolean = false, passphrase?: string, operationId?: string): Promise<RestoreResult> {
+    if (!isTauriRuntime()) {
+      throw new Error("Recovery is only available in the desktop application");
+    }
+
+    return ensureRestored(await invoke<RestoreResult>("recover_records", { backupPath, table, ids, force, passphrase, operationId }));
+  },
+
+  /**
+   * Restores an incremental backup by replaying its full base backup and the
+   * increments up to it. The data is imported into the open database, so no reconnect is needed.
+   */
+  async restoreBackupChain(backupFilename: string, force: boolean = false, operationId?: string): Promise<RestoreResult> {
+    if (!isTauriRuntime()) {
+      throw new Error("Restore is only available in the desktop application");
+    }
+
+    return ensureRestored(await invoke<RestoreResult>("restore_backup_chain", { backupFilename, force, operationId }));
+  },
+
+  /**
+   * Reports the backup, restore or maintenance operation currently running, if any.
+   * Only one runs at a time; others fail with an "operation in progress" error.
+   */
+  async getRunningOperation(): Promise<RunningOperation | null> {
+    if (!isTauriRuntime()) return null;
+    return await invoke<RunningOperation | null>("get_running_operation");
+  },
+
+  /**
+   * Asks a running backup or restore to stop. A cancelled restore leaves the data unchanged.
+   */
+  async cancelOperation(operationId: string): Promise<boolean> {
+    if (!isTauriRuntime()) return false;
+    return await invoke<boolean>("cancel_operation", { operationId });
+  },
+
+  /**
+   * Subscribes to progress events of backups and restores. Returns the unsubscribe function.
+   */
+  async onOperationProgress(handler: (progress: OperationProgress) => void): Promise<UnlistenFn> {
+    if (!isTauriRuntime()) return () => {};
+    return await listen<OperationProgress>("operation-progress", (event) => handler(event.payload));
   },
 
   /**
    * Imports and restores from an external backup file path.
    * Closes DB connection, replaces file, reopens connection.
    */
-  async importBackup(sourcePath: string): Promise<string> {
+  async importBackup(sourcePath: string, force: boolean = false, passphrase?: string, operationId?: string): Promise<string> {
     if (!isTauriRuntime()) {
       throw new Error("Import is only available in the desktop application");
     }
@@ -116,12 +204,198 @@ export const backupService = {
     await closeDatabase();
 
     // Step 2: Have Rust replace the database file with the external backup
-    const result = await invoke<string>("import_backup", { sourcePath });
+    // Step 3: Reopen the database connection, also after a failed or cancelled import
+    try {
+      return await invoke<string>("import_backup", { sourcePath, force, passphrase, operationId });
+    } finally {
+      await getDb();
+    }
+  },
 
-    // Step 3: Reopen the database connection
-    await getDb();
+  /**
+   * Exports every data table to a zip of CSV or JSON files for spreadsheets and scripts.
+   * Returns null if the user cancels the save dialog.
+   */
+  async exportPortableArchive(format: PortableArchiveFormat, operationId?: string): Promise<PortableArchiveManifest | null> {
+    if (!isTauriRuntime()) {
+      throw new Error("Export is only available in the desktop application");
+    }
 
-    return result;
+    const date = new Date().toISOString().slice(0, 10);
+    const destination = await save({
+      defaultPath: `motormods_data_${date}_${format}.zip`,
+      filters: [{
+        name: "Zip Archive",
+        extensions: ["zip"]
+      }],
+      title: "Export Data To"
+    });
+
+    if (!destination) {
+      return null;
+    }
+
+    return await invoke<PortableArchiveManifest>("export_portable_archive", {
+      destinationPath: destination,
+      format,
+      operationId
+    });
+  },
+
+  /**
+   * Opens a file picker to select a portable data archive (.zip)
+   */
+  async selectPortableArchive(): Promise<string | null> {
+    if (!isTauriRuntime()) {
+      throw new Error("File picker is only available in the desktop application");
+    }
+
+    const selected = await open({
+      multiple: false,
+      directory: false,
+      filters: [{
+        name: "Zip Archive",
+        extensions: ["zip"]
+      }],
+      title: "Select Data Archive to Import"
+    });
+
+    return selected && typeof selected === 'string' ? selected : null;
+  },
+
+  /**
+   * Replaces the tables in a portable archive with its rows. Nothing changes unless every
+   * value and reference in the archive is valid. A safety backup is created first.
+   */
+  async importPortableArchive(sourcePath: string, operationId?: string): Promise<RestoreResult> {
+    if (!isTauriRuntime()) {
+      throw new Error("Import is only available in the desktop application");
+    }
+
+    return ensureRestored(await invoke<RestoreResult>("import_portable_archive", { sourcePath, operationId }));
+  },
+
+  /**
+   * Merges invoices, returns and stock adjustments from another terminal's database into
+   * this one. With dryRun nothing is written and the report shows what would be merged.
+   */
+  async mergeImport(sourcePath: string, dryRun: boolean, force: boolean = false, passphrase?: string, operationId?: string): Promise<MergeReport> {
+    if (!isTauriRuntime()) {
+      throw new Error("Merge is only available in the desktop application");
+    }
+
+    return await invoke<MergeReport>("merge_import", { sourcePath, dryRun, force, passphrase, operationId });
+  },
+
+  /**
+   * Checks a backup's integrity, schema and checksum without restoring it
+   */
+  async verifyBackup(backupPath: string, passphrase?: string): Promise<BackupVerification> {
+    if (!isTauriRuntime()) {
+      throw new Error("Verification is only available in the desktop application");
+    }
+
+    return await invoke<BackupVerification>("verify_backup", { backupPath, passphrase });
+  },
+
+  /**
+   * Reports each mirror destination: whether it is reachable, its free space and the last mirror run
+   */
+  async getMirrorStatus(): Promise<MirrorStatus[]> {
+    if (!isTauriRuntime()) return [];
+    return await invoke<MirrorStatus[]>("get_mirror_status");
+  },
+
+  /**
+   * Lists invoices (with items) stored in a backup, without restoring it
+   */
+  async browseBackupInvoices(backupPath: string, filter: BackupBrowseFilter = {}): Promise<BackupBrowsePage<InvoiceWithItems>> {
+    return await invoke<BackupBrowsePage<InvoiceWithItems>>("browse_backup_invoices", { backupPath, filter });
+  },
+
+  /**
+   * Lists products stored in a backup, without restoring it
+   */
+  async browseBackupProducts(backupPath: string, filter: BackupBrowseFilter = {}): Promise<BackupBrowsePage<Product>> {
+    return await invoke<BackupBrowsePage<Product>>("browse_backup_products", { backupPath, filter });
+  },
+
+  /**
+   * Lists sales returns (with items) stored in a backup, without restoring it
+   */
+  async browseBackupReturns(backupPath: string, filter: BackupBrowseFilter = {}): Promise<BackupBrowsePage<SalesReturnWithItems>> {
+    return await invoke<BackupBrowsePage<SalesReturnWithItems>>("browse_backup_returns", { backupPath, filter });
+  },
+
+  /**
+   * Frees the backup kept open by the browser
+   */
+  async closeBackupBrowser(): Promise<void> {
+    if (!isTauriRuntime()) return;
+    await invoke("close_backup_browser");
+  },
+
+  /**
+   * Whether offsite backup is configured, and the uploads still queued (e.g. while offline)
+   */
+  async getOffsiteStatus(): Promise<OffsiteStatus> {
+    if (!isTauriRuntime()) return { configured: false, has_secret_key: false, has_passphrase: false, pending: [] };
+    return await invoke<OffsiteStatus>("get_offsite_status");
+  },
+
+  /**
+   * Saves the offsite secret key and encryption passphrase. They are kept outside the
+   * database so backups and exports never contain them. Omitted values are left unchanged.
+   */
+  async setOffsiteCredentials(secretKey?: string, passphrase?: string): Promise<void> {
+    if (!isTauriRuntime()) {
+      throw new Error("Offsite backups are only available in the desktop application");
+    }
+    await invoke("set_offsite_credentials", { secretKey, passphrase });
+  },
+
+  /**
+   * Lists the backups stored in the offsite bucket, newest first
+   */
+  async listOffsiteBackups(): Promise<RemoteBackup[]> {
+    if (!isTauriRuntime()) return [];
+    return await invoke<RemoteBackup[]>("list_offsite_backups");
+  },
+
+  /**
+   * Downloads an offsite backup (and the rest of its incremental chain) into the backups folder.
+   * It then shows up in listBackupFiles and restores like any local backup. Returns the local path.
+   */
+  async downloadOffsiteBackup(filename: string): Promise<string> {
+    if (!isTauriRuntime()) {
+      throw new Error("Offsite backups are only available in the desktop application");
+    }
+    return await invoke<string>("download_offsite_backup", { filename });
+  },
+
+  /**
+   * Uploads queued backups now. Returns how many were uploaded.
+   */
+  async retryOffsiteUploads(): Promise<number> {
+    if (!isTauriRuntime()) return 0;
+    return await invoke<number>("retry_offsite_uploads");
+  },
+
+  /**
+   * Opens a folder picker to choose a mirror destination (USB drive, second disk, network folder)
+   */
+  async selectMirrorFolder(): Promise<string | null> {
+    if (!isTauriRuntime()) {
+      throw new Error("Folder picker is only available in the desktop application");
+    }
+
+    const selected = await open({
+      multiple: false,
+      directory: true,
+      title: "Select Folder to Mirror Backups To"
+    });
+
+    return selected && typeof selected === 'string' ? selected : null;
   },
 
   /**
@@ -137,7 +411,7 @@ export const backupService = {
       directory: false,
       filters: [{
         name: "Database Backup",
-        extensions: ["db"]
+        extensions: ["db", "zst", "gz", "enc"]
       }],
       title: "Select Backup File to Import"
     });
@@ -152,16 +426,22 @@ export const backupService = {
   /**
    * Exports a backup to a user-selected location
    */
-  async exportBackup(backupFilename: string): Promise<string | null> {
+  async exportBackup(backupFilename: string, passphrase?: string): Promise<string | null> {
     if (!isTauriRuntime()) {
       throw new Error("Export is only available in the desktop application");
     }
 
+    // Compressed backups keep their extension so they can be imported again
+    const extension = passphrase ? "enc"
+      : backupFilename.endsWith(".db.zst") ? "zst"
+        : backupFilename.endsWith(".db.gz") ? "gz"
+          : "db";
+
     const destination = await save({
-      defaultPath: backupFilename,
+      defaultPath: passphrase ? `${backupFilename}.enc` : backupFilename,
       filters: [{
         name: "Database Backup",
-        extensions: ["db"]
+        extensions: [extension]
       }],
       title: "Export Backup To"
     });
@@ -172,7 +452,8 @@ export const backupService = {
 
     const result = await invoke<string>("export_backup", {
       backupFilename,
-      destinationPath: destination
+      destinationPath: destination,
+      passphrase
     });
 
     return result;
@@ -251,9 +532,14 @@ export const backupService = {
   },
 
   /**
-   * Checks if auto backup should run and triggers it
+   * Checks if auto backup should run and triggers it.
+   * In the desktop app the Rust scheduler owns auto-backups, so this only applies to web mode.
    */
   async checkAndTriggerAutoBackup(): Promise<void> {
+    if (isTauriRuntime()) {
+      return;
+    }
+
     const today = new Date().toISOString().split("T")[0];
     const lastBackup = localStorage.getItem(LAST_BACKUP_KEY);
 
@@ -301,25 +587,29 @@ export const backupService = {
         [cutoffIso]
       );
 
-      // Also clean up actual backup files
-      const backupFiles = await this.listBackupFiles();
-      for (const file of backupFiles) {
-        if (file.modified_at < cutoffIso) {
-          try {
-            await this.deleteBackup(file.filename);
-            console.log(`Deleted old backup: ${file.filename}`);
-          } catch (e) {
-            console.error(`Failed to delete backup ${file.filename}:`, e);
-          }
-        }
+      // Backup files follow the daily/weekly/monthly retention policy in Rust
+      const report = await this.applyRetention(false);
+      for (const failure of report.failed) {
+        console.error(`Failed to delete backup ${failure}`);
       }
 
-      console.log(`Cleaned up backups older than ${retentionDays} days`);
+      console.log(`Retention removed ${report.deleted.length} backups, kept ${report.kept.length}`);
     } catch (error) {
       console.error("Failed to cleanup old backups:", error);
     }
   },
 
+  /**
+   * Applies the backup retention policy. With dryRun, only reports what would be deleted.
+   */
+  async applyRetention(dryRun: boolean): Promise<RetentionReport> {
+    if (!isTauriRuntime()) {
+      return { dry_run: dryRun, kept: [], deleted: [], failed: [], protected_newest: null };
+    }
+
+    return await invoke<RetentionReport>("apply_backup_retention", { dryRun });
+  },
+
   /**
    * Gets information about the last backup
    */
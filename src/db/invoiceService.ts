@@ -1,4 +1,6 @@
-import { Invoice, InvoiceItem } from "../types";
+import { invoke } from "@tauri-apps/api/core";
+import { CreateInvoiceResult, Invoice, InvoiceItem, StockShortage } from "../types";
+import { syncStockQuantityToFirestore } from "./firestoreSync";
 import { getDb } from "./index";
 import { productService } from "./productService";
 import { isTauriRuntime } from "./runtime";
@@ -18,6 +20,14 @@ const loadReturnsForDeduction = (): Array<{ return_date: string; total_amount: n
   }
 };
 
+/** A sale refused because some lines ask for more than the stock left */
+export class InsufficientStockError extends Error {
+  constructor(message: string, public readonly shortages: StockShortage[]) {
+    super(message);
+    this.name = "InsufficientStockError";
+  }
+}
+
 const INVOICES_KEY = "motormods_invoices_v1";
 const INVOICE_ITEMS_KEY = "motormods_invoice_items_v1";
 
@@ -111,50 +121,21 @@ export const invoiceService = {
       return;
     }
 
-    const db = await getDb();
-
-    // Stock validation (desktop)
-    const productCosts = new Map<string, number>();
-    for (const item of items) {
-      const rows = await db.select<{ quantity: number; name: string; purchase_price: number }[]>(
-        "SELECT quantity, name, purchase_price FROM products WHERE id = $1",
-        [item.product_id]
-      );
-      const available = rows[0]?.quantity ?? 0;
-      const name = rows[0]?.name ?? item.product_id;
-      const purchasePrice = rows[0]?.purchase_price ?? 0;
-      productCosts.set(item.product_id, purchasePrice);
-      if (available < item.quantity) {
-        throw new Error(`Insufficient stock for ${name}. Available ${available}, requested ${item.quantity}.`);
-      }
+    // The sale, its stock checks and stock movements run in one transaction in the backend
+    const result = await invoke<CreateInvoiceResult>("create_invoice", {
+      invoice: {
+        ...invoice,
+        customer_phone: invoice.customer_phone ?? null,
+        discount_amount: invoice.discount_amount ?? 0,
+      },
+      items: items.map(({ id, product_id, quantity, price }) => ({ id, product_id, quantity, price })),
+    });
+    if (!result.success) {
+      throw new InsufficientStockError(result.message, result.shortages);
     }
 
-    // Start a transaction if possible, but tauri-plugin-sql handles simple queries.
-    // We'll run them sequentially for now.
-
-    const createdAt = invoice.created_at || new Date().toISOString();
-    await db.execute(
-      "INSERT INTO invoices (id, customer_name, customer_phone, discount_amount, total_amount, created_at) VALUES ($1, $2, $3, $4, $5, $6)",
-      [invoice.id, invoice.customer_name, invoice.customer_phone ?? null, invoice.discount_amount ?? 0, invoice.total_amount, createdAt]
-    );
-
-    for (const item of items) {
-      const costPrice = item.cost_price ?? productCosts.get(item.product_id) ?? 0;
-      await db.execute(
-        "INSERT INTO invoice_items (id, invoice_id, product_id, quantity, price, cost_price) VALUES ($1, $2, $3, $4, $5, $6)",
-        [item.id, invoice.id, item.product_id, item.quantity, item.price, costPrice]
-      );
-
-      // Deduct stock and log adjustment
-      await productService.updateQuantity(item.product_id, -item.quantity);
-      await productService.updateLastSaleDate(item.product_id);
-      await stockAdjustmentService.create(
-        item.product_id,
-        'sale',
-        -item.quantity,
-        `Invoice ${invoice.id.slice(0, 8).toUpperCase()}`,
-        'system'
-      );
+    for (const level of result.stock) {
+      syncStockQuantityToFirestore(level.product_id, level.quantity).catch(console.error);
     }
   },
 
@@ -1,5 +1,7 @@
+import { invoke } from "@tauri-apps/api/core";
 import { v4 as uuidv4 } from "uuid";
-import { ReturnItem, ReturnReason, SalesReturn, SalesReturnWithItems } from "../types";
+import { CreateReturnResult, ReturnChangeResult, ReturnItem, ReturnReason, ReturnShortage, SalesReturn, SalesReturnWithItems } from "../types";
+import { syncStockQuantityToFirestore } from "./firestoreSync";
 import { getDb } from "./index";
 import { productService } from "./productService";
 import { isTauriRuntime } from "./runtime";
@@ -8,6 +10,22 @@ import { stockAdjustmentService } from "./stockAdjustmentService";
 const RETURNS_KEY = "motormods_sales_returns_v1";
 const RETURN_ITEMS_KEY = "motormods_return_items_v1";
 
+/** A return refused because some lines exceed what the invoice sold minus earlier returns */
+export class ReturnQuantityError extends Error {
+    constructor(message: string, public readonly shortages: ReturnShortage[]) {
+        super(message);
+        this.name = "ReturnQuantityError";
+    }
+}
+
+/** A cancellation or amendment refused for quantities or stock; details in `result` */
+export class ReturnChangeError extends Error {
+    constructor(message: string, public readonly result: ReturnChangeResult) {
+        super(message);
+        this.name = "ReturnChangeError";
+    }
+}
+
 interface CreateReturnData {
     invoiceId: string;
     reason: ReturnReason;
@@ -77,6 +95,31 @@ export const returnsService = {
      * Create a new sales return with stock reversal
      */
     async create(data: CreateReturnData): Promise<SalesReturn> {
+        if (isTauriRuntime()) {
+            // Validation, numbering, stock and adjustments run in one transaction in the backend
+            const result = await invoke<CreateReturnResult>("create_sales_return", {
+                salesReturn: {
+                    invoice_id: data.invoiceId,
+                    reason: data.reason,
+                    notes: data.notes,
+                    items: data.items.map(item => ({
+                        product_id: item.productId,
+                        quantity: item.quantity,
+                        rate: item.rate,
+                    })),
+                    created_by: data.createdBy ?? 'system',
+                },
+            });
+            if (!result.success || !result.sales_return) {
+                throw new ReturnQuantityError(result.message, result.shortages);
+            }
+
+            for (const level of result.stock) {
+                syncStockQuantityToFirestore(level.product_id, level.quantity).catch(console.error);
+            }
+            return result.sales_return;
+        }
+
         const returnNo = await this.generateReturnNumber();
         const totalAmount = data.items.reduce((sum, item) => sum + (item.quantity * item.rate), 0);
 
@@ -102,66 +145,21 @@ export const returnsService = {
             line_total: item.quantity * item.rate,
         }));
 
-        if (!isTauriRuntime()) {
-            // Save return
-            const returns = loadReturns();
-            returns.push(salesReturn);
-            saveReturns(returns);
+        // Save return
+        const returns = loadReturns();
+        returns.push(salesReturn);
+        saveReturns(returns);
 
-            // Save return items
-            const allItems = loadReturnItems();
-            allItems.push(...returnItems);
-            saveReturnItems(allItems);
-
-            // Increase stock for each returned item
-            for (const item of data.items) {
-                await productService.updateQuantity(item.productId, item.quantity);
-                await stockAdjustmentService.create(
-                    item.productId,
-                    'return',
-                    item.quantity,
-                    `Return ${returnNo}: ${data.reason}`,
-                    data.createdBy ?? 'system'
-                );
-            }
-
-            return salesReturn;
-        }
+        // Save return items
+        const allItems = loadReturnItems();
+        allItems.push(...returnItems);
+        saveReturnItems(allItems);
 
-        const db = await getDb();
-
-        // Insert return header
-        await db.execute(
-            `INSERT INTO sales_returns (id, return_no, invoice_id, return_date, reason, total_amount, notes, status, created_at, updated_at)
-       VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)`,
-            [
-                salesReturn.id,
-                salesReturn.return_no,
-                salesReturn.invoice_id,
-                salesReturn.return_date,
-                salesReturn.reason,
-                salesReturn.total_amount,
-                salesReturn.notes,
-                salesReturn.status,
-                salesReturn.created_at,
-                salesReturn.updated_at,
-            ]
-        );
-
-        // Insert return items and reverse stock
-        for (const item of returnItems) {
-            await db.execute(
-                `INSERT INTO return_items (id, return_id, product_id, quantity, rate, line_total)
-         VALUES ($1, $2, $3, $4, $5, $6)`,
-                [item.id, item.return_id, item.product_id, item.quantity, item.rate, item.line_total]
-            );
-
-            // Increase stock
-            await productService.updateQuantity(item.product_id, item.quantity);
-
-            // Log adjustment
+        // Increase stock for each returned item
+        for (const item of data.items) {
+            await productService.updateQuantity(item.productId, item.quantity);
             await stockAdjustmentService.create(
-                item.product_id,
+                item.productId,
                 'return',
                 item.quantity,
                 `Return ${returnNo}: ${data.reason}`,
@@ -374,41 +372,34 @@ export const returnsService = {
      * Cancel a return (reverses stock changes)
      */
     async cancel(id: string, cancelledBy: string = 'system'): Promise<boolean> {
-        const salesReturn = await this.getById(id);
-        if (!salesReturn || salesReturn.status === 'cancelled') {
-            return false;
-        }
-
-        if (!isTauriRuntime()) {
-            const returns = loadReturns();
-            const idx = returns.findIndex(r => r.id === id);
-            if (idx >= 0) {
-                returns[idx].status = 'cancelled';
-                returns[idx].updated_at = new Date().toISOString();
-                saveReturns(returns);
+        if (isTauriRuntime()) {
+            // Grace window, stock checks and compensating adjustments are enforced in one transaction
+            const result = await invoke<ReturnChangeResult>("cancel_sales_return", {
+                returnId: id,
+                cancelledBy,
+            });
+            if (!result.success) {
+                throw new ReturnChangeError(result.message, result);
             }
 
-            // Reverse stock changes
-            for (const item of salesReturn.items) {
-                await productService.updateQuantity(item.product_id, -item.quantity);
-                await stockAdjustmentService.create(
-                    item.product_id,
-                    'manual_deduction',
-                    -item.quantity,
-                    `Cancelled return ${salesReturn.return_no}`,
-                    cancelledBy
-                );
+            for (const level of result.stock) {
+                syncStockQuantityToFirestore(level.product_id, level.quantity).catch(console.error);
             }
-
             return true;
         }
 
-        const db = await getDb();
+        const salesReturn = await this.getById(id);
+        if (!salesReturn || salesReturn.status === 'cancelled') {
+            return false;
+        }
 
-        await db.execute(
-            "UPDATE sales_returns SET status = 'cancelled', updated_at = CURRENT_TIMESTAMP WHERE id = $1",
-            [id]
-        );
+        const returns = loadReturns();
+        const idx = returns.findIndex(r => r.id === id);
+        if (idx >= 0) {
+            returns[idx].status = 'cancelled';
+            returns[idx].updated_at = new Date().toISOString();
+            saveReturns(returns);
+        }
 
         // Reverse stock changes
         for (const item of salesReturn.items) {
@@ -425,6 +416,37 @@ export const returnsService = {
         return true;
     },
 
+    /**
+     * Replace the reason, notes and items of a return within the grace window (desktop only)
+     */
+    async amend(id: string, data: Omit<CreateReturnData, "invoiceId" | "createdBy">, amendedBy: string = 'system'): Promise<SalesReturn> {
+        if (!isTauriRuntime()) {
+            throw new Error("Amending returns is only available in the desktop app");
+        }
+
+        const result = await invoke<ReturnChangeResult>("amend_sales_return", {
+            amendment: {
+                return_id: id,
+                reason: data.reason,
+                notes: data.notes,
+                items: data.items.map(item => ({
+                    product_id: item.productId,
+                    quantity: item.quantity,
+                    rate: item.rate,
+                })),
+                amended_by: amendedBy,
+            },
+        });
+        if (!result.success || !result.sales_return) {
+            throw new ReturnChangeError(result.message, result);
+        }
+
+        for (const level of result.stock) {
+            syncStockQuantityToFirestore(level.product_id, level.quantity).catch(console.error);
+        }
+        return result.sales_return;
+    },
+
     /**
      * Get set of invoice IDs that have been returned
      */
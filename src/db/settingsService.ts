@@ -11,7 +11,26 @@ const defaultSettings: AppSettings = {
     non_moving_threshold_days: 120,
     auto_backup_enabled: true,
     auto_backup_time: '23:00',
+    auto_backup_frequency: 'daily',
+    auto_backup_day: 0,
     backup_retention_days: 30,
+    backup_keep_daily: 7,
+    backup_keep_weekly: 4,
+    backup_keep_monthly: 12,
+    safety_backup_keep_count: 3,
+    safety_backup_max_age_days: 30,
+    backup_compression: 'none',
+    backup_compression_level: 3,
+    backup_mode: 'full',
+    backup_full_interval_days: 7,
+    backup_mirror_destinations: '[]',
+    offsite_enabled: false,
+    offsite_endpoint: '',
+    offsite_region: 'us-east-1',
+    offsite_bucket: '',
+    offsite_prefix: 'motormods',
+    offsite_access_key: '',
+    return_grace_hours: 24,
     // Store Details
     store_name: 'MotorMods',
     store_email: '',
@@ -1,5 +1,6 @@
+import { invoke } from "@tauri-apps/api/core";
 import { v4 as uuidv4 } from "uuid";
-import { AdjustmentType, StockAdjustment } from "../types";
+import { AdjustmentType, StockAdjustment, StockReconciliation } from "../types";
 import { getDb } from "./index";
 import { isTauriRuntime } from "./runtime";
 
@@ -254,4 +255,16 @@ export const stockAdjustmentService = {
 
         return summary;
     },
+
+    /**
+     * Compare every product's stock with opening stock plus all adjustments.
+     * With apply, posts an 'other' adjustment per discrepancy so the ledger matches the stock.
+     */
+    async reconcile(apply: boolean = false, createdBy: string = 'system'): Promise<StockReconciliation> {
+        if (!isTauriRuntime()) {
+            throw new Error("Stock reconciliation is only available in the desktop application");
+        }
+
+        return await invoke<StockReconciliation>("reconcile_stock", { apply, createdBy });
+    },
 };
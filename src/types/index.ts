@@ -53,6 +53,28 @@ export interface InvoiceWithItems extends Invoice {
   items: InvoiceItem[];
 }
 
+// A line of a refused sale (create_invoice)
+export interface StockShortage {
+  item_id: string;
+  product_id: string;
+  product_name: string | null; // null if the product does not exist
+  requested: number;
+  available: number; // left for this line after earlier lines of the same product
+}
+
+export interface StockLevel {
+  product_id: string;
+  quantity: number;
+}
+
+export interface CreateInvoiceResult {
+  success: boolean;
+  message: string;
+  invoice_id: string;
+  shortages: StockShortage[];
+  stock: StockLevel[]; // new stock of every product sold
+}
+
 // ============================================
 // SALES RETURN TYPES
 // ============================================
@@ -68,6 +90,10 @@ export interface SalesReturn {
   status: 'completed' | 'cancelled';
   created_at: string;
   updated_at: string;
+  cancelled_by?: string | null;
+  cancelled_at?: string | null;
+  amended_by?: string | null;
+  amended_at?: string | null;
   // Joined fields
   customer_name?: string;
   original_invoice_total?: number;
@@ -94,6 +120,42 @@ export type ReturnReason =
   | 'defective'
   | 'other';
 
+// A return line asking for more than can still be returned (create_sales_return)
+export interface ReturnShortage {
+  product_id: string;
+  product_name: string | null;
+  requested: number;
+  sold: number; // 0 if the invoice did not sell this product
+  already_returned: number; // by earlier returns that are not cancelled
+  available: number;
+}
+
+export interface CreateReturnResult {
+  success: boolean;
+  message: string;
+  sales_return: SalesReturn | null;
+  shortages: ReturnShortage[];
+  stock: StockLevel[]; // new stock of every product returned
+}
+
+// A product without the stock a return cancellation or amendment would take away
+export interface StockConflict {
+  product_id: string;
+  product_name: string | null;
+  in_stock: number;
+  to_remove: number;
+}
+
+// cancel_sales_return / amend_sales_return
+export interface ReturnChangeResult {
+  success: boolean;
+  message: string;
+  sales_return: SalesReturn | null;
+  shortages: ReturnShortage[];
+  stock_conflicts: StockConflict[];
+  stock: StockLevel[];
+}
+
 export const RETURN_REASON_LABELS: Record<ReturnReason, string> = {
   damage: 'Damage',
   wrong_part: 'Wrong Part',
@@ -113,10 +175,32 @@ export interface StockAdjustment {
   adjustment_type: AdjustmentType;
   quantity: number;
   notes: string | null;
+  reference_id?: string | null; // invoice or sales return that caused the movement
   created_by: string;
   created_at: string;
 }
 
+// reconcile_stock
+export interface StockDiscrepancy {
+  product_id: string;
+  product_name: string;
+  sku: string | null;
+  opening_stock: number;
+  movements: number;
+  expected: number;
+  recorded: number;
+  difference: number;
+  has_opening_stock: boolean;
+}
+
+export interface StockReconciliation {
+  applied: boolean;
+  message: string;
+  products_checked: number;
+  discrepancies: StockDiscrepancy[];
+  adjustments_posted: number;
+}
+
 export type AdjustmentType =
   | 'opening_stock'
   | 'manual_add'
@@ -153,12 +237,208 @@ export interface BackupLog {
   created_at: string;
 }
 
+// Manifest stored alongside each backup file
+export interface BackupManifest {
+  format_version: number;
+  filename: string;
+  sha256: string;
+  file_size: number;
+  compression: BackupCompression;
+  created_at: string;
+  backup_type: 'auto' | 'manual' | 'safety' | 'unknown';
+  created_by: string | null;
+  app_version: string | null;
+  schema_version: number;
+  table_counts: Record<string, number>;
+  kind: 'full' | 'incremental';
+  base_file: string | null; // full backup an incremental chain starts from
+  parent_file: string | null; // previous backup in the chain
+  stale?: boolean; // the file's size no longer matches the manifest
+}
+
+// Progress of a long-running backup or restore (operation-progress event)
+export interface OperationProgress {
+  operation_id: string;
+  kind: 'backup' | 'restore';
+  phase: 'preparing' | 'copying' | 'mirroring' | 'exporting' | 'importing' | 'finished';
+  table: string | null;
+  done: number;
+  total: number;
+}
+
+// Backup, restore or maintenance operation holding the operation lock (get_running_operation)
+export interface RunningOperation {
+  activity: string;
+  started_at: string;
+  file: string | null; // backup being read, which cannot be deleted meanwhile
+}
+
+// Schema version of the live database (get_schema_version)
+export interface SchemaVersion {
+  version: number;
+  latest: number; // version this app migrates to
+}
+
+// A folder every backup is copied to (get_mirror_status)
+export interface MirrorStatus {
+  destination: string;
+  available: boolean; // false when the drive or network share is not mounted
+  free_bytes: number | null;
+  last_attempt_at: string | null;
+  last_success_at: string | null;
+  last_file: string | null;
+  last_error: string | null;
+}
+
+// Backup stored in the offsite S3-compatible bucket (list_offsite_backups)
+export interface RemoteBackup {
+  filename: string;
+  key: string;
+  size: number;
+  last_modified: string;
+  has_manifest: boolean;
+  downloaded: boolean; // already in the local backups folder
+}
+
+// Backup waiting to be uploaded offsite
+export interface PendingUpload {
+  filename: string;
+  queued_at: string;
+  attempts: number;
+  last_attempt_at: string | null;
+  last_error: string | null;
+}
+
+export interface OffsiteStatus {
+  configured: boolean;
+  has_secret_key: boolean;
+  has_passphrase: boolean; // uploads are encrypted with it; nothing is uploaded without one
+  pending: PendingUpload[];
+}
+
+// Filter for the read-only backup browser (browse_backup_* commands)
+export interface BackupBrowseFilter {
+  search?: string; // ids, names, phone numbers, SKUs, barcodes
+  date_from?: string; // YYYY-MM-DD, inclusive
+  date_to?: string;
+  category?: string; // products only
+  page?: number; // 1-based
+  page_size?: number;
+}
+
+export interface BackupBrowsePage<T> {
+  rows: T[];
+  total: number;
+  page: number;
+  page_size: number;
+}
+
+// Spreadsheet-friendly zip of every data table (export_portable_archive)
+export type PortableArchiveFormat = 'csv' | 'json';
+
+export interface PortableArchiveColumn {
+  name: string;
+  type: string; // declared SQLite type
+  not_null: boolean;
+  primary_key: boolean;
+}
+
+export interface PortableArchiveTable {
+  name: string;
+  file: string; // path inside the zip
+  rows: number;
+  columns: PortableArchiveColumn[];
+  foreign_keys: { column: string; references_table: string; references_column: string }[];
+}
+
+export interface PortableArchiveManifest {
+  format_version: number;
+  app_version: string;
+  created_at: string;
+  format: PortableArchiveFormat;
+  tables: PortableArchiveTable[];
+}
+
+// Result of merging another terminal's database (merge_import)
+export interface MergeTableResult {
+  table: string;
+  imported: number;
+  already_present: number;
+  conflicts: number;
+}
+
+export interface MergeConflict {
+  table: string;
+  id: string;
+  reason: string;
+  columns: string[]; // columns that differ between the two databases
+}
+
+export interface StockChange {
+  product_id: string;
+  product_name: string | null;
+  delta: number;
+}
+
+export interface MergeReport {
+  success: boolean;
+  dry_run: boolean; // nothing was written
+  message: string;
+  safety_backup: string | null;
+  tables: MergeTableResult[];
+  products_added: string[];
+  stock_changes: StockChange[];
+  conflicts: MergeConflict[];
+}
+
+// Per-table outcome of a data restore
+export interface TableRestoreResult {
+  table: string;
+  in_backup: boolean;
+  imported: number;
+  failed: number;
+  skipped: number;
+}
+
+// Response from Rust data restore commands.
+// A failed restore is rolled back entirely and reports success: false.
+export interface RestoreResult {
+  success: boolean;
+  message: string;
+  records_imported: number;
+  safety_backup: string;
+  tables: TableRestoreResult[];
+  errors: string[];
+}
+
+// Per-table comparison of a backup with the live database
+export interface TableDiff {
+  table: string;
+  in_live: boolean;
+  in_backup: boolean;
+  live_rows: number;
+  backup_rows: number;
+  only_in_live: number;
+  only_in_backup: number;
+  changed: number;
+}
+
+// Response from Rust preview_restore command
+export interface RestorePreview {
+  backup_path: string;
+  tables: TableDiff[];
+  live_latest_invoice: string | null;
+  backup_latest_invoice: string | null;
+  schema_upgrades: string[];
+}
+
 // Response from Rust backup_database command
 export interface BackupResult {
   filename: string;
   path: string;
   file_size: number;
   created_at: string;
+  manifest: BackupManifest;
 }
 
 // Backup file info from filesystem
@@ -167,6 +447,30 @@ export interface BackupFileInfo {
   path: string;
   file_size: number;
   modified_at: string;
+  manifest: BackupManifest | null;
+}
+
+// Result of the Rust verify_backup command
+export interface BackupVerification {
+  path: string;
+  ok: boolean;
+  integrity_ok: boolean;
+  integrity_errors: string[];
+  foreign_key_violations: number;
+  missing_tables: string[];
+  missing_columns: string[];
+  checksum: 'match' | 'mismatch' | 'no_manifest';
+  warnings: string[];
+  errors: string[];
+}
+
+// Result of applying (or previewing) the backup retention policy
+export interface RetentionReport {
+  dry_run: boolean;
+  kept: string[];
+  deleted: string[];
+  failed: string[];
+  protected_newest: string | null;
 }
 
 // ============================================
@@ -175,6 +479,12 @@ export interface BackupFileInfo {
 
 export type LowStockMethod = 'reorder_level' | 'percentage' | 'days_supply';
 
+export type BackupFrequency = 'daily' | 'weekly';
+
+export type BackupCompression = 'none' | 'zstd' | 'gzip';
+
+export type BackupMode = 'full' | 'incremental';
+
 export interface AppSettings {
   // Stock Settings
   low_stock_method: LowStockMethod;
@@ -184,7 +494,28 @@ export interface AppSettings {
   // Backup Settings
   auto_backup_enabled: boolean;
   auto_backup_time: string;
+  auto_backup_frequency: BackupFrequency;
+  auto_backup_day: number; // 0 = Sunday, used for weekly backups
   backup_retention_days: number;
+  backup_keep_daily: number;
+  backup_keep_weekly: number;
+  backup_keep_monthly: number;
+  safety_backup_keep_count: number; // newest safety backups always kept
+  safety_backup_max_age_days: number; // older safety backups are kept this long
+  backup_compression: BackupCompression;
+  backup_compression_level: number; // zstd 1-19, gzip 0-9
+  backup_mode: BackupMode;
+  backup_full_interval_days: number; // incremental mode starts a new chain this often
+  backup_mirror_destinations: string; // JSON array of folders every backup is copied to
+  offsite_enabled: boolean;
+  offsite_endpoint: string; // e.g. https://s3.eu-west-1.amazonaws.com or http://192.168.1.10:9000
+  offsite_region: string;
+  offsite_bucket: string;
+  offsite_prefix: string; // folder inside the bucket
+  offsite_access_key: string;
+  // The secret key and passphrase are stored outside the database (set_offsite_credentials)
+  // Returns
+  return_grace_hours: number; // returns can be cancelled or amended this long after creation
   // Store Details (for invoice branding)
   store_name: string;
   store_email: string;